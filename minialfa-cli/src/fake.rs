@@ -0,0 +1,187 @@
+//! Прибор без железа: настоящий контроллер `minialfa-core` и модель камеры с DUT из
+//! [`minialfa_core::physics`], доступные через псевдотерминал.
+//!
+//! Время прибора идет в [`SPEEDUP`] раз быстрее, чтобы удержание и проверка на течь
+//! не занимали минуты. Отладочный вывод контроллера идет в stdout этого процесса.
+
+use std::{
+    convert::Infallible,
+    io::{Read, Write},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crossbeam::channel::Sender;
+use minialfa_core::{
+    controller::{self, SensorResult},
+    klapan::KlapanState,
+    mock, physics,
+    platform::{self, Clock},
+    remote,
+};
+use serialport::{SerialPort, TTYPort};
+
+const SPEEDUP: u32 = 10;
+
+struct FastClock(platform::StdClock);
+
+impl Clock for FastClock {
+    fn now(&self) -> Duration {
+        self.0.now() * SPEEDUP
+    }
+}
+
+type Chamber = physics::Chamber<FastClock>;
+
+struct FakeValve(Arc<Mutex<Chamber>>);
+
+impl platform::Valve for FakeValve {
+    type Error = Infallible;
+
+    fn set_state(&mut self, state: KlapanState) -> Result<(), Infallible> {
+        self.0.lock().unwrap().set_valve(state);
+        Ok(())
+    }
+}
+
+/// Период опроса SCTB, None - опрос остановлен
+struct FakeSensors(Arc<Mutex<Option<Duration>>>);
+
+impl FakeSensors {
+    fn new(chamber: Arc<Mutex<Chamber>>, sensor_channel: Sender<SensorResult>) -> Self {
+        let period = Arc::new(Mutex::new(None::<Duration>));
+        let shared = period.clone();
+        std::thread::spawn(move || loop {
+            let Some(period) = *shared.lock().unwrap() else {
+                std::thread::sleep(Duration::from_millis(10));
+                continue;
+            };
+            std::thread::sleep(period / SPEEDUP);
+
+            let result = {
+                let mut chamber = chamber.lock().unwrap();
+                // DUT во всех гнездах, контроллер берет первые dut_count
+                let f = std::array::from_fn(|socket| chamber.measure_dut_frequency(socket));
+                SensorResult::SctbSensorResult {
+                    f,
+                    p: chamber.measure_pressure(),
+                    t: chamber.measure_temperature(),
+                }
+            };
+            let _ = sensor_channel.try_send(result);
+        });
+        Self(period)
+    }
+}
+
+impl platform::SensorsControl for FakeSensors {
+    type Error = Infallible;
+
+    fn start(&mut self, period: Duration) -> Result<(), Infallible> {
+        self.0.lock().unwrap().replace(period);
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), Infallible> {
+        self.0.lock().unwrap().take();
+        Ok(())
+    }
+
+    fn gauges(&self) -> &[String] {
+        &[]
+    }
+}
+
+/// Запускает прибор на одном конце псевдотерминала, возвращает другой конец
+pub fn spawn() -> anyhow::Result<TTYPort> {
+    let (master, slave) = TTYPort::pair()?;
+    serve(master)?;
+    Ok(slave)
+}
+
+fn serve(port: TTYPort) -> anyhow::Result<()> {
+    let chamber = Arc::new(Mutex::new(Chamber::with_clock(FastClock(
+        platform::StdClock::new(),
+    ))));
+
+    let mut controller = controller::Controller::new(
+        mock::MemoryStorage::default(),
+        FastClock(platform::StdClock::new()),
+    );
+    let mut valve = FakeValve(chamber.clone());
+    let mut sensors = FakeSensors::new(chamber, controller.sensor_chanel());
+
+    // экрана нет, команды отрисовки выбрасываются
+    let display = controller.display_chanel();
+    std::thread::spawn(move || while display.recv().is_ok() {});
+
+    let (commands, replies) = controller.remote_chanel();
+    std::thread::Builder::new()
+        .name("Controller".to_string())
+        .spawn(move || loop {
+            controller.poll(&mut sensors, &mut valve);
+        })?;
+
+    // ошибки разбора команд уходят в порт так же, как ответы контроллера
+    let (errors, parse_errors) = crossbeam::channel::unbounded::<String>();
+    let mut tx = port.try_clone_native()?;
+    std::thread::spawn(move || loop {
+        let line = crossbeam::select! {
+            recv(replies) -> line => line,
+            recv(parse_errors) -> line => line,
+        };
+        let Ok(line) = line else {
+            break;
+        };
+        if tx
+            .write_all(format!("{}\r\n", remote::frame(&line)).as_bytes())
+            .is_err()
+        {
+            break;
+        }
+    });
+
+    let mut rx = port;
+    std::thread::spawn(move || {
+        let mut line = remote::LineBuffer::new();
+        let mut buf = [0u8; 64];
+        loop {
+            let n = match rx.read(&mut buf) {
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+                // другой конец еще не открыт или уже закрыт
+                Err(_) => {
+                    std::thread::sleep(Duration::from_millis(100));
+                    continue;
+                }
+            };
+            for &b in &buf[..n] {
+                match line.push(b) {
+                    Some(Ok(cmd)) => {
+                        let _ = commands.send(cmd);
+                    }
+                    Some(Err(e)) => {
+                        let _ = errors.send(remote::error(&e));
+                    }
+                    None => {}
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Прибор для внешних программ: печатает путь к псевдотерминалу и работает до Ctrl+C
+pub fn run() -> anyhow::Result<()> {
+    let slave = spawn()?;
+    println!(
+        "Fake device at {}",
+        slave.name().unwrap_or_else(|| "<unknown>".to_string())
+    );
+    // конец держится открытым, иначе чтение на приборе вернет ошибку
+    let _slave = slave;
+    loop {
+        std::thread::park();
+    }
+}
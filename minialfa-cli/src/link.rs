@@ -0,0 +1,166 @@
+//! Клиент текстового протокола прибора, см. `minialfa_core::remote`
+
+use std::{
+    io::{Read, Write},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use minialfa_core::remote;
+use serialport::SerialPort;
+
+/// Сколько ждать ответа на команду
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Строка из порта прибора
+pub enum Line {
+    /// Строка в обрамлении [`remote::frame`], кроме ответа: заголовок CSV, CSV или JSON
+    Stream(String),
+    /// Отладочный вывод прошивки: без обрамления или с неверной суммой
+    Debug(String),
+}
+
+/// Ответ `OK key=value ...`
+#[derive(Debug, Default)]
+pub struct Reply(Vec<(String, String)>);
+
+impl Reply {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn pairs(&self) -> &[(String, String)] {
+        &self.0
+    }
+
+    fn parse(args: &str) -> Self {
+        Self(
+            args.split_whitespace()
+                .map(|pair| match pair.split_once('=') {
+                    Some((k, v)) => (k.to_string(), v.to_string()),
+                    None => (pair.to_string(), String::new()),
+                })
+                .collect(),
+        )
+    }
+}
+
+pub struct Link {
+    port: Box<dyn SerialPort>,
+    buf: Vec<u8>,
+}
+
+impl Link {
+    pub fn open(path: &str, baud: u32) -> anyhow::Result<Self> {
+        let port = serialport::new(path, baud)
+            .timeout(Duration::from_millis(100))
+            .open()
+            .with_context(|| format!("Failed to open {path}"))?;
+        Ok(Self::new(port))
+    }
+
+    pub fn new(port: Box<dyn SerialPort>) -> Self {
+        Self {
+            port,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Отправить команду и дождаться ответа. Строки, пришедшие до ответа, отдаются в `sink`.
+    pub fn command(
+        &mut self,
+        command: &str,
+        sink: &mut impl FnMut(Line) -> anyhow::Result<()>,
+    ) -> anyhow::Result<Reply> {
+        self.port.write_all(format!("{command}\n").as_bytes())?;
+        self.port.flush()?;
+
+        let deadline = Instant::now() + COMMAND_TIMEOUT;
+        while let Some(line) = self.read_line(deadline)? {
+            let line = match classify(line) {
+                Line::Stream(line) => line,
+                debug => {
+                    sink(debug)?;
+                    continue;
+                }
+            };
+            if line == "OK" {
+                return Ok(Reply::default());
+            } else if let Some(args) = line.strip_prefix("OK ") {
+                return Ok(Reply::parse(args));
+            } else if let Some(e) = line.strip_prefix("ERR ") {
+                bail!("{command}: {e}");
+            } else {
+                sink(Line::Stream(line))?;
+            }
+        }
+        bail!("{command}: no reply in {COMMAND_TIMEOUT:?}")
+    }
+
+    /// Принимать строки в течение `duration`
+    pub fn listen(
+        &mut self,
+        duration: Duration,
+        sink: &mut impl FnMut(Line) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let deadline = Instant::now() + duration;
+        while let Some(line) = self.read_line(deadline)? {
+            sink(classify(line))?;
+        }
+        Ok(())
+    }
+
+    /// None - до `deadline` строка не пришла
+    fn read_line(&mut self, deadline: Instant) -> anyhow::Result<Option<String>> {
+        loop {
+            if let Some(end) = self.buf.iter().position(|&b| b == b'\n') {
+                let line = self.buf.drain(..=end).collect::<Vec<_>>();
+                let line = String::from_utf8_lossy(&line).trim().to_string();
+                if line.is_empty() {
+                    continue;
+                }
+                return Ok(Some(line));
+            }
+
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+
+            let mut chunk = [0u8; 256];
+            match self.port.read(&mut chunk) {
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+fn classify(line: String) -> Line {
+    match remote::unframe(&line) {
+        Some(line) => Line::Stream(line.to_string()),
+        None => Line::Debug(line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_by_frame() {
+        let stream =
+            |line: &str| matches!(classify(line.to_string()), Line::Stream(l) if l == "12,sctb");
+        assert!(stream(&remote::frame("12,sctb")));
+        // отладочный вывод может начинаться с цифры или `{`
+        assert!(matches!(classify("12,sctb".to_string()), Line::Debug(_)));
+        assert!(matches!(classify("{ debug }".to_string()), Line::Debug(_)));
+        assert!(matches!(
+            classify("$12,sctb*00".to_string()),
+            Line::Debug(_)
+        ));
+    }
+}
@@ -0,0 +1,350 @@
+//! Программа для стенда: проводит испытание DUT через последовательный порт прибора
+//! и сохраняет лог показаний и текстовый отчет.
+//!
+//! ```shell
+//! # испытание на приборе
+//! cargo run -p minialfa-cli --target x86_64-unknown-linux-gnu -- --port /dev/ttyUSB0 --dut SN123
+//! # то же без железа
+//! cargo run -p minialfa-cli --target x86_64-unknown-linux-gnu -- --fake --dut SN123 --mode curve
+//! # прибор без железа на псевдотерминале для других программ
+//! cargo run -p minialfa-cli --target x86_64-unknown-linux-gnu -- fake
+//! ```
+
+mod fake;
+mod link;
+mod report;
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+
+use link::{Line, Link, Reply};
+use report::{Report, Verdict};
+
+const USAGE: &str = "\
+Usage: minialfa-cli (--port <path> [--baud <n>] | --fake) [options]
+       minialfa-cli fake
+
+Options:
+  --dut <id>            DUT name for file names and report (default: dut)
+  --mode <mode>         auto, curve or leak (default: auto)
+  --format <fmt>        log format: csv or json (default: csv)
+  --out <dir>           output directory (default: .)
+  --set <name>=<value>  set a device parameter before the test, may repeat
+  --timeout <s>         test timeout (default: 600)
+  --verbose             print device debug output
+
+Exit code: 0 - pass or no verdict, 2 - any DUT failed, 1 - error";
+
+/// Как часто спрашивать состояние прибора во время испытания
+const POLL_PERIOD: Duration = Duration::from_millis(500);
+
+struct Args {
+    port: Option<String>,
+    baud: u32,
+    fake: bool,
+    dut: String,
+    mode: String,
+    format: String,
+    out: PathBuf,
+    set: Vec<(String, String)>,
+    timeout: Duration,
+    verbose: bool,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> anyhow::Result<Self> {
+        let mut parsed = Self {
+            port: None,
+            baud: 115200,
+            fake: false,
+            dut: "dut".to_string(),
+            mode: "auto".to_string(),
+            format: "csv".to_string(),
+            out: PathBuf::from("."),
+            set: Vec::new(),
+            timeout: Duration::from_secs(600),
+            verbose: false,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .with_context(|| format!("{arg}: value expected"))
+            };
+            match arg.as_str() {
+                "--port" => parsed.port = Some(value()?),
+                "--baud" => parsed.baud = value()?.parse()?,
+                "--fake" => parsed.fake = true,
+                "--dut" => parsed.dut = value()?,
+                "--mode" => parsed.mode = value()?,
+                "--format" => parsed.format = value()?,
+                "--out" => parsed.out = PathBuf::from(value()?),
+                "--set" => {
+                    let v = value()?;
+                    let (name, value) = v
+                        .split_once('=')
+                        .with_context(|| format!("--set {v}: expected name=value"))?;
+                    parsed.set.push((name.to_string(), value.to_string()));
+                }
+                "--timeout" => parsed.timeout = Duration::from_secs(value()?.parse()?),
+                "--verbose" => parsed.verbose = true,
+                _ => bail!("unknown argument {arg}\n\n{USAGE}"),
+            }
+        }
+
+        if !matches!(parsed.mode.as_str(), "auto" | "curve" | "leak") {
+            bail!("--mode {}: expected auto, curve or leak", parsed.mode);
+        }
+        if !matches!(parsed.format.as_str(), "csv" | "json") {
+            bail!("--format {}: expected csv or json", parsed.format);
+        }
+        if parsed.port.is_some() == parsed.fake {
+            bail!("either --port or --fake is required\n\n{USAGE}");
+        }
+        Ok(parsed)
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+    let res = match args.peek().map(String::as_str) {
+        Some("fake") => fake::run().map(|_| Verdict::None),
+        Some("--help" | "-h") => {
+            println!("{USAGE}");
+            return;
+        }
+        _ => Args::parse(args).and_then(|args| run(&args)),
+    };
+
+    match res {
+        Ok(Verdict::Fail(_)) => std::process::exit(2),
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run(args: &Args) -> anyhow::Result<Verdict> {
+    let mut link = match &args.port {
+        Some(port) => Link::open(port, args.baud)?,
+        None => Link::new(Box::new(fake::spawn()?)),
+    };
+    session(&mut link, args)
+}
+
+/// Испытание, после которого параметры из `--set` и формат потока возвращаются к прежним
+/// значениям, даже если оно не удалось
+fn session(link: &mut Link, args: &Args) -> anyhow::Result<Verdict> {
+    let mut print_debug = debug_printer(args.verbose);
+
+    // прибор мог остаться на экране результата от прошлого испытания
+    link.command("ABORT", &mut print_debug)
+        .context("Device does not respond")?;
+    let saved = link.command("GET", &mut print_debug)?;
+    let mut touched = vec!["stream"];
+    for (name, _) in &args.set {
+        if !touched.contains(&name.as_str()) {
+            touched.push(name);
+        }
+    }
+
+    let verdict = test(link, args);
+    let restored = restore(link, &saved, &touched, &mut print_debug)
+        .context("Failed to restore device parameters");
+    match (verdict, restored) {
+        (Err(e), Err(restore_error)) => {
+            eprintln!("{restore_error:#}");
+            Err(e)
+        }
+        (verdict, restored) => restored.and(verdict),
+    }
+}
+
+/// Остановить испытание и вернуть параметры `names` к значениям из `saved`
+fn restore(
+    link: &mut Link,
+    saved: &Reply,
+    names: &[&str],
+    sink: &mut impl FnMut(Line) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    link.command("ABORT", sink)?;
+    for name in names {
+        // неизвестный прибору параметр и не был записан
+        if let Some(value) = saved.get(name) {
+            link.command(&format!("SET {name} {value}"), sink)?;
+        }
+    }
+    Ok(())
+}
+
+fn debug_printer(verbose: bool) -> impl FnMut(Line) -> anyhow::Result<()> {
+    move |line| {
+        if let Line::Debug(line) = line {
+            if verbose {
+                println!("device: {line}");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn test(link: &mut Link, args: &Args) -> anyhow::Result<Verdict> {
+    let mut print_debug = debug_printer(args.verbose);
+    for (name, value) in &args.set {
+        link.command(&format!("SET {name} {value}"), &mut print_debug)?;
+    }
+    let parameters = link.command("GET", &mut print_debug)?;
+
+    let mut report = Report::create(&args.out, &args.dut, &args.format)?;
+    let mut sink = |line: Line| match line {
+        Line::Stream(line) => report.log(&line),
+        debug => print_debug(debug),
+    };
+
+    link.command(&format!("SET stream {}", args.format), &mut sink)?;
+    link.command(&format!("START {}", args.mode), &mut sink)?;
+    println!("{}: {} test started", args.dut, args.mode);
+
+    let started = Instant::now();
+    loop {
+        link.listen(POLL_PERIOD, &mut sink)?;
+        let status = link.command("READ", &mut sink)?;
+        match status.get("state") {
+            Some("result") => break,
+            Some("measuring") if started.elapsed() < args.timeout => {}
+            Some("measuring") => bail!("timeout {:?}", args.timeout),
+            state => bail!("test aborted on device, state {state:?}"),
+        }
+    }
+    // в ответе по DUT 1 - сколько каналов испытано
+    let first = link.command("RESULT", &mut sink)?;
+    let channels = first
+        .get("channels")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(1);
+    let mut results = vec![first];
+    for n in 2..=channels {
+        results.push(link.command(&format!("RESULT {n}"), &mut sink)?);
+    }
+
+    let verdicts = results.iter().map(report::verdict).collect::<Vec<_>>();
+    let (log, report) = report.finish(&args.mode, &parameters, &results)?;
+    for (i, verdict) in verdicts.iter().enumerate() {
+        match channels {
+            1 => println!("{}: {}", args.dut, verdict.text()),
+            _ => println!("{} DUT {}: {}", args.dut, i + 1, verdict.text()),
+        }
+    }
+    println!("Log:    {}", log.display());
+    println!("Report: {}", report.display());
+
+    Ok(report::overall(&verdicts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> anyhow::Result<Args> {
+        Args::parse(line.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn parse_args() {
+        let parsed =
+            args("--fake --dut SN1 --mode curve --set threshold=0.5 --timeout 30").unwrap();
+        assert!(parsed.fake);
+        assert_eq!(parsed.dut, "SN1");
+        assert_eq!(parsed.mode, "curve");
+        assert_eq!(parsed.set, [("threshold".to_string(), "0.5".to_string())]);
+        assert_eq!(parsed.timeout, Duration::from_secs(30));
+
+        assert!(args("--dut SN1").is_err());
+        assert!(args("--fake --port /dev/ttyUSB0").is_err());
+        assert!(args("--fake --mode manual").is_err());
+        assert!(args("--fake --set threshold").is_err());
+        assert!(args("--fake --dut").is_err());
+    }
+
+    /// Испытание на приборе без железа, файлы - во временном каталоге
+    fn fake_test(name: &str, set: &str) -> (Verdict, String) {
+        let out = std::env::temp_dir().join(format!("minialfa-cli-{name}-{}", std::process::id()));
+        let args = args(&format!(
+            "--fake --dut {name} --out {} --timeout 60 {set}",
+            out.display()
+        ))
+        .unwrap();
+        let verdict = run(&args).unwrap();
+
+        let mut report = String::new();
+        for entry in std::fs::read_dir(&out).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "txt") {
+                report = std::fs::read_to_string(path).unwrap();
+            } else {
+                let log = std::fs::read_to_string(path).unwrap();
+                assert!(log.starts_with(minialfa_core::stream::CSV_HEADER));
+                assert!(log.lines().count() > 10);
+            }
+        }
+        std::fs::remove_dir_all(out).unwrap();
+        (verdict, report)
+    }
+
+    #[test]
+    fn fake_device_pass() {
+        let (verdict, report) = fake_test("pass", "--set tolerances_enabled=1");
+        assert_eq!(verdict, Verdict::Pass);
+        assert!(report.contains("DUT:       pass"));
+        assert!(report.contains("sensivity"));
+    }
+
+    #[test]
+    fn parameters_restored_after_test() {
+        let out = std::env::temp_dir().join(format!("minialfa-cli-restore-{}", std::process::id()));
+        let mut link = Link::new(Box::new(fake::spawn().unwrap()));
+        let mut sink = debug_printer(false);
+        let before = link.command("GET", &mut sink).unwrap();
+
+        // испытание не дождалось результата
+        let args = args(&format!(
+            "--fake --out {} --timeout 0 --set threshold=2.5 --set wait_time_s=3",
+            out.display()
+        ))
+        .unwrap();
+        assert!(session(&mut link, &args).is_err());
+
+        let after = link.command("GET", &mut sink).unwrap();
+        assert_eq!(after.pairs(), before.pairs());
+        assert_eq!(
+            link.command("READ", &mut sink).unwrap().get("state"),
+            Some("title")
+        );
+        std::fs::remove_dir_all(out).unwrap();
+    }
+
+    #[test]
+    fn fake_device_second_dut_fails() {
+        // DUT во втором гнезде с F0 выше допуска F вакуума
+        let (verdict, report) = fake_test("duts", "--set tolerances_enabled=1 --set dut_count=2");
+        assert_eq!(verdict, Verdict::Fail("f_vac".to_string()));
+        assert!(report.contains("Результат DUT 1"));
+        assert!(report.contains("Вердикт DUT 1: ГОДЕН"));
+        assert!(report.contains("Вердикт DUT 2: БРАК (f_vac)"));
+    }
+
+    #[test]
+    fn fake_device_fail() {
+        let (verdict, _) = fake_test(
+            "fail",
+            "--set tolerances_enabled=1 --set tol_sensivity_min=4.5",
+        );
+        assert_eq!(verdict, Verdict::Fail("sensivity".to_string()));
+    }
+}
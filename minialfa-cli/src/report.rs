@@ -0,0 +1,156 @@
+//! Файлы испытания DUT: лог показаний (CSV/JSON) и текстовый отчет
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::link::Reply;
+
+pub struct Report {
+    dut: String,
+    started: u64,
+    log_path: PathBuf,
+    log: BufWriter<File>,
+    report_path: PathBuf,
+}
+
+impl Report {
+    /// `<dut>_<unix time>.{csv,jsonl}` и `<dut>_<unix time>.txt` в `dir`
+    pub fn create(dir: &Path, dut: &str, format: &str) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+
+        let started = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+        let name = format!("{dut}_{started}");
+        let log_path = dir.join(format!(
+            "{name}.{}",
+            if format == "json" { "jsonl" } else { "csv" }
+        ));
+
+        Ok(Self {
+            dut: dut.to_string(),
+            started,
+            log: BufWriter::new(File::create(&log_path)?),
+            log_path,
+            report_path: dir.join(format!("{name}.txt")),
+        })
+    }
+
+    pub fn log(&mut self, line: &str) -> anyhow::Result<()> {
+        writeln!(self.log, "{line}")?;
+        Ok(())
+    }
+
+    /// Пишет отчет по результатам всех DUT, возвращает пути лога и отчета
+    pub fn finish(
+        mut self,
+        mode: &str,
+        parameters: &Reply,
+        results: &[Reply],
+    ) -> anyhow::Result<(PathBuf, PathBuf)> {
+        self.log.flush()?;
+
+        let mut r = BufWriter::new(File::create(&self.report_path)?);
+        writeln!(r, "Мини-Альфа: отчет об испытании")?;
+        writeln!(r, "DUT:       {}", self.dut)?;
+        writeln!(r, "Начало:    {} (unix)", self.started)?;
+        writeln!(r, "Режим:     {mode}")?;
+        writeln!(r, "Лог:       {}", self.log_path.display())?;
+
+        writeln!(r, "\nПараметры")?;
+        for (k, v) in parameters.pairs().iter().filter(|(k, _)| k != "stream") {
+            writeln!(r, "  {k:<20} {v}")?;
+        }
+
+        let several = results.len() > 1;
+        let mut verdicts = Vec::new();
+        for (i, result) in results.iter().enumerate() {
+            let dut = if several {
+                format!(" DUT {}", i + 1)
+            } else {
+                String::new()
+            };
+            writeln!(r, "\nРезультат{dut}")?;
+            // точки кривой печатаются таблицей ниже
+            let in_table = |k: &str| mode == "curve" && matches!(k, "p" | "f" | "t");
+            for (k, v) in result.pairs().iter().filter(|(k, _)| !in_table(k)) {
+                writeln!(r, "  {k:<20} {v}")?;
+            }
+
+            if mode == "curve" {
+                let column = |key| {
+                    result
+                        .get(key)
+                        .map(|v| v.split(',').map(str::to_string).collect::<Vec<_>>())
+                        .unwrap_or_default()
+                };
+                let (p, f, t) = (column("p"), column("f"), column("t"));
+                writeln!(r, "\nКривая{dut}")?;
+                writeln!(r, "  {:>12} {:>12} {:>8}", "P, mmHg", "F, Hz", "T, *C")?;
+                for (i, p) in p.iter().enumerate() {
+                    writeln!(
+                        r,
+                        "  {:>12} {:>12} {:>8}",
+                        p,
+                        f.get(i).map_or("", String::as_str),
+                        t.get(i).map_or("", String::as_str)
+                    )?;
+                }
+            }
+
+            let verdict = verdict(result);
+            if several {
+                writeln!(r, "\nВердикт{dut}: {}", verdict.text())?;
+            }
+            verdicts.push(verdict);
+        }
+
+        writeln!(r, "\nВердикт:   {}", overall(&verdicts).text())?;
+        r.flush()?;
+
+        Ok((self.log_path, self.report_path))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+    Pass,
+    Fail(String),
+    /// Допуски выключены или режим без вердикта
+    None,
+}
+
+impl Verdict {
+    pub fn text(&self) -> String {
+        match self {
+            Verdict::Pass => "ГОДЕН".to_string(),
+            Verdict::Fail(criterion) => format!("БРАК ({criterion})"),
+            Verdict::None => "нет (допуски не заданы)".to_string(),
+        }
+    }
+}
+
+pub fn verdict(result: &Reply) -> Verdict {
+    match (result.get("verdict"), result.get("passed")) {
+        (Some("pass"), _) | (_, Some("1")) => Verdict::Pass,
+        (Some(v), _) if v.starts_with("fail") => {
+            Verdict::Fail(v.trim_start_matches("fail:").to_string())
+        }
+        (_, Some("0")) => Verdict::Fail("leak_rate".to_string()),
+        _ => Verdict::None,
+    }
+}
+
+/// Брак, если бракован хоть один DUT, иначе годен, если годен хоть один
+pub fn overall(verdicts: &[Verdict]) -> Verdict {
+    verdicts
+        .iter()
+        .find(|v| matches!(v, Verdict::Fail(_)))
+        .or_else(|| verdicts.iter().find(|v| **v == Verdict::Pass))
+        .cloned()
+        .unwrap_or(Verdict::None)
+}
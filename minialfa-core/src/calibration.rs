@@ -0,0 +1,238 @@
+//! Расчет калибровки испытуемого датчика SCTB по точкам (P, F) измерения и запись её в датчик.
+//!
+//! Калибровка - полином P(F - F0) из блока [`CALIBRATION_ADDR`](crate::i2c_sensor::CALIBRATION_ADDR),
+//! F0 - частота в самой глубокой точке откачки.
+
+use crate::i2c_sensor::{Calibration, DecodeError, I2CSensor, ReadError, CALIBRATION_COEFFS};
+use crate::linear_regression::polynomial_regression;
+
+/// Наибольшая степень полинома, сколько коэффициентов помещается в датчик
+pub const MAX_DEGREE: usize = CALIBRATION_COEFFS - 1;
+
+/// Рассчитанная калибровка и насколько она повторяет точки измерения
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CalibrationFit {
+    pub calibration: Calibration,
+    /// Наибольшее отклонение P по калибровке от измеренного, mmHg
+    pub max_error: f32,
+    pub points: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WriteError {
+    /// Драйвер не умеет писать в испытуемый датчик
+    Unsupported,
+    /// Ошибка шины при записи или обратном чтении
+    Bus,
+    /// Обратное чтение не разобрано
+    Decode(DecodeError),
+    /// Прочитано не то, что записано
+    Mismatch(Calibration),
+}
+
+impl WriteError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WriteError::Unsupported => "unsupported",
+            WriteError::Bus => "bus",
+            WriteError::Decode(_) => "decode",
+            WriteError::Mismatch(_) => "mismatch",
+        }
+    }
+}
+
+/// Чем закончилась калибровка после измерения
+#[derive(Clone, Debug, PartialEq)]
+pub enum CalibrationOutcome {
+    /// Точек мало или все на одной частоте
+    NoFit,
+    /// Записана и прочитана обратно без расхождений
+    Written(CalibrationFit),
+    Failed {
+        fit: CalibrationFit,
+        error: WriteError,
+    },
+}
+
+impl CalibrationOutcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CalibrationOutcome::NoFit => "no_fit",
+            CalibrationOutcome::Written(_) => "written",
+            CalibrationOutcome::Failed { error, .. } => error.name(),
+        }
+    }
+
+    pub fn fit(&self) -> Option<&CalibrationFit> {
+        match self {
+            CalibrationOutcome::NoFit => None,
+            CalibrationOutcome::Written(fit) | CalibrationOutcome::Failed { fit, .. } => Some(fit),
+        }
+    }
+}
+
+/// МНК-полином P(F - F0) по точкам (P, F) степени до [`MAX_DEGREE`], не выше числа точек - 1.
+/// None, если точек меньше двух или частота не менялась.
+pub fn fit(data: &[(f32, f32)]) -> Option<CalibrationFit> {
+    let points = data
+        .iter()
+        .copied()
+        .filter(|p| p.0.is_finite() && p.1.is_finite())
+        .collect::<Vec<_>>();
+    let f0 = points.iter().min_by(|a, b| a.0.total_cmp(&b.0))?.1;
+
+    // (F - F0) в сотнях и тысячах Hz, без нормировки степени до 6-й в нормальных
+    // уравнениях теряют точность
+    let scale = points.iter().map(|p| (p.1 - f0).abs()).fold(0.0, f32::max);
+    if scale == 0.0 {
+        return None;
+    }
+    let scaled = points
+        .iter()
+        .map(|&(p, f)| ((f - f0) / scale, p))
+        .collect::<Vec<_>>();
+    let poly = polynomial_regression(&scaled, MAX_DEGREE.min(points.len() - 1))?;
+
+    // неиспользуемые старшие коэффициенты - 0, NaN в датчике значит "не записан"
+    let mut coeffs = [0.0; CALIBRATION_COEFFS];
+    for (i, (c, k)) in coeffs.iter_mut().zip(poly).enumerate() {
+        *c = k / scale.powi(i as i32);
+    }
+    let calibration = Calibration { f0, coeffs };
+
+    let max_error = points
+        .iter()
+        .map(|&(p, f)| (calibration.pressure(f) - p).abs())
+        .fold(0.0, f32::max);
+    Some(CalibrationFit {
+        calibration,
+        max_error,
+        points: points.len(),
+    })
+}
+
+/// Запись калибровки и проверка обратным чтением. Вызывается при остановленном опросе.
+pub fn program<I2C, E>(
+    sensor: &mut I2CSensor,
+    i2c_bus: &mut I2C,
+    calibration: &Calibration,
+) -> Result<(), WriteError>
+where
+    I2C: embedded_hal::blocking::i2c::WriteRead<Error = E>
+        + embedded_hal::blocking::i2c::Write<Error = E>,
+    E: std::fmt::Debug,
+{
+    let address = sensor.address();
+    let map_err = |e: ReadError<E>| {
+        println!("Failed to write calibration to I2C sensor at {address}: {e:?}");
+        match e {
+            ReadError::Bus(_) => WriteError::Bus,
+            ReadError::Decode(e) => WriteError::Decode(e),
+        }
+    };
+
+    sensor
+        .write_calibration(i2c_bus, calibration)
+        .map_err(map_err)?;
+    let written = sensor.read_calibration(i2c_bus).map_err(map_err)?;
+    if written != *calibration {
+        return Err(WriteError::Mismatch(written));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i2c_sensor::{SensorInfo, LEGACY_VERSION};
+    use crate::mock::MockI2c;
+
+    const OLD: Calibration = Calibration {
+        f0: 30000.0,
+        coeffs: [0.0, 0.25, 0.0, 0.0],
+    };
+    const NEW: Calibration = Calibration {
+        f0: 30010.0,
+        coeffs: [0.5, 0.2, 1e-4, 0.0],
+    };
+
+    fn bus(version: u8) -> MockI2c {
+        let info = SensorInfo {
+            serial: 1,
+            firmware: (1, 4),
+            period_ms: 100,
+        };
+        let mut bus = MockI2c::default();
+        bus.add_sctb(11, version, [f32::NAN, 25.0, 30000.0, f32::NAN], info, OLD);
+        bus
+    }
+
+    #[test]
+    fn fit_reproduces_polynomial() {
+        // P = 0.5 + 0.2*x + 1e-4*x^2, x = F - 30010, F от 30010 до 31000
+        let data = (0..=33)
+            .map(|i| {
+                let x = 30.0 * i as f32;
+                (0.5 + 0.2 * x + 1e-4 * x * x, 30010.0 + x)
+            })
+            .collect::<Vec<_>>();
+        let fit = fit(&data).unwrap();
+        assert_eq!(fit.points, data.len());
+        assert_eq!(fit.calibration.f0, 30010.0);
+        for (c, expected) in fit.calibration.coeffs.iter().zip(NEW.coeffs) {
+            assert!((c - expected).abs() < 1e-3, "{:?}", fit.calibration.coeffs);
+        }
+        assert!(fit.max_error < 1e-2, "{}", fit.max_error);
+    }
+
+    #[test]
+    fn fit_by_two_points_is_a_line() {
+        let fit = fit(&[(760.0, 31520.0), (f32::NAN, 30500.0), (0.0, 30000.0)]).unwrap();
+        assert_eq!(fit.points, 2);
+        assert_eq!(fit.calibration.f0, 30000.0);
+        assert_eq!(fit.calibration.coeffs[2..], [0.0, 0.0]);
+        assert!((fit.calibration.coeffs[1] - 0.5).abs() < 1e-5);
+        assert!(fit.max_error < 1e-3);
+    }
+
+    #[test]
+    fn no_fit_without_frequency_change() {
+        assert_eq!(fit(&[]), None);
+        assert_eq!(fit(&[(1.0, 30000.0)]), None);
+        assert_eq!(fit(&[(760.0, 30000.0), (1.0, 30000.0)]), None);
+        assert_eq!(fit(&[(760.0, f32::NAN), (1.0, 30000.0)]), None);
+    }
+
+    #[test]
+    fn program_verifies_read_back() {
+        let mut bus = bus(2);
+        let mut sensor = I2CSensor::new(11);
+        assert_eq!(program(&mut sensor, &mut bus, &NEW), Ok(()));
+        assert_eq!(sensor.read_calibration(&mut bus).unwrap(), NEW);
+    }
+
+    #[test]
+    fn read_back_mismatch() {
+        // датчик подтверждает запись, но блок не меняет
+        let mut bus = bus(2);
+        bus.read_only.push(11);
+        let mut sensor = I2CSensor::new(11);
+        assert_eq!(
+            program(&mut sensor, &mut bus, &NEW),
+            Err(WriteError::Mismatch(OLD))
+        );
+    }
+
+    #[test]
+    fn program_errors() {
+        let mut bus = bus(LEGACY_VERSION);
+        let mut sensor = I2CSensor::new(11);
+        assert_eq!(
+            program(&mut sensor, &mut bus, &NEW),
+            Err(WriteError::Decode(DecodeError::Version(LEGACY_VERSION)))
+        );
+
+        let mut sensor = I2CSensor::new(12);
+        assert_eq!(program(&mut sensor, &mut bus, &NEW), Err(WriteError::Bus));
+    }
+}
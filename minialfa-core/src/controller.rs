@@ -0,0 +1,2987 @@
+// Принимает текущие показания
+// Принимает команды с энкодера
+// стейт-машина состояния
+// Отдает команды монитору на отрисовку, если что-то изметилось
+
+use std::{collections::VecDeque, time::Duration};
+
+use crossbeam::channel::{self, Receiver, Sender};
+use num_derive::FromPrimitive;
+
+use crate::calibration::{self, CalibrationFit, CalibrationOutcome};
+use crate::gauge::{
+    self, GaugeCommand, GaugeError, GaugeReading, GaugeReply, Pressure, PressureUnit,
+};
+use crate::i2c_sensor::{SctbDevice, SctbInfo, SCAN_ADDRESSES};
+use crate::klapan::KlapanState;
+use crate::linear_regression::{
+    linear_regression, polynomial_regression, robust_linear_regression, RegressionFit,
+};
+use crate::modbus::{self, Coil, Exception, Request, Response};
+use crate::platform::{Clock, SensorsControl, SettingsStorage, Valve};
+use crate::profile::{self, Profiles};
+use crate::remote::{
+    self, DeviceState, DutResult, RemoteCommand, RemoteError, RunMode, RunResult, Status,
+};
+use crate::stream::{self, Phase, PressureSource, StreamFormat, StreamSample};
+use crate::verdict::{Measured, Range, Tolerances, Verdict};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EncoderCommand {
+    Increment,
+    Decrement,
+    Push,
+    Pull,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum SensorResult {
+    SctbSensorResult {
+        /// Частоты DUT по каналам, неназначенные и не ответившие - NaN
+        f: [f32; MAX_DUTS],
+        p: f32,
+        t: f32,
+    },
+    /// Отсчет образцового датчика номер `gauge` в [`SensorsControl::gauges`]
+    GaugeResult {
+        gauge: usize,
+        reading: GaugeReading,
+    },
+}
+
+pub enum DisplayCommand {
+    TitleScreen {
+        option: &'static str,
+        selected: bool,
+        /// Сколько назначенных DUT есть на шине и сколько их всего, None - шина не опрашивается
+        dut: Option<(usize, usize)>,
+    },
+    SetupMenu {
+        values: Parameters,
+        selected: SelectedParameter,
+        precision: Precission,
+        /// Имя редактируемого профиля и позиция курсора в нем
+        profile: String,
+        cursor: usize,
+        /// Адреса найденных датчиков SCTB, None - шина не опрашивается
+        sctb: Option<Vec<u8>>,
+    },
+    ProfileSelect {
+        name: String,
+        index: usize,
+        active: bool,
+    },
+    Measure {
+        f: Option<f32>,
+        p: Option<f32>,
+        threashold: f32,
+        wait_time: Option<Duration>,
+        drift: Option<Drift>,
+    },
+    Result {
+        f: f32,
+        p: f32,
+        t: Option<f32>,
+        threashold: f32,
+        sensivity: f32,
+        fit: Option<RegressionFit>,
+        hold: Option<HoldResult>,
+        /// None, если допуски выключены
+        verdict: Option<Verdict>,
+        /// Номер DUT с 1, None - DUT один
+        dut: Option<usize>,
+    },
+    CurveResult {
+        result: CurveResult,
+        page: usize,
+        dut: Option<usize>,
+    },
+    LeakResult {
+        result: LeakResult,
+    },
+    GaugeSetup {
+        /// Идентификатор выбранного датчика, None - датчиков нет
+        gauge: Option<String>,
+        selected: GaugeMenuItem,
+        /// Значение выбранного пункта редактируется или ждет подтверждения
+        editing: bool,
+        /// Давление юстировки атмосферы, mbar
+        atmosphere: f32,
+        /// Прочитанные или заданные точки переключения, mbar
+        switch_points: [Option<f32>; gauge::SWITCH_POINTS as usize],
+        /// Результат последней команды, закрывается любым действием
+        message: Vec<String>,
+    },
+    Diagnostics {
+        /// None - испытуемый датчик не ответил
+        dut: Option<SctbInfo>,
+        /// Номер DUT с 1, None - DUT один
+        channel: Option<usize>,
+    },
+    /// Первая страница результата, если после измерения писалась калибровка DUT
+    CalibrationResult {
+        outcome: CalibrationOutcome,
+        dut: Option<usize>,
+    },
+}
+
+/// Результат проверки на течь
+#[derive(Clone, Copy, Debug)]
+pub struct LeakResult {
+    /// Давление в момент отсечки и в конце, mmHg
+    pub p_start: f32,
+    pub p_end: f32,
+    /// Скорость роста давления, mbar/s
+    pub dp_dt: f32,
+    /// Течь, mbar·l/s
+    pub leak_rate: f32,
+    pub limit: f32,
+    pub passed: bool,
+    pub duration: Duration,
+    pub fit: Option<RegressionFit>,
+}
+
+/// Скорость дрейфа за окно стабилизации
+#[derive(Clone, Copy, Debug)]
+pub struct Drift {
+    /// Hz/s
+    pub df_dt: f32,
+    /// mmHg/s
+    pub dp_dt: f32,
+}
+
+impl Drift {
+    pub fn is_settled(&self, parameters: &Parameters) -> bool {
+        self.df_dt.abs() <= parameters.settle_df_max && self.dp_dt.abs() <= parameters.settle_dp_max
+    }
+}
+
+/// Чем закончилось удержание
+#[derive(Clone, Copy, Debug)]
+pub struct HoldResult {
+    /// true - частота и давление стабилизировались, false - истекло wait_time_s
+    pub settled: bool,
+    pub duration: Duration,
+}
+
+/// Точка кривой, записанная по окончании удержания на уставке
+#[derive(Clone, Copy, Debug)]
+pub struct CurvePoint {
+    pub p: f32,
+    pub f: f32,
+    pub t: f32,
+    pub hold: Option<HoldResult>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CurveResult {
+    /// В порядке откачки, от большего давления к меньшему
+    pub points: Vec<CurvePoint>,
+    /// Чувствительность между соседними точками, Hz/mmHg
+    pub segments: Vec<f32>,
+    /// Коэффициенты полинома F(P) = c0 + c1 * P + c2 * P^2 ...
+    pub poly: Option<Vec<f32>>,
+}
+
+impl CurveResult {
+    pub const PAGES: usize = 3;
+
+    pub fn new(points: Vec<CurvePoint>) -> Self {
+        let segments = points
+            .windows(2)
+            .map(|w| sensivity((w[0].p, w[0].f), (w[1].p, w[1].f)))
+            .collect();
+
+        let data = points.iter().map(|p| (p.p, p.f)).collect::<Vec<_>>();
+        let poly =
+            polynomial_regression(&data, CURVE_POLY_DEGREE.min(data.len().saturating_sub(1)));
+
+        Self {
+            points,
+            segments,
+            poly,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, FromPrimitive)]
+pub enum Precission {
+    #[default]
+    C1,
+    C01,
+    C001,
+}
+
+impl Precission {
+    pub fn value(&self) -> usize {
+        match self {
+            Precission::C1 => 0,
+            Precission::C01 => 1,
+            Precission::C001 => 2,
+        }
+    }
+
+    fn increment_value(current: f32) -> f32 {
+        match current.into() {
+            Precission::C1 => 1.0,
+            Precission::C01 => 0.1,
+            Precission::C001 => 0.01,
+        }
+    }
+
+    fn decrement_value(current: f32) -> f32 {
+        let inc = Self::increment_value(current);
+        if current - inc < inc {
+            inc / 10.0
+        } else {
+            inc
+        }
+    }
+}
+
+impl From<f32> for Precission {
+    fn from(v: f32) -> Self {
+        if (0.0..=0.09).contains(&v) {
+            Precission::C001
+        } else if v <= 0.9 {
+            Precission::C01
+        } else {
+            Precission::C1
+        }
+    }
+}
+
+pub const MAX_CURVE_POINTS: usize = 5;
+/// Сколько испытуемых датчиков опрашивается за одну откачку
+pub const MAX_DUTS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Parameters {
+    pub threshold: f32,
+    pub wait_time_s: u32,
+    pub update_period_ms: u32,
+    pub try_use_alternative_sensor: bool,
+    pub curve_points_count: u32,
+    pub curve_points: [f32; MAX_CURVE_POINTS],
+    /// Завершать удержание досрочно при стабилизации F и P
+    pub settle_enabled: bool,
+    pub settle_window_s: u32,
+    /// Hz/s
+    pub settle_df_max: f32,
+    /// mmHg/s
+    pub settle_dp_max: f32,
+    /// Объем камеры с DUT, мл
+    pub chamber_volume_ml: u32,
+    /// Время измерения роста давления после отсечки
+    pub leak_time_s: u32,
+    /// Допустимая течь, mbar·l/s
+    pub leak_limit: f32,
+    /// Выносить вердикт ГОДЕН/БРАК по допускам
+    pub tolerances_enabled: bool,
+    pub tolerances: Tolerances,
+    /// Формат потока показаний в консольный UART во время измерения
+    pub stream_format: StreamFormat,
+    /// Рассчитывать калибровку DUT после измерения и записывать её в датчик
+    pub write_calibration: bool,
+    /// Адреса I2C образцового и испытуемых датчиков SCTB
+    pub sctb_reference: u32,
+    pub dut_count: u32,
+    pub sctb_duts: [u32; MAX_DUTS],
+    /// Запускать "Авто", когда на шине появляются все DUT
+    pub auto_start: bool,
+}
+
+#[derive(PartialEq, Clone, Copy, FromPrimitive, Default, Debug)]
+pub enum SelectedParameter {
+    #[default]
+    ProfileName,
+    Threshold,
+    UpdatePeriodMs,
+    PSensorSelect,
+    WaitTimeS,
+    SettleEnabled,
+    SettleWindowS,
+    SettleDfMax,
+    SettleDpMax,
+    CurvePointsCount,
+    CurvePoint1,
+    CurvePoint2,
+    CurvePoint3,
+    CurvePoint4,
+    CurvePoint5,
+    ChamberVolumeMl,
+    LeakTimeS,
+    LeakLimit,
+    TolerancesEnabled,
+    SensivityMin,
+    SensivityMax,
+    FAtmMin,
+    FAtmMax,
+    FVacMin,
+    FVacMax,
+    TMin,
+    TMax,
+    StreamFormat,
+    WriteCalibration,
+    SctbReference,
+    DutCount,
+    SctbDut1,
+    SctbDut2,
+    SctbDut3,
+    SctbDut4,
+    AutoStart,
+    Diagnostics,
+    ReferenceGauge,
+    SaveAndExit,
+}
+
+impl SelectedParameter {
+    pub fn curve_point(index: usize) -> Self {
+        num::FromPrimitive::from_usize(SelectedParameter::CurvePoint1 as usize + index)
+            .unwrap_or(SelectedParameter::SaveAndExit)
+    }
+
+    pub fn curve_point_index(&self) -> Option<usize> {
+        (SelectedParameter::CurvePoint1 as usize..=SelectedParameter::CurvePoint5 as usize)
+            .contains(&(*self as usize))
+            .then(|| *self as usize - SelectedParameter::CurvePoint1 as usize)
+    }
+
+    pub fn sctb_dut(index: usize) -> Self {
+        num::FromPrimitive::from_usize(SelectedParameter::SctbDut1 as usize + index)
+            .unwrap_or(SelectedParameter::SaveAndExit)
+    }
+
+    pub fn sctb_dut_index(&self) -> Option<usize> {
+        (SelectedParameter::SctbDut1 as usize..=SelectedParameter::SctbDut4 as usize)
+            .contains(&(*self as usize))
+            .then(|| *self as usize - SelectedParameter::SctbDut1 as usize)
+    }
+}
+
+/// Пункты подменю "Образцовый датчик"
+#[derive(PartialEq, Clone, Copy, FromPrimitive, Debug)]
+pub enum GaugeMenuItem {
+    /// Выбор датчика из найденных
+    Gauge,
+    Info,
+    AdjustZero,
+    AdjustAtmosphere,
+    SwitchPoint1,
+    SwitchPoint2,
+    Back,
+    Count,
+}
+
+impl GaugeMenuItem {
+    /// Номер точки переключения 1..=[`gauge::SWITCH_POINTS`]
+    pub fn switch_point(&self) -> Option<u8> {
+        match self {
+            GaugeMenuItem::SwitchPoint1 => Some(1),
+            GaugeMenuItem::SwitchPoint2 => Some(2),
+            _ => None,
+        }
+    }
+}
+
+/// Состояние подменю "Образцовый датчик"
+struct GaugeMenu {
+    gauge: usize,
+    item: GaugeMenuItem,
+    editing: bool,
+    atmosphere: f32,
+    switch_points: [Option<f32>; gauge::SWITCH_POINTS as usize],
+    message: Vec<String>,
+}
+
+impl Default for GaugeMenu {
+    fn default() -> Self {
+        Self {
+            gauge: 0,
+            item: GaugeMenuItem::Gauge,
+            editing: false,
+            atmosphere: DEFAULT_ATMOSPHERE_MBAR,
+            switch_points: [None; gauge::SWITCH_POINTS as usize],
+            message: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Title,
+    ProfileSelect,
+    Setup,
+    GaugeSetup,
+    Diagnostics,
+    Measuring,
+    Result,
+}
+
+#[derive(PartialEq, Clone, Copy, FromPrimitive)]
+enum TitleOptions {
+    Auto = 0,
+    Manual = 1,
+    Curve = 2,
+    Leak = 3,
+    Profile = 4,
+    Setup = 5,
+    Count,
+}
+
+static TITLE_OPTIONS: [&str; 6] = ["Авто", "Ручной", "Кривая", "Течь", "Профиль", "Настройки"];
+
+pub(crate) const MIN_PREASURE: f32 = 0.01;
+pub(crate) const MAX_PRESSURE: f32 = 800.0;
+
+pub(crate) const MAX_WAIT_TIME_S: u32 = 5 * 60; //5 min
+
+/// Юстировка атмосферы образцового датчика, mbar
+const DEFAULT_ATMOSPHERE_MBAR: f32 = 1013.0;
+const ATMOSPHERE_LIMITS: Range = Range {
+    min: 500.0,
+    max: 1200.0,
+};
+/// Точки переключения образцового датчика, mbar
+const SWITCH_POINT_LIMITS: Range = Range {
+    min: 1e-4,
+    max: 1000.0,
+};
+
+/// Точки (P, F) дальше этого числа СКО от прямой считаются выбросами
+const OUTLIER_SIGMA: f32 = 3.0;
+/// Максимальное количество точек для расчета чувствительности
+const MAX_HISTORY: usize = 2000;
+
+pub(crate) const MIN_SETTLE_WINDOW_S: u32 = 2;
+pub(crate) const MAX_SETTLE_WINDOW_S: u32 = 60;
+pub(crate) const MIN_DRIFT: f32 = 0.01;
+pub(crate) const MAX_DRIFT: f32 = 10.0;
+
+/// Период поиска датчиков SCTB вне измерения
+const SCAN_PERIOD: Duration = Duration::from_secs(2);
+
+/// Степень полинома F(P) в режиме "Кривая"
+const CURVE_POLY_DEGREE: usize = 2;
+
+pub(crate) const MIN_CHAMBER_VOLUME_ML: u32 = 10;
+pub(crate) const MAX_CHAMBER_VOLUME_ML: u32 = 10000;
+const CHAMBER_VOLUME_STEP: u32 = 10;
+pub(crate) const MIN_LEAK_TIME_S: u32 = 5;
+pub(crate) const MAX_LEAK_TIME_S: u32 = 10 * 60;
+const LEAK_TIME_STEP: u32 = 5;
+pub(crate) const MIN_LEAK_LIMIT: f32 = 1e-6;
+pub(crate) const MAX_LEAK_LIMIT: f32 = 1.0;
+
+// пределы и шаги настройки допусков
+pub(crate) const SENSIVITY_LIMITS: Range = Range::new(0.0, 100.0);
+const SENSIVITY_STEP: f32 = 0.1;
+pub(crate) const FREQUENCY_LIMITS: Range = Range::new(0.0, 100000.0);
+const FREQUENCY_STEP: f32 = 10.0;
+pub(crate) const TEMPERATURE_LIMITS: Range = Range::new(-40.0, 100.0);
+const TEMPERATURE_STEP: f32 = 1.0;
+
+pub(crate) const MIN_INTERVAL: u32 = 50;
+pub(crate) const MAX_INTERVAL: u32 = 200;
+const INTERVAL_STEP: u32 = 10;
+
+pub struct Controller<S: SettingsStorage, C: Clock> {
+    encoder: (Sender<EncoderCommand>, Receiver<EncoderCommand>),
+    sensors: (Sender<SensorResult>, Receiver<SensorResult>),
+    display: (Sender<DisplayCommand>, Receiver<DisplayCommand>),
+    /// Строки команд с консольного UART
+    remote: (Sender<String>, Receiver<String>),
+    /// Строки в консольный UART: ответы на команды и поток показаний
+    replies: (Sender<String>, Receiver<String>),
+    /// Запросы Modbus RTU
+    modbus: (Sender<modbus::Transaction>, Receiver<modbus::Transaction>),
+
+    parameters: Parameters,
+    profiles: Profiles,
+    /// Профиль под курсором на экране выбора
+    profile_option: usize,
+    /// Позиция курсора в имени профиля в настройках
+    name_cursor: usize,
+
+    title_option: TitleOptions,
+    current_mode: TitleOptions,
+    current_state: State,
+    current_setup_parameter: SelectedParameter,
+    gauge_menu: GaugeMenu,
+
+    prev_p: f32,
+    /// Частоты DUT по каналам, на экран и в статус - первого
+    prev_f: [f32; MAX_DUTS],
+    prev_t: f32,
+    /// Последние показания датчиков без выбора источника, для потока
+    prev_sctb_p: f32,
+    /// Первый образцовый датчик, mbar
+    prev_gauge_p: f32,
+    run_start: Duration,
+
+    initial_point: Option<(f32, [f32; MAX_DUTS])>,
+    /// Точки (P, F) по каналам DUT
+    history: Vec<Vec<(f32, f32)>>,
+
+    curve_setpoints: Vec<f32>,
+    /// Точки кривой с частотой первого DUT и частоты всех DUT в них
+    curve_points: Vec<CurvePoint>,
+    curve_f: Vec<[f32; MAX_DUTS]>,
+    /// (P, F, T) за удержание на текущей уставке, в точку пишется среднее
+    curve_hold: Vec<(f32, [f32; MAX_DUTS], f32)>,
+    /// Страница экрана результата, сквозная по всем DUT
+    result_page: usize,
+
+    start_waiting_time: Option<Duration>,
+    /// (время, P, F) за последние settle_window_s удержания
+    hold_window: VecDeque<(Duration, f32, f32)>,
+
+    /// Время отсечки камеры в режиме "Течь"
+    leak_start: Option<Duration>,
+    /// (секунды от отсечки, P)
+    leak_history: Vec<(f32, f32)>,
+
+    /// DUT по каналам, прочитанные при запуске текущего измерения
+    run_duts: Vec<Option<SctbInfo>>,
+    /// Результаты последнего измерения по каналам DUT, для экрана результата и команды RESULT
+    last_results: Vec<DutResult>,
+    /// DUT на экране диагностики
+    diagnostics_dut: usize,
+    /// Сколько измерений закончено с включения
+    results: u16,
+
+    /// Датчики SCTB по последнему поиску, None - не искали или драйвер не умеет
+    sctb_devices: Option<Vec<SctbDevice>>,
+    last_scan: Option<Duration>,
+
+    storage: S,
+    clock: C,
+}
+
+impl<S: SettingsStorage, C: Clock> Controller<S, C> {
+    pub fn new(storage: S, clock: C) -> Self {
+        Self {
+            encoder: channel::bounded(3),
+            sensors: channel::bounded(3),
+            display: channel::bounded(3),
+            remote: channel::bounded(3),
+            replies: channel::bounded(16),
+            modbus: channel::bounded(1),
+
+            parameters: Parameters::default(),
+            profiles: Profiles::load(&storage),
+            profile_option: 0,
+            name_cursor: 0,
+
+            title_option: TitleOptions::Auto,
+            current_mode: TitleOptions::Auto,
+            current_state: State::Title,
+            current_setup_parameter: SelectedParameter::Threshold,
+            gauge_menu: GaugeMenu::default(),
+
+            prev_p: 0.0,
+            prev_f: [0.0; MAX_DUTS],
+            prev_t: 0.0,
+            prev_sctb_p: f32::NAN,
+            prev_gauge_p: f32::NAN,
+            run_start: Duration::ZERO,
+
+            initial_point: None,
+            history: Vec::new(),
+
+            curve_setpoints: Vec::new(),
+            curve_points: Vec::new(),
+            curve_f: Vec::new(),
+            curve_hold: Vec::new(),
+            result_page: 0,
+
+            start_waiting_time: None,
+            hold_window: VecDeque::new(),
+
+            leak_start: None,
+            leak_history: Vec::new(),
+
+            run_duts: Vec::new(),
+            last_results: Vec::new(),
+            diagnostics_dut: 0,
+            results: 0,
+
+            sctb_devices: None,
+            last_scan: None,
+
+            storage,
+            clock,
+        }
+        .with_active_profile()
+    }
+
+    fn with_active_profile(mut self) -> Self {
+        self.parameters = Parameters::load(&mut self.storage, self.profiles.active);
+        println!("Profile: {}", self.profiles.active_name());
+        self
+    }
+
+    pub fn command_chanel(&self) -> Sender<EncoderCommand> {
+        self.encoder.0.clone()
+    }
+
+    pub fn sensor_chanel(&self) -> Sender<SensorResult> {
+        self.sensors.0.clone()
+    }
+
+    /// Отправитель строк команд и получатель строк ответов
+    pub fn remote_chanel(&self) -> (Sender<String>, Receiver<String>) {
+        (self.remote.0.clone(), self.replies.1.clone())
+    }
+
+    pub fn modbus_chanel(&self) -> Sender<modbus::Transaction> {
+        self.modbus.0.clone()
+    }
+
+    pub fn display_chanel(&self) -> Receiver<DisplayCommand> {
+        self.send_title_screen(false);
+        self.display.1.clone()
+    }
+
+    fn send_title_screen(&self, selected: bool) {
+        self.display
+            .0
+            .send(DisplayCommand::TitleScreen {
+                option: TITLE_OPTIONS[self.title_option as usize],
+                selected,
+                dut: self
+                    .duts_found()
+                    .map(|found| (found, self.parameters.duts().len())),
+            })
+            .unwrap()
+    }
+
+    pub fn poll<SC: SensorsControl, V: Valve>(&mut self, sensors: &mut SC, klapan: &mut V) {
+        match self.current_state {
+            State::Measuring if self.leak_start.is_some() || self.curve_holding() => {
+                klapan.set_state(KlapanState::Isolate)
+            }
+            State::Measuring => klapan.set_state(KlapanState::Vacuum),
+            _ => klapan.set_state(KlapanState::Atmosphere),
+        }
+        .unwrap();
+
+        if let Ok(res) = self.encoder.1.try_recv() {
+            //println!("Encoder result: {:?}", res);
+            match self.current_state {
+                State::Title => {
+                    if self.process_title_cmd(res) {
+                        self.start_sensors(sensors);
+                    }
+                }
+                State::ProfileSelect => self.process_profile_select(res),
+                State::Setup => self.process_setup(res, sensors),
+                State::GaugeSetup => self.process_gauge_setup(res, sensors),
+                State::Diagnostics => self.process_diagnostics(res, sensors),
+                State::Measuring | State::Result => match res {
+                    EncoderCommand::Pull => self.return_to_title(sensors),
+                    EncoderCommand::Increment | EncoderCommand::Decrement
+                        if self.current_state == State::Result =>
+                    {
+                        self.flip_result_page(res)
+                    }
+                    _ => {}
+                },
+            }
+        } else if let Ok(line) = self.remote.1.try_recv() {
+            let reply = match remote::parse(&line).and_then(|cmd| self.process_remote(cmd, sensors))
+            {
+                Ok(reply) => reply,
+                Err(e) => remote::error(&e),
+            };
+            if let Err(e) = self.replies.0.try_send(reply) {
+                println!("Failed to send remote reply: {e}");
+            }
+        } else if let Ok((request, reply)) = self.modbus.1.try_recv() {
+            let _ = reply.send(self.process_modbus(request, sensors));
+        } else if let Ok(res) = self.sensors.1.try_recv() {
+            //println!("Sensor result: {:?}", res);
+            if self.current_state == State::Measuring {
+                let source =
+                    if self.parameters.try_use_alternative_sensor && !sensors.gauges().is_empty() {
+                        PressureSource::Gauge
+                    } else {
+                        PressureSource::Sctb
+                    };
+                let (p, t) = match res {
+                    SensorResult::SctbSensorResult { f, p, t } => {
+                        self.prev_f = f;
+                        self.prev_t = t;
+                        self.prev_sctb_p = p;
+                        self.stream_sample(res, source);
+                        if source == PressureSource::Gauge {
+                            (self.prev_p, t)
+                        } else {
+                            (p, t)
+                        }
+                    }
+                    SensorResult::GaugeResult { gauge, reading } => {
+                        // остальные датчики только пишутся в поток
+                        if gauge != 0 {
+                            self.stream_sample(res, source);
+                            return;
+                        }
+                        self.prev_gauge_p = reading.pressure.to(PressureUnit::Mbar);
+                        self.stream_sample(res, source);
+                        // ignore this sesor result if disabled or failed
+                        match reading.value(PressureUnit::MmHg) {
+                            Some(p) if source == PressureSource::Gauge => (p, self.prev_t),
+                            _ => return,
+                        }
+                    }
+                };
+
+                if self.current_mode == TitleOptions::Leak {
+                    self.process_leak(sensors, p);
+                    self.prev_p = p;
+                    return;
+                }
+
+                // capture initial point
+                if self.initial_point.is_none() {
+                    self.initial_point.replace((p, self.prev_f));
+                }
+
+                self.push_history(p, self.prev_f);
+                // стабилизация и экран - по первому DUT
+                let f = self.prev_f[0];
+
+                let threshold = self.current_threshold();
+
+                if let Some(start_waiting_time) = self.start_waiting_time {
+                    let wait_time_s = Duration::from_secs(self.parameters.wait_time_s as u64);
+                    let now = self.clock.now();
+
+                    // Идет удержание
+                    let drift = self.update_drift(now, p, f);
+                    if self.current_mode == TitleOptions::Curve && p.is_finite() {
+                        self.curve_hold.push((p, self.prev_f, t));
+                    }
+                    let settled = self.parameters.settle_enabled
+                        && drift.is_some_and(|d| d.is_settled(&self.parameters));
+
+                    if settled || (now - start_waiting_time) >= wait_time_s {
+                        let hold = HoldResult {
+                            settled,
+                            duration: now - start_waiting_time,
+                        };
+                        if settled {
+                            println!(
+                                "Settled after {:.1} s: dF/dt={:.4} Hz/s, dP/dt={:.4} mmHg/s",
+                                hold.duration.as_secs_f32(),
+                                drift.map_or(f32::NAN, |d| d.df_dt),
+                                drift.map_or(f32::NAN, |d| d.dp_dt)
+                            );
+                        } else {
+                            println!("Waiting time expired");
+                        }
+                        self.start_waiting_time.take(); // clear waiting time
+
+                        if self.current_mode == TitleOptions::Curve {
+                            self.finish_curve_point(sensors, hold);
+                        } else {
+                            self.finish_measurement(sensors, p, t, hold);
+                        }
+                    } else {
+                        // update screen
+                        self.display
+                            .0
+                            .send(DisplayCommand::Measure {
+                                f: Some(f),
+                                p: Some(p),
+                                threashold: threshold,
+                                wait_time: Some(start_waiting_time + wait_time_s - now),
+                                drift,
+                            })
+                            .unwrap();
+                    }
+                } else {
+                    // Only in auto and curve mode
+                    if matches!(self.current_mode, TitleOptions::Auto | TitleOptions::Curve)
+                        && self.prev_p > p
+                        && p <= threshold
+                    {
+                        self.start_waiting_time.replace(self.clock.now());
+                        self.hold_window.clear();
+                        self.curve_hold.clear();
+                    }
+
+                    // update screen
+                    self.display
+                        .0
+                        .send(DisplayCommand::Measure {
+                            f: Some(f),
+                            p: Some(p),
+                            threashold: threshold,
+                            wait_time: None,
+                            drift: None,
+                        })
+                        .unwrap();
+                }
+                self.prev_p = p;
+            }
+        } else if self.current_state != State::Measuring
+            && self
+                .last_scan
+                .filter(|&t| self.clock.now().saturating_sub(t) < SCAN_PERIOD)
+                .is_none()
+        {
+            self.scan_sctb(sensors);
+        } else {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Сколько назначенных DUT есть на шине по последнему поиску, None - шина не опрашивается
+    fn duts_found(&self) -> Option<usize> {
+        self.sctb_devices.as_ref().map(|devices| {
+            self.parameters
+                .duts()
+                .iter()
+                .filter(|&&a| devices.iter().any(|d| d.address as u32 == a))
+                .count()
+        })
+    }
+
+    /// Все ли DUT на шине
+    fn dut_present(&self) -> Option<bool> {
+        self.duts_found()
+            .map(|found| found == self.parameters.duts().len())
+    }
+
+    /// Поиск датчиков SCTB при остановленном опросе: подключение и отключение DUT
+    fn scan_sctb<SC: SensorsControl>(&mut self, sensors: &mut SC) {
+        self.last_scan.replace(self.clock.now());
+        let Some(devices) = sensors.scan_sctb() else {
+            return;
+        };
+        if self.sctb_devices.as_ref() == Some(&devices) {
+            return;
+        }
+        println!(
+            "SCTB sensors at {:?}",
+            devices.iter().map(|d| d.address).collect::<Vec<_>>()
+        );
+
+        if let Some(previous) = &self.sctb_devices {
+            let found =
+                |devices: &[SctbDevice], a: u32| devices.iter().any(|d| d.address as u32 == a);
+            for &a in self.parameters.duts() {
+                match (found(previous, a), found(&devices, a)) {
+                    (false, true) => println!("DUT at {a} connected"),
+                    (true, false) => println!("DUT at {a} removed"),
+                    _ => {}
+                }
+            }
+        }
+
+        let was_present = self.dut_present();
+        self.sctb_devices.replace(devices);
+        // "Авто" - когда вставлен последний из DUT
+        let plugged = was_present == Some(false) && self.dut_present() == Some(true);
+
+        if plugged
+            && self.parameters.auto_start
+            && matches!(self.current_state, State::Title | State::Result)
+        {
+            println!("Auto start on new DUT");
+            self.abort_run(sensors);
+            if let Err(e) = self.start_run(RunMode::Auto, sensors) {
+                println!("Auto start failed: {e:?}");
+            }
+            return;
+        }
+
+        match self.current_state {
+            State::Title => self.send_title_screen(false),
+            State::Setup => self.send_setup_menu(),
+            _ => {}
+        }
+    }
+
+    /// Роли датчиков SCTB из параметров, до чтения DUT и запуска опроса
+    fn assign_sctb<SC: SensorsControl>(&self, sensors: &mut SC) {
+        let duts = self
+            .parameters
+            .duts()
+            .iter()
+            .map(|&a| a as u8)
+            .collect::<Vec<_>>();
+        sensors.assign_sctb(self.parameters.sctb_reference as u8, &duts);
+    }
+
+    fn start_sensors<SC: SensorsControl>(&mut self, sensors: &mut SC) {
+        // опрос еще не идет, шина свободна
+        self.assign_sctb(sensors);
+        self.run_duts = (0..self.parameters.duts().len())
+            .map(|i| {
+                let dut = sensors.dut_info(i);
+                match &dut {
+                    Some(dut) => println!(
+                        "DUT {} at {}: S/N {}, firmware {}.{}",
+                        i + 1,
+                        dut.address,
+                        dut.info.serial,
+                        dut.info.firmware.0,
+                        dut.info.firmware.1
+                    ),
+                    None => println!("DUT {} info not available", i + 1),
+                }
+                dut
+            })
+            .collect();
+
+        sensors
+            .start(Duration::from_millis(
+                self.parameters.update_period_ms as u64,
+            ))
+            .expect("Failed to starts sensors");
+
+        if self.parameters.try_use_alternative_sensor {
+            match sensors.gauges().first() {
+                Some(id) => println!("Reference gauge: {id}"),
+                None => println!("No reference gauge, using SCTB"),
+            }
+        }
+    }
+
+    /// Отмена измерения или выход с экрана результата
+    fn return_to_title<SC: SensorsControl>(&mut self, sensors: &mut SC) {
+        self.current_state = State::Title;
+        self.leak_start.take();
+        self.title_option = self.current_mode;
+
+        sensors.stop().unwrap();
+
+        self.send_title_screen(false)
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            state: match self.current_state {
+                State::Title => DeviceState::Title,
+                State::ProfileSelect => DeviceState::ProfileSelect,
+                State::Setup => DeviceState::Setup,
+                State::GaugeSetup => DeviceState::GaugeSetup,
+                State::Diagnostics => DeviceState::Diagnostics,
+                State::Measuring => DeviceState::Measuring,
+                State::Result => DeviceState::Result,
+            },
+            mode: match self.current_mode {
+                TitleOptions::Manual => RunMode::Manual,
+                TitleOptions::Curve => RunMode::Curve,
+                TitleOptions::Leak => RunMode::Leak,
+                _ => RunMode::Auto,
+            },
+            p: self.prev_p,
+            f: self.prev_f[0],
+            t: self.prev_t,
+            threshold: self.current_threshold(),
+            timer_s: self
+                .start_waiting_time
+                .or(self.leak_start)
+                .map_or(0.0, |start| (self.clock.now() - start).as_secs_f32()),
+            profile: self.profiles.active,
+            results: self.results,
+            dut: self.dut_present(),
+        }
+    }
+
+    fn busy(&self) -> RemoteError {
+        RemoteError::Busy(self.status().state.name())
+    }
+
+    /// Запуск измерения по UART или Modbus, только с главного экрана
+    fn start_run<SC: SensorsControl>(
+        &mut self,
+        mode: RunMode,
+        sensors: &mut SC,
+    ) -> Result<(), RemoteError> {
+        if self.current_state != State::Title {
+            return Err(self.busy());
+        }
+        self.title_option = match mode {
+            RunMode::Auto => TitleOptions::Auto,
+            RunMode::Manual => TitleOptions::Manual,
+            RunMode::Curve => TitleOptions::Curve,
+            RunMode::Leak => TitleOptions::Leak,
+        };
+        if self.process_title_cmd(EncoderCommand::Pull) {
+            self.start_sensors(sensors);
+        }
+        Ok(())
+    }
+
+    fn abort_run<SC: SensorsControl>(&mut self, sensors: &mut SC) {
+        if matches!(self.current_state, State::Measuring | State::Result) {
+            self.return_to_title(sensors);
+        }
+    }
+
+    /// Меню и измерение работают с копией параметров на экране
+    fn check_parameters_writable(&self) -> Result<(), RemoteError> {
+        if matches!(self.current_state, State::Setup | State::Measuring) {
+            return Err(self.busy());
+        }
+        Ok(())
+    }
+
+    fn save_parameters(&mut self) -> Result<(), RemoteError> {
+        if self.current_state == State::Setup {
+            return Err(self.busy());
+        }
+        self.parameters
+            .store(&mut self.storage, self.profiles.active)
+            .map_err(|e| RemoteError::Storage(format!("{e:?}")))
+    }
+
+    fn set_last_results(&mut self, results: Vec<DutResult>) {
+        self.last_results = results;
+        self.results = self.results.wrapping_add(1);
+        self.result_page = 0;
+        self.send_result_page();
+    }
+
+    fn process_remote<SC: SensorsControl>(
+        &mut self,
+        cmd: RemoteCommand,
+        sensors: &mut SC,
+    ) -> Result<String, RemoteError> {
+        match cmd {
+            RemoteCommand::Start(mode) => {
+                self.start_run(mode, sensors)?;
+                Ok(remote::ok(&[("mode", mode.name().to_string())]))
+            }
+            RemoteCommand::Abort => {
+                self.abort_run(sensors);
+                Ok(remote::ok(&[]))
+            }
+            RemoteCommand::Read => Ok(remote::format_status(&self.status())),
+            RemoteCommand::Get(Some(name)) => {
+                let value = self
+                    .parameters
+                    .get(&name)
+                    .ok_or(RemoteError::UnknownParameter(name.clone()))?;
+                Ok(remote::ok(&[(&name, value)]))
+            }
+            RemoteCommand::Get(None) => {
+                Ok(remote::ok(&remote::PARAMETER_NAMES.map(|name| {
+                    (name, self.parameters.get(name).unwrap_or_default())
+                })))
+            }
+            RemoteCommand::Set { name, value } => {
+                self.check_parameters_writable()?;
+                self.parameters.set(&name, &value)?;
+                let value = self.parameters.get(&name).unwrap_or_default();
+                Ok(remote::ok(&[(&name, value)]))
+            }
+            RemoteCommand::Save => {
+                self.save_parameters()?;
+                Ok(remote::ok(&[("profile", self.profiles.active.to_string())]))
+            }
+            RemoteCommand::Result { dut } => {
+                if self.last_results.is_empty() {
+                    return Err(RemoteError::NoResult);
+                }
+                self.last_results
+                    .get(dut)
+                    .map(|result| remote::format_result(result, dut, self.last_results.len()))
+                    .ok_or(RemoteError::UnknownDut(dut + 1))
+            }
+            RemoteCommand::Gauges => {
+                let ids = sensors.gauges();
+                let stats = (0..ids.len())
+                    .map(|i| sensors.gauge_stats(i))
+                    .collect::<Vec<_>>();
+                Ok(remote::format_gauges(ids, &stats))
+            }
+            RemoteCommand::Gauge { gauge, command } => {
+                if self.current_state == State::Measuring {
+                    return Err(self.busy());
+                }
+                if gauge >= sensors.gauges().len() {
+                    return Err(RemoteError::UnknownGauge(gauge));
+                }
+                let reply = sensors
+                    .gauge_command(gauge, command)
+                    .map_err(RemoteError::Gauge)?;
+                Ok(remote::format_gauge_reply(gauge, &reply))
+            }
+            RemoteCommand::Help => Ok(remote::help()),
+        }
+    }
+
+    fn process_modbus<SC: SensorsControl>(
+        &mut self,
+        request: Request,
+        sensors: &mut SC,
+    ) -> Result<Response, Exception> {
+        match request {
+            Request::ReadCoils { address, count } => {
+                modbus::read_coils(&self.status(), address, count).map(Response::Coils)
+            }
+            Request::ReadHoldingRegisters { address, count } => {
+                modbus::read_holding(&self.parameters, address, count).map(Response::Registers)
+            }
+            Request::ReadInputRegisters { address, count } => {
+                modbus::read_input(&self.status(), &self.last_results, address, count)
+                    .map(Response::Registers)
+            }
+            Request::WriteSingleCoil { address, value } => {
+                self.write_coils(address, &[value], sensors)?;
+                Ok(Response::Written {
+                    address,
+                    value: if value { 0xFF00 } else { 0 },
+                })
+            }
+            Request::WriteMultipleCoils { address, values } => {
+                self.write_coils(address, &values, sensors)?;
+                Ok(Response::Written {
+                    address,
+                    value: values.len() as u16,
+                })
+            }
+            Request::WriteSingleRegister { address, value } => {
+                self.check_parameters_writable()?;
+                modbus::write_holding(&mut self.parameters, address, &[value])?;
+                Ok(Response::Written { address, value })
+            }
+            Request::WriteMultipleRegisters { address, values } => {
+                self.check_parameters_writable()?;
+                modbus::write_holding(&mut self.parameters, address, &values)?;
+                Ok(Response::Written {
+                    address,
+                    value: values.len() as u16,
+                })
+            }
+        }
+    }
+
+    /// Запись 1 выполняет команду coil, запись 0 ничего не делает
+    fn write_coils<SC: SensorsControl>(
+        &mut self,
+        address: u16,
+        values: &[bool],
+        sensors: &mut SC,
+    ) -> Result<(), Exception> {
+        let coils = Coil::range(address, values.len() as u16)?;
+        for (coil, _) in coils.into_iter().zip(values).filter(|(_, v)| **v) {
+            match coil {
+                Coil::Start(mode) => self.start_run(mode, sensors)?,
+                Coil::Abort => self.abort_run(sensors),
+                Coil::Save => self.save_parameters()?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Строка потока показаний, если он включен. Если UART не успевает, отсчет теряется.
+    fn stream_sample(&self, sensor: SensorResult, source: PressureSource) {
+        let now = self.clock.now();
+        let phase = if let Some(leak_start) = self.leak_start {
+            let leak_time = Duration::from_secs(self.parameters.leak_time_s as u64);
+            Phase::Leak((leak_start + leak_time).saturating_sub(now))
+        } else if let Some(start_waiting_time) = self.start_waiting_time {
+            let wait_time = Duration::from_secs(self.parameters.wait_time_s as u64);
+            Phase::Hold((start_waiting_time + wait_time).saturating_sub(now))
+        } else {
+            Phase::Pumping
+        };
+
+        let sample = StreamSample {
+            time: now.saturating_sub(self.run_start),
+            sensor,
+            source,
+            p: match source {
+                PressureSource::Sctb => self.prev_sctb_p,
+                PressureSource::Gauge => {
+                    Pressure::new(self.prev_gauge_p, PressureUnit::Mbar).to(PressureUnit::MmHg)
+                }
+            },
+            sctb_p: self.prev_sctb_p,
+            sctb_t: self.prev_t,
+            f: self.prev_f,
+            gauge_p: match sensor {
+                SensorResult::GaugeResult { reading, .. } => {
+                    reading.pressure.to(PressureUnit::Mbar)
+                }
+                SensorResult::SctbSensorResult { .. } => self.prev_gauge_p,
+            },
+            phase,
+        };
+        if let Some(line) = sample.format(self.parameters.stream_format) {
+            let _ = self.replies.0.try_send(line);
+        }
+    }
+
+    /// Порог срабатывания удержания: в режиме "Кривая" - текущая уставка
+    fn current_threshold(&self) -> f32 {
+        if self.current_mode == TitleOptions::Curve {
+            self.curve_setpoints
+                .get(self.curve_points.len())
+                .copied()
+                .unwrap_or(self.parameters.threshold)
+        } else {
+            self.parameters.threshold
+        }
+    }
+
+    /// Конец удержания: расчет чувствительности по каждому DUT и экран результата
+    fn finish_measurement<SC: SensorsControl>(
+        &mut self,
+        sensors: &mut SC,
+        p: f32,
+        t: f32,
+        hold: HoldResult,
+    ) {
+        // end -> result screen
+        self.current_state = State::Result;
+
+        sensors.stop().unwrap();
+
+        let initial_point = self.initial_point.take();
+        let results = (0..self.parameters.duts().len())
+            .map(|i| {
+                let history = self.history.get(i).map_or(&[][..], Vec::as_slice);
+                let f = self.prev_f[i];
+                let fit = robust_linear_regression(history, OUTLIER_SIGMA);
+                let sensivity = if let Some(fit) = &fit {
+                    println!(
+                        "DUT {}: sensivity {:.3} Hz/mmHg (se {:.3}, R2 {:.5}, b {:.2} Hz), {} points, {} rejected",
+                        i + 1, fit.line.k, fit.k_std_err, fit.r2, fit.line.b, fit.points, fit.rejected
+                    );
+                    fit.line.k
+                } else {
+                    // недостаточно точек для регрессии
+                    let s = initial_point.map_or(f32::NAN, |(p0, f0)| {
+                        sensivity((p0, f0[i]), (p, f))
+                    });
+                    println!(
+                        "DUT {}: sensivity {s:.3} Hz/mmHg by 2 points, {} samples",
+                        i + 1,
+                        history.len()
+                    );
+                    s
+                };
+                let f_atm = initial_point.map_or(f32::NAN, |(_, f0)| f0[i]);
+
+                let verdict = self.parameters.tolerances_enabled.then(|| {
+                    self.parameters.tolerances.check(&Measured {
+                        sensivity,
+                        f_atm,
+                        f_vac: f,
+                        t,
+                    })
+                });
+                if let Some(verdict) = verdict {
+                    println!("DUT {}: verdict {verdict:?}", i + 1);
+                }
+
+                let calibration = self
+                    .parameters
+                    .write_calibration
+                    .then(|| calibration::fit(history))
+                    .map(|fit| Self::write_dut_calibration(sensors, i, fit));
+
+                DutResult {
+                    result: RunResult::Sensivity {
+                        p,
+                        f,
+                        t,
+                        sensivity,
+                        f_atm,
+                        fit,
+                        hold,
+                        verdict,
+                    },
+                    dut: self.run_duts.get(i).copied().flatten(),
+                    calibration,
+                }
+            })
+            .collect();
+        self.set_last_results(results);
+    }
+
+    /// Удержание на уставке в режиме "Кривая": камера отсечена от насоса
+    fn curve_holding(&self) -> bool {
+        self.current_mode == TitleOptions::Curve && self.start_waiting_time.is_some()
+    }
+
+    /// Конец удержания на уставке в режиме "Кривая": в точку пишутся средние за удержание
+    fn finish_curve_point<SC: SensorsControl>(&mut self, sensors: &mut SC, hold: HoldResult) {
+        let duts = self.parameters.duts().len();
+        let hold_samples = std::mem::take(&mut self.curve_hold);
+        let f = std::array::from_fn(|i| mean(hold_samples.iter().map(|s| s.1[i])));
+        let point = CurvePoint {
+            p: mean(hold_samples.iter().map(|s| s.0)),
+            f: f[0],
+            t: mean(hold_samples.iter().map(|s| s.2)),
+            hold: Some(hold),
+        };
+        println!(
+            "Curve point {}: P={:.3} mmHg, F={:.3?} Hz, T={:.2} *C, {} samples, {}",
+            self.curve_points.len() + 1,
+            point.p,
+            &f[..duts],
+            point.t,
+            hold_samples.len(),
+            if hold.settled { "settled" } else { "timeout" }
+        );
+        self.curve_points.push(point);
+        self.curve_f.push(f);
+
+        if self.curve_points.len() < self.curve_setpoints.len() {
+            // к следующей уставке
+            return;
+        }
+
+        self.current_state = State::Result;
+        sensors.stop().unwrap();
+
+        let points = std::mem::take(&mut self.curve_points);
+        let curve_f = std::mem::take(&mut self.curve_f);
+        let results = (0..duts)
+            .map(|i| {
+                let result = CurveResult::new(
+                    points
+                        .iter()
+                        .zip(&curve_f)
+                        .map(|(point, f)| CurvePoint { f: f[i], ..*point })
+                        .collect(),
+                );
+                for (j, s) in result.segments.iter().enumerate() {
+                    println!(
+                        "DUT {}: segment {:.3}..{:.3} mmHg: {:.3} Hz/mmHg",
+                        i + 1,
+                        result.points[j].p,
+                        result.points[j + 1].p,
+                        s
+                    );
+                }
+                if let Some(poly) = &result.poly {
+                    println!("DUT {}: F(P) coefficients: {poly:?}", i + 1);
+                }
+
+                let calibration = self.parameters.write_calibration.then(|| {
+                    let data = result.points.iter().map(|p| (p.p, p.f)).collect::<Vec<_>>();
+                    Self::write_dut_calibration(sensors, i, calibration::fit(&data))
+                });
+
+                DutResult {
+                    result: RunResult::Curve(result),
+                    dut: self.run_duts.get(i).copied().flatten(),
+                    calibration,
+                }
+            })
+            .collect();
+        self.set_last_results(results);
+    }
+
+    /// Запись рассчитанной калибровки в DUT номер `dut`, опрос уже остановлен
+    fn write_dut_calibration<SC: SensorsControl>(
+        sensors: &mut SC,
+        dut: usize,
+        fit: Option<CalibrationFit>,
+    ) -> CalibrationOutcome {
+        let n = dut + 1;
+        let Some(fit) = fit else {
+            println!("DUT {n} calibration: not enough points");
+            return CalibrationOutcome::NoFit;
+        };
+        println!(
+            "DUT {n} calibration: F0={:.3} Hz, coefficients {:?}, max error {:.4} mmHg, {} points",
+            fit.calibration.f0, fit.calibration.coeffs, fit.max_error, fit.points
+        );
+        match sensors.write_dut_calibration(dut, &fit.calibration) {
+            Ok(()) => {
+                println!("DUT {n} calibration written");
+                CalibrationOutcome::Written(fit)
+            }
+            Err(error) => {
+                println!("DUT {n} calibration not written: {error:?}");
+                CalibrationOutcome::Failed { fit, error }
+            }
+        }
+    }
+
+    /// Режим "Течь": откачка до порога, отсечка, рост давления в течение leak_time_s
+    fn process_leak<SC: SensorsControl>(&mut self, sensors: &mut SC, p: f32) {
+        let now = self.clock.now();
+        let leak_time = Duration::from_secs(self.parameters.leak_time_s as u64);
+
+        let wait_time = if let Some(leak_start) = self.leak_start {
+            if p.is_finite() {
+                self.leak_history
+                    .push(((now - leak_start).as_secs_f32(), p));
+            }
+
+            if now - leak_start >= leak_time {
+                self.finish_leak_test(sensors, now - leak_start);
+                return;
+            }
+            Some(leak_start + leak_time - now)
+        } else {
+            if self.prev_p > p && p <= self.parameters.threshold {
+                println!("Chamber isolated at {p:.3} mmHg");
+                self.leak_start.replace(now);
+                self.leak_history.clear();
+                self.leak_history.push((0.0, p));
+            }
+            None
+        };
+
+        self.display
+            .0
+            .send(DisplayCommand::Measure {
+                f: Some(self.prev_f[0]),
+                p: Some(p),
+                threashold: self.parameters.threshold,
+                wait_time,
+                drift: None,
+            })
+            .unwrap();
+    }
+
+    fn finish_leak_test<SC: SensorsControl>(&mut self, sensors: &mut SC, duration: Duration) {
+        self.current_state = State::Result;
+        self.leak_start.take();
+
+        sensors.stop().unwrap();
+
+        let fit = robust_linear_regression(&self.leak_history, OUTLIER_SIGMA);
+        let (p_start, p_end) = (
+            self.leak_history.first().map_or(f32::NAN, |p| p.1),
+            self.leak_history.last().map_or(f32::NAN, |p| p.1),
+        );
+        // mmHg/s
+        let dp_dt = fit.map_or_else(
+            || (p_end - p_start) / duration.as_secs_f32(),
+            |fit| fit.line.k,
+        );
+
+        let dp_dt = Pressure::new(dp_dt, PressureUnit::MmHg).to(PressureUnit::Mbar);
+        let leak_rate = dp_dt * self.parameters.chamber_volume_ml as f32 / 1000.0;
+        let result = LeakResult {
+            p_start,
+            p_end,
+            dp_dt,
+            leak_rate,
+            limit: self.parameters.leak_limit,
+            passed: leak_rate <= self.parameters.leak_limit,
+            duration,
+            fit,
+        };
+        println!(
+            "Leak test: P {:.4} -> {:.4} mmHg in {:.1} s, dP/dt={:.3e} mbar/s, leak={:.3e} mbar*l/s (limit {:.1e}): {}",
+            result.p_start,
+            result.p_end,
+            duration.as_secs_f32(),
+            result.dp_dt,
+            result.leak_rate,
+            result.limit,
+            if result.passed { "PASS" } else { "FAIL" }
+        );
+
+        // течь одна на камеру, результат - на первый DUT
+        self.set_last_results(vec![DutResult {
+            result: RunResult::Leak(result),
+            dut: self.run_duts.first().copied().flatten(),
+            calibration: None,
+        }]);
+    }
+
+    /// Страницы результата одного DUT: калибровка, если писалась, и сам результат
+    fn dut_result_pages(result: &DutResult) -> usize {
+        let pages = match result.result {
+            RunResult::Curve(_) => CurveResult::PAGES,
+            _ => 1,
+        };
+        pages + usize::from(result.calibration.is_some())
+    }
+
+    /// Страницы экрана результата подряд по всем DUT
+    fn result_pages(&self) -> usize {
+        self.last_results.iter().map(Self::dut_result_pages).sum()
+    }
+
+    fn flip_result_page(&mut self, cmd: EncoderCommand) {
+        let pages = self.result_pages();
+        if pages < 2 {
+            return;
+        }
+
+        self.result_page = match cmd {
+            EncoderCommand::Increment => (self.result_page + 1) % pages,
+            _ => (self.result_page + pages - 1) % pages,
+        };
+        self.send_result_page();
+    }
+
+    fn send_result_page(&self) {
+        let mut page = self.result_page;
+        for (channel, result) in self.last_results.iter().enumerate() {
+            let pages = Self::dut_result_pages(result);
+            if page < pages {
+                self.send_dut_result_page(result, channel, page);
+                return;
+            }
+            page -= pages;
+        }
+    }
+
+    fn send_dut_result_page(&self, result: &DutResult, channel: usize, mut page: usize) {
+        // номер на экране, только если DUT несколько
+        let dut = (self.last_results.len() > 1).then_some(channel + 1);
+        if let Some(outcome) = &result.calibration {
+            if page == 0 {
+                self.display
+                    .0
+                    .send(DisplayCommand::CalibrationResult {
+                        outcome: outcome.clone(),
+                        dut,
+                    })
+                    .unwrap();
+                return;
+            }
+            page -= 1;
+        }
+
+        let command = match &result.result {
+            RunResult::Curve(result) => DisplayCommand::CurveResult {
+                result: result.clone(),
+                page,
+                dut,
+            },
+            &RunResult::Sensivity {
+                p,
+                f,
+                t,
+                sensivity,
+                fit,
+                hold,
+                verdict,
+                ..
+            } => DisplayCommand::Result {
+                p,
+                f,
+                t: Some(t),
+                threashold: self.parameters.threshold,
+                sensivity,
+                fit,
+                hold: Some(hold),
+                verdict,
+                dut,
+            },
+            &RunResult::Leak(result) => DisplayCommand::LeakResult { result },
+        };
+        self.display.0.send(command).unwrap();
+    }
+
+    fn process_title_cmd(&mut self, cmd: EncoderCommand) -> bool {
+        match cmd {
+            EncoderCommand::Increment | EncoderCommand::Decrement => {
+                self.title_option = match (self.title_option, cmd) {
+                    (TitleOptions::Auto, EncoderCommand::Decrement) => TitleOptions::Setup,
+
+                    _ => num::FromPrimitive::from_u32(
+                        (self.title_option as u32).wrapping_add_signed(
+                            if cmd == EncoderCommand::Increment {
+                                1i32
+                            } else {
+                                -1
+                            },
+                        ) % (TitleOptions::Count as u32),
+                    )
+                    .unwrap(),
+                };
+
+                self.send_title_screen(false);
+                false
+            }
+            EncoderCommand::Push => {
+                self.send_title_screen(true);
+                false
+            }
+            EncoderCommand::Pull => {
+                self.initial_point.take(); // clear initial point
+                self.history.clear();
+                self.curve_points.clear();
+                self.curve_f.clear();
+                self.curve_hold.clear();
+                self.start_waiting_time.take(); // clear waiting time
+                self.leak_start.take();
+                match self.title_option {
+                    TitleOptions::Auto
+                    | TitleOptions::Manual
+                    | TitleOptions::Curve
+                    | TitleOptions::Leak => {
+                        // уставки проходятся по мере откачки
+                        self.curve_setpoints = self.parameters.curve_points().to_vec();
+                        self.curve_setpoints.sort_by(|a, b| b.total_cmp(a));
+
+                        // enter working cycle
+                        self.prev_p = 0.0;
+                        self.prev_sctb_p = f32::NAN;
+                        self.prev_gauge_p = f32::NAN;
+                        self.run_start = self.clock.now();
+                        if self.parameters.stream_format == StreamFormat::Csv {
+                            let _ = self.replies.0.try_send(stream::CSV_HEADER.to_string());
+                        }
+                        self.current_mode = self.title_option; // save current mode for return
+                        self.current_state = State::Measuring;
+                        self.display
+                            .0
+                            .send(DisplayCommand::Measure {
+                                f: None,
+                                p: None,
+                                threashold: self.parameters.threshold,
+                                wait_time: None,
+                                drift: None,
+                            })
+                            .unwrap();
+
+                        true
+                    }
+                    TitleOptions::Profile => {
+                        // enter profile select
+                        self.current_state = State::ProfileSelect;
+                        self.profile_option = self.profiles.active;
+                        self.send_profile_select();
+                        false
+                    }
+                    TitleOptions::Setup => {
+                        // enter setup
+                        self.current_state = State::Setup;
+                        self.current_setup_parameter = SelectedParameter::ProfileName;
+                        self.name_cursor = 0;
+                        self.send_setup_menu();
+                        false
+                    }
+                    TitleOptions::Count => unreachable!(),
+                }
+            }
+        }
+    }
+
+    fn process_profile_select(&mut self, cmd: EncoderCommand) {
+        match cmd {
+            EncoderCommand::Increment => {
+                self.profile_option = (self.profile_option + 1) % profile::MAX_PROFILES;
+                self.send_profile_select();
+            }
+            EncoderCommand::Decrement => {
+                self.profile_option =
+                    (self.profile_option + profile::MAX_PROFILES - 1) % profile::MAX_PROFILES;
+                self.send_profile_select();
+            }
+            EncoderCommand::Pull => {
+                // activate profile, return to title
+                self.profiles.active = self.profile_option;
+                self.profiles.store(&mut self.storage);
+                self.parameters = Parameters::load(&mut self.storage, self.profiles.active);
+                println!("Profile: {}", self.profiles.active_name());
+
+                self.current_state = State::Title;
+                self.title_option = TitleOptions::Profile;
+                self.send_title_screen(false);
+            }
+            EncoderCommand::Push => {}
+        }
+    }
+
+    fn send_profile_select(&self) {
+        self.display
+            .0
+            .send(DisplayCommand::ProfileSelect {
+                name: self.profiles.names[self.profile_option]
+                    .trim_end()
+                    .to_string(),
+                index: self.profile_option,
+                active: self.profile_option == self.profiles.active,
+            })
+            .unwrap();
+    }
+
+    fn send_setup_menu(&self) {
+        self.display
+            .0
+            .send(DisplayCommand::SetupMenu {
+                values: self.parameters,
+                selected: self.current_setup_parameter,
+                precision: Precission::from(self.parameters.threshold),
+                profile: self.profiles.names[self.profiles.active].clone(),
+                cursor: self.name_cursor,
+                sctb: self
+                    .sctb_devices
+                    .as_ref()
+                    .map(|devices| devices.iter().map(|d| d.address).collect()),
+            })
+            .unwrap();
+    }
+
+    fn process_setup<SC: SensorsControl>(&mut self, cmd: EncoderCommand, sensors: &mut SC) {
+        if cmd == EncoderCommand::Pull {
+            match self.current_setup_parameter {
+                SelectedParameter::SaveAndExit => {
+                    self.current_state = State::Title;
+                    self.current_setup_parameter = SelectedParameter::ProfileName;
+                    self.title_option = TitleOptions::Setup;
+
+                    if let Err(e) = self
+                        .parameters
+                        .store(&mut self.storage, self.profiles.active)
+                    {
+                        println!("Failed to store settings: {e:?}");
+                    }
+                    self.profiles.store(&mut self.storage);
+
+                    self.send_title_screen(false)
+                }
+                SelectedParameter::Diagnostics => {
+                    self.current_state = State::Diagnostics;
+                    self.diagnostics_dut = 0;
+                    self.send_diagnostics(sensors);
+                }
+                SelectedParameter::ReferenceGauge => {
+                    self.current_state = State::GaugeSetup;
+                    self.gauge_menu = GaugeMenu::default();
+                    self.send_gauge_menu(sensors);
+                }
+                // next character of the name
+                SelectedParameter::ProfileName if self.name_cursor + 1 < profile::NAME_LEN => {
+                    self.name_cursor += 1;
+                    self.send_setup_menu();
+                }
+                _ => {
+                    self.name_cursor = 0;
+
+                    self.current_setup_parameter =
+                        // skip SelectedParameter::PSensorSelect
+                        if self.current_setup_parameter == SelectedParameter::UpdatePeriodMs {
+                            SelectedParameter::WaitTimeS
+                        } else {
+                            num::FromPrimitive::from_u32(self.current_setup_parameter as u32 + 1)
+                                .unwrap_or_default()
+                        };
+
+                    // skip settle limits if disabled
+                    if !self.parameters.settle_enabled
+                        && self.current_setup_parameter == SelectedParameter::SettleWindowS
+                    {
+                        self.current_setup_parameter = SelectedParameter::CurvePointsCount;
+                    }
+
+                    // skip unused curve points
+                    if self
+                        .current_setup_parameter
+                        .curve_point_index()
+                        .is_some_and(|i| i >= self.parameters.curve_points_count as usize)
+                    {
+                        self.current_setup_parameter = SelectedParameter::ChamberVolumeMl;
+                    }
+
+                    // skip unused DUT addresses
+                    if self
+                        .current_setup_parameter
+                        .sctb_dut_index()
+                        .is_some_and(|i| i >= self.parameters.duts().len())
+                    {
+                        self.current_setup_parameter = SelectedParameter::AutoStart;
+                    }
+
+                    // skip tolerances if disabled
+                    if !self.parameters.tolerances_enabled
+                        && self.current_setup_parameter == SelectedParameter::SensivityMin
+                    {
+                        self.current_setup_parameter = SelectedParameter::StreamFormat;
+                    }
+
+                    self.send_setup_menu();
+                }
+            }
+        } else if cmd != EncoderCommand::Push {
+            match self.current_setup_parameter {
+                SelectedParameter::ProfileName => profile::step_char(
+                    &mut self.profiles.names[self.profiles.active],
+                    self.name_cursor,
+                    cmd == EncoderCommand::Increment,
+                ),
+                SelectedParameter::Threshold => {
+                    self.parameters.threshold = Self::step_pressure(self.parameters.threshold, cmd)
+                }
+                SelectedParameter::UpdatePeriodMs => match cmd {
+                    EncoderCommand::Increment
+                        if self.parameters.update_period_ms < MAX_INTERVAL =>
+                    {
+                        self.parameters.update_period_ms += INTERVAL_STEP;
+                    }
+                    EncoderCommand::Decrement
+                        if self.parameters.update_period_ms > MIN_INTERVAL =>
+                    {
+                        self.parameters.update_period_ms -= INTERVAL_STEP;
+                    }
+                    _ => {}
+                },
+                SelectedParameter::PSensorSelect => {
+                    self.parameters.try_use_alternative_sensor ^= true
+                }
+                SelectedParameter::WaitTimeS => match cmd {
+                    EncoderCommand::Increment if self.parameters.wait_time_s < MAX_WAIT_TIME_S => {
+                        self.parameters.wait_time_s += 1;
+                    }
+                    EncoderCommand::Decrement if self.parameters.wait_time_s > 0 => {
+                        self.parameters.wait_time_s -= 1;
+                    }
+                    _ => {}
+                },
+
+                SelectedParameter::SettleEnabled => self.parameters.settle_enabled ^= true,
+                SelectedParameter::SettleWindowS => match cmd {
+                    EncoderCommand::Increment
+                        if self.parameters.settle_window_s < MAX_SETTLE_WINDOW_S =>
+                    {
+                        self.parameters.settle_window_s += 1;
+                    }
+                    EncoderCommand::Decrement
+                        if self.parameters.settle_window_s > MIN_SETTLE_WINDOW_S =>
+                    {
+                        self.parameters.settle_window_s -= 1;
+                    }
+                    _ => {}
+                },
+                SelectedParameter::SettleDfMax => {
+                    self.parameters.settle_df_max =
+                        Self::step_decimal(self.parameters.settle_df_max, MIN_DRIFT, MAX_DRIFT, cmd)
+                }
+                SelectedParameter::SettleDpMax => {
+                    self.parameters.settle_dp_max =
+                        Self::step_decimal(self.parameters.settle_dp_max, MIN_DRIFT, MAX_DRIFT, cmd)
+                }
+
+                SelectedParameter::CurvePointsCount => match cmd {
+                    EncoderCommand::Increment
+                        if self.parameters.curve_points_count < MAX_CURVE_POINTS as u32 =>
+                    {
+                        self.parameters.curve_points_count += 1;
+                    }
+                    EncoderCommand::Decrement if self.parameters.curve_points_count > 2 => {
+                        self.parameters.curve_points_count -= 1;
+                    }
+                    _ => {}
+                },
+                SelectedParameter::CurvePoint1
+                | SelectedParameter::CurvePoint2
+                | SelectedParameter::CurvePoint3
+                | SelectedParameter::CurvePoint4
+                | SelectedParameter::CurvePoint5 => {
+                    if let Some(i) = self.current_setup_parameter.curve_point_index() {
+                        self.parameters.curve_points[i] =
+                            Self::step_pressure(self.parameters.curve_points[i], cmd);
+                    }
+                }
+
+                SelectedParameter::ChamberVolumeMl => match cmd {
+                    EncoderCommand::Increment
+                        if self.parameters.chamber_volume_ml < MAX_CHAMBER_VOLUME_ML =>
+                    {
+                        self.parameters.chamber_volume_ml += CHAMBER_VOLUME_STEP;
+                    }
+                    EncoderCommand::Decrement
+                        if self.parameters.chamber_volume_ml > MIN_CHAMBER_VOLUME_ML =>
+                    {
+                        self.parameters.chamber_volume_ml -= CHAMBER_VOLUME_STEP;
+                    }
+                    _ => {}
+                },
+                SelectedParameter::LeakTimeS => match cmd {
+                    EncoderCommand::Increment if self.parameters.leak_time_s < MAX_LEAK_TIME_S => {
+                        self.parameters.leak_time_s += LEAK_TIME_STEP;
+                    }
+                    EncoderCommand::Decrement if self.parameters.leak_time_s > MIN_LEAK_TIME_S => {
+                        self.parameters.leak_time_s -= LEAK_TIME_STEP;
+                    }
+                    _ => {}
+                },
+                SelectedParameter::LeakLimit => {
+                    self.parameters.leak_limit = Self::step_125(
+                        self.parameters.leak_limit,
+                        MIN_LEAK_LIMIT,
+                        MAX_LEAK_LIMIT,
+                        cmd,
+                    )
+                }
+
+                SelectedParameter::TolerancesEnabled => self.parameters.tolerances_enabled ^= true,
+                SelectedParameter::SensivityMin | SelectedParameter::SensivityMax => {
+                    Self::step_range(
+                        &mut self.parameters.tolerances.sensivity,
+                        self.current_setup_parameter == SelectedParameter::SensivityMax,
+                        SENSIVITY_STEP,
+                        SENSIVITY_LIMITS,
+                        cmd,
+                    )
+                }
+                SelectedParameter::FAtmMin | SelectedParameter::FAtmMax => Self::step_range(
+                    &mut self.parameters.tolerances.f_atm,
+                    self.current_setup_parameter == SelectedParameter::FAtmMax,
+                    FREQUENCY_STEP,
+                    FREQUENCY_LIMITS,
+                    cmd,
+                ),
+                SelectedParameter::FVacMin | SelectedParameter::FVacMax => Self::step_range(
+                    &mut self.parameters.tolerances.f_vac,
+                    self.current_setup_parameter == SelectedParameter::FVacMax,
+                    FREQUENCY_STEP,
+                    FREQUENCY_LIMITS,
+                    cmd,
+                ),
+                SelectedParameter::TMin | SelectedParameter::TMax => Self::step_range(
+                    &mut self.parameters.tolerances.t,
+                    self.current_setup_parameter == SelectedParameter::TMax,
+                    TEMPERATURE_STEP,
+                    TEMPERATURE_LIMITS,
+                    cmd,
+                ),
+
+                SelectedParameter::StreamFormat => {
+                    self.parameters.stream_format = self
+                        .parameters
+                        .stream_format
+                        .next(cmd == EncoderCommand::Increment)
+                }
+                SelectedParameter::WriteCalibration => self.parameters.write_calibration ^= true,
+                SelectedParameter::SctbReference => {
+                    self.parameters.sctb_reference = self.step_sctb_address(
+                        self.parameters.sctb_reference,
+                        self.parameters.duts(),
+                        cmd,
+                    )
+                }
+                SelectedParameter::DutCount => {
+                    self.parameters.dut_count = match cmd {
+                        EncoderCommand::Increment => self.parameters.dut_count + 1,
+                        _ => self.parameters.dut_count.saturating_sub(1),
+                    }
+                    .clamp(1, MAX_DUTS as u32)
+                }
+                SelectedParameter::SctbDut1
+                | SelectedParameter::SctbDut2
+                | SelectedParameter::SctbDut3
+                | SelectedParameter::SctbDut4 => {
+                    if let Some(i) = self.current_setup_parameter.sctb_dut_index() {
+                        // занятые образцовым и остальными DUT
+                        let taken = std::iter::once(self.parameters.sctb_reference)
+                            .chain(
+                                self.parameters
+                                    .duts()
+                                    .iter()
+                                    .enumerate()
+                                    .filter(|&(j, _)| j != i)
+                                    .map(|(_, &a)| a),
+                            )
+                            .collect::<Vec<_>>();
+                        self.parameters.sctb_duts[i] =
+                            self.step_sctb_address(self.parameters.sctb_duts[i], &taken, cmd);
+                    }
+                }
+                SelectedParameter::AutoStart => self.parameters.auto_start ^= true,
+
+                SelectedParameter::Diagnostics
+                | SelectedParameter::ReferenceGauge
+                | SelectedParameter::SaveAndExit => { /* nothing */ }
+            }
+
+            self.send_setup_menu();
+        }
+    }
+
+    fn send_gauge_menu<SC: SensorsControl>(&self, sensors: &SC) {
+        let menu = &self.gauge_menu;
+        self.display
+            .0
+            .send(DisplayCommand::GaugeSetup {
+                gauge: sensors.gauges().get(menu.gauge).cloned(),
+                selected: menu.item,
+                editing: menu.editing,
+                atmosphere: menu.atmosphere,
+                switch_points: menu.switch_points,
+                message: menu.message.clone(),
+            })
+            .unwrap();
+    }
+
+    /// Подменю "Образцовый датчик": вращение - выбор пункта, нажатие - действие.
+    /// Юстировка и запись точки переключения - в два нажатия, между ними вращение
+    /// меняет значение (для нуля - отменяет).
+    fn process_gauge_setup<SC: SensorsControl>(&mut self, cmd: EncoderCommand, sensors: &mut SC) {
+        if cmd == EncoderCommand::Push {
+            return;
+        }
+
+        let menu = &mut self.gauge_menu;
+        if !menu.message.is_empty() {
+            menu.message.clear();
+            self.send_gauge_menu(sensors);
+            return;
+        }
+
+        let gauges = sensors.gauges().len();
+        let rotation = if cmd == EncoderCommand::Increment {
+            1.0
+        } else {
+            -1.0
+        };
+        match (cmd, menu.editing) {
+            (EncoderCommand::Pull, _) if menu.item == GaugeMenuItem::Back => {
+                self.current_state = State::Setup;
+                self.current_setup_parameter = SelectedParameter::SaveAndExit;
+                self.send_setup_menu();
+                return;
+            }
+            (EncoderCommand::Pull, _) if gauges == 0 => {
+                menu.message = vec!["Нет датчиков".to_string()];
+            }
+            (EncoderCommand::Pull, false) => match menu.item {
+                GaugeMenuItem::Gauge => {
+                    menu.gauge = (menu.gauge + 1) % gauges;
+                    menu.switch_points = [None; gauge::SWITCH_POINTS as usize];
+                }
+                GaugeMenuItem::Info => {
+                    menu.message =
+                        Self::gauge_message(sensors.gauge_command(menu.gauge, GaugeCommand::Info));
+                }
+                GaugeMenuItem::AdjustZero | GaugeMenuItem::AdjustAtmosphere => menu.editing = true,
+                item => {
+                    if let Some(i) = item.switch_point() {
+                        // редактирование начинается с текущего значения в приборе
+                        match sensors.gauge_command(menu.gauge, GaugeCommand::GetSwitchPoint(i)) {
+                            Ok(GaugeReply::SwitchPoint { p_mbar, .. }) => {
+                                menu.switch_points[i as usize - 1] = Some(p_mbar);
+                                menu.editing = true;
+                            }
+                            other => menu.message = Self::gauge_message(other),
+                        }
+                    }
+                }
+            },
+            (EncoderCommand::Pull, true) => {
+                menu.editing = false;
+                let command = match menu.item {
+                    GaugeMenuItem::AdjustZero => Some(GaugeCommand::AdjustZero),
+                    GaugeMenuItem::AdjustAtmosphere => {
+                        Some(GaugeCommand::AdjustAtmosphere(menu.atmosphere))
+                    }
+                    item => item.switch_point().and_then(|i| {
+                        Some(GaugeCommand::SetSwitchPoint(
+                            i,
+                            menu.switch_points[i as usize - 1]?,
+                        ))
+                    }),
+                };
+                if let Some(command) = command {
+                    let reply = sensors.gauge_command(menu.gauge, command);
+                    if let Ok(GaugeReply::SwitchPoint { index, p_mbar }) = reply {
+                        if let Some(p) = menu.switch_points.get_mut(index as usize - 1) {
+                            p.replace(p_mbar);
+                        }
+                    }
+                    menu.message = Self::gauge_message(reply);
+                }
+            }
+            (_, false) => {
+                let count = GaugeMenuItem::Count as i32;
+                menu.item = num::FromPrimitive::from_i32(
+                    (menu.item as i32 + rotation as i32).rem_euclid(count),
+                )
+                .unwrap();
+            }
+            (_, true) => match menu.item {
+                GaugeMenuItem::AdjustAtmosphere => {
+                    menu.atmosphere = (menu.atmosphere.round() + rotation)
+                        .clamp(ATMOSPHERE_LIMITS.min, ATMOSPHERE_LIMITS.max)
+                }
+                item => match item.switch_point() {
+                    Some(i) => {
+                        let p = &mut menu.switch_points[i as usize - 1];
+                        *p = p.map(|p| {
+                            Self::step_125(p, SWITCH_POINT_LIMITS.min, SWITCH_POINT_LIMITS.max, cmd)
+                        });
+                    }
+                    None => menu.editing = false,
+                },
+            },
+        }
+
+        self.send_gauge_menu(sensors);
+    }
+
+    fn send_diagnostics<SC: SensorsControl>(&self, sensors: &mut SC) {
+        self.assign_sctb(sensors);
+        self.display
+            .0
+            .send(DisplayCommand::Diagnostics {
+                dut: sensors.dut_info(self.diagnostics_dut),
+                channel: (self.parameters.duts().len() > 1).then_some(self.diagnostics_dut + 1),
+            })
+            .unwrap();
+    }
+
+    /// Экран "Диагностика": вращение - следующий DUT (или перечитать единственный), нажатие -
+    /// назад в настройки
+    fn process_diagnostics<SC: SensorsControl>(&mut self, cmd: EncoderCommand, sensors: &mut SC) {
+        match cmd {
+            EncoderCommand::Pull => {
+                self.current_state = State::Setup;
+                self.current_setup_parameter = SelectedParameter::ReferenceGauge;
+                self.send_setup_menu();
+            }
+            EncoderCommand::Increment | EncoderCommand::Decrement => {
+                let duts = self.parameters.duts().len();
+                self.diagnostics_dut = if cmd == EncoderCommand::Increment {
+                    (self.diagnostics_dut + 1) % duts
+                } else {
+                    (self.diagnostics_dut + duts - 1) % duts
+                };
+                self.send_diagnostics(sensors)
+            }
+            EncoderCommand::Push => {}
+        }
+    }
+
+    /// Строки сообщения с результатом команды датчику
+    fn gauge_message(reply: Result<GaugeReply, GaugeError>) -> Vec<String> {
+        let text = |v: Option<String>| v.unwrap_or_else(|| "-".to_string());
+        match reply {
+            Ok(GaugeReply::Done) => vec!["Готово".to_string()],
+            Ok(GaugeReply::Info(info)) => vec![
+                format!("S/N {}", text(info.serial)),
+                format!("ПО {}", text(info.firmware)),
+                format!("Наработка {} ч", text(info.hours.map(|h| h.to_string()))),
+                format!("Единица {}", text(info.unit)),
+            ],
+            Ok(GaugeReply::SwitchPoint { index, p_mbar }) => {
+                vec![format!("Точка {index}: {p_mbar:.1e} mbar")]
+            }
+            Err(GaugeError::Unsupported) => vec!["Не поддерживается".to_string()],
+            Err(GaugeError::NoResponse) => vec!["Нет ответа".to_string()],
+            Err(GaugeError::Fault) => vec!["Ошибка линии".to_string()],
+        }
+    }
+
+    /// Следующий найденный на шине адрес SCTB, кроме занятых другими ролями. Если искать
+    /// не из чего - соседний адрес.
+    fn step_sctb_address(&self, current: u32, taken: &[u32], cmd: EncoderCommand) -> u32 {
+        let found = self
+            .sctb_devices
+            .iter()
+            .flatten()
+            .map(|d| d.address as u32)
+            .filter(|a| !taken.contains(a))
+            .collect::<Vec<_>>();
+        let (min, max) = (*SCAN_ADDRESSES.start() as u32, *SCAN_ADDRESSES.end() as u32);
+
+        match (cmd, found.first(), found.last()) {
+            (EncoderCommand::Increment, Some(&first), _) => found
+                .iter()
+                .copied()
+                .find(|&a| a > current)
+                .unwrap_or(first),
+            (EncoderCommand::Decrement, _, Some(&last)) => found
+                .iter()
+                .rev()
+                .copied()
+                .find(|&a| a < current)
+                .unwrap_or(last),
+            (EncoderCommand::Increment, None, _) => (current + 1).min(max),
+            (EncoderCommand::Decrement, _, None) => current.saturating_sub(1).max(min),
+            _ => current,
+        }
+    }
+
+    /// Шаг давления энкодером: 1 -> 0.1 -> 0.01 в зависимости от значения
+    fn step_pressure(value: f32, cmd: EncoderCommand) -> f32 {
+        Self::step_decimal(value, MIN_PREASURE, MAX_PRESSURE, cmd)
+    }
+
+    fn step_decimal(value: f32, min: f32, max: f32, cmd: EncoderCommand) -> f32 {
+        match cmd {
+            EncoderCommand::Increment if value < max => {
+                let step = Precission::increment_value(value);
+                ((value / step).round() + 1.0) * step
+            }
+            EncoderCommand::Decrement if value > min => {
+                let step = Precission::decrement_value(value);
+                ((value / step).round() - 1.0) * step
+            }
+            _ => value,
+        }
+    }
+
+    /// Шаг одной из границ допуска, min не больше max
+    fn step_range(range: &mut Range, upper: bool, step: f32, limits: Range, cmd: EncoderCommand) {
+        let delta = match cmd {
+            EncoderCommand::Increment => 1.0,
+            EncoderCommand::Decrement => -1.0,
+            _ => return,
+        };
+
+        if upper {
+            range.max = (((range.max / step).round() + delta) * step).clamp(range.min, limits.max);
+        } else {
+            range.min = (((range.min / step).round() + delta) * step).clamp(limits.min, range.max);
+        }
+    }
+
+    /// Шаг по ряду 1-2-5: 1e-3 -> 2e-3 -> 5e-3 -> 1e-2
+    fn step_125(value: f32, min: f32, max: f32, cmd: EncoderCommand) -> f32 {
+        let mut exp = value.log10().floor();
+        let mut mantissa = (value / 10f32.powf(exp)).round() as u32;
+        if mantissa >= 10 {
+            mantissa /= 10;
+            exp += 1.0;
+        }
+
+        let (mantissa, exp) = match cmd {
+            EncoderCommand::Increment => match mantissa {
+                0..=1 => (2, exp),
+                2..=4 => (5, exp),
+                _ => (1, exp + 1.0),
+            },
+            EncoderCommand::Decrement => match mantissa {
+                0..=1 => (5, exp - 1.0),
+                2..=4 => (1, exp),
+                _ => (2, exp),
+            },
+            _ => return value,
+        };
+        (mantissa as f32 * 10f32.powf(exp)).clamp(min, max)
+    }
+
+    /// Наклоны F(t) и P(t) за скользящее окно удержания.
+    /// None, пока окно не заполнено на всю длину settle_window_s.
+    fn update_drift(&mut self, now: Duration, p: f32, f: f32) -> Option<Drift> {
+        let window = Duration::from_secs(self.parameters.settle_window_s as u64);
+
+        if p.is_finite() && f.is_finite() {
+            self.hold_window.push_back((now, p, f));
+        }
+        while let Some(&(time, _, _)) = self.hold_window.front() {
+            if now - time > window {
+                self.hold_window.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let &(first, _, _) = self.hold_window.front()?;
+        if self.hold_window.len() < 3 || (now - first).as_secs_f32() < window.as_secs_f32() * 0.9 {
+            return None;
+        }
+
+        let slope = |value: fn(&(Duration, f32, f32)) -> f32| {
+            let data = self
+                .hold_window
+                .iter()
+                .map(|s| ((s.0 - first).as_secs_f32(), value(s)))
+                .collect::<Vec<_>>();
+            linear_regression(&data).k
+        };
+
+        Some(Drift {
+            df_dt: slope(|s| s.2),
+            dp_dt: slope(|s| s.1),
+        })
+    }
+
+    /// Точка в историю каждого назначенного DUT, не ответивший канал пропускается
+    fn push_history(&mut self, p: f32, f: [f32; MAX_DUTS]) {
+        if !p.is_finite() {
+            return;
+        }
+
+        self.history
+            .resize_with(self.parameters.duts().len(), Vec::new);
+        for (history, f) in self.history.iter_mut().zip(f) {
+            if !f.is_finite() {
+                continue;
+            }
+
+            if history.len() == MAX_HISTORY {
+                // прореживание, чтобы сохранить весь диапазон давлений
+                let mut i = 0;
+                history.retain(|_| {
+                    i += 1;
+                    i % 2 == 0
+                });
+            }
+            history.push((p, f));
+        }
+    }
+}
+
+/// Среднее конечных значений, NaN - таких нет
+fn mean(values: impl Iterator<Item = f32>) -> f32 {
+    let (sum, n) = values
+        .filter(|v| v.is_finite())
+        .fold((0.0f64, 0), |(sum, n), v| (sum + v as f64, n + 1));
+    if n > 0 {
+        (sum / n as f64) as f32
+    } else {
+        f32::NAN
+    }
+}
+
+/// Чувствительность по двум точкам (P, F), Hz/mmHg
+pub fn sensivity(initial_point: (f32, f32), last_point: (f32, f32)) -> f32 {
+    let delta_p = initial_point.0 - last_point.0;
+    let delta_f = initial_point.1 - last_point.1;
+    delta_f / delta_p
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            update_period_ms: 100,
+            try_use_alternative_sensor: false,
+            wait_time_s: 10,
+            curve_points_count: 3,
+            curve_points: [100.0, 10.0, 1.0, 0.1, 0.01],
+            settle_enabled: false,
+            settle_window_s: 5,
+            settle_df_max: 0.05,
+            settle_dp_max: 0.01,
+            chamber_volume_ml: 500,
+            leak_time_s: 30,
+            leak_limit: 1e-3,
+            tolerances_enabled: false,
+            tolerances: Tolerances::default(),
+            stream_format: StreamFormat::Off,
+            write_calibration: false,
+            sctb_reference: 15,
+            dut_count: 1,
+            sctb_duts: [11, 12, 13, 14],
+            auto_start: false,
+        }
+    }
+}
+
+impl Parameters {
+    /// Активные уставки режима "Кривая"
+    pub fn curve_points(&self) -> &[f32] {
+        &self.curve_points[..(self.curve_points_count as usize).min(MAX_CURVE_POINTS)]
+    }
+
+    /// Адреса опрашиваемых DUT
+    pub fn duts(&self) -> &[u32] {
+        &self.sctb_duts[..(self.dut_count as usize).clamp(1, MAX_DUTS)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calibration::WriteError;
+    use crate::i2c_sensor::{Calibration, I2CSensor, SensorInfo, LEGACY_VERSION};
+    use crate::mock::{ManualClock, MemoryStorage, MockI2c, MockSensors, MockValve};
+
+    /// Контроллер на хосте: часы, клапан и опрос в памяти
+    pub(crate) struct Bench {
+        pub ctrl: Controller<MemoryStorage, ManualClock>,
+        pub sensors: MockSensors,
+        pub valve: MockValve,
+        pub clock: ManualClock,
+        display: Receiver<DisplayCommand>,
+        replies: Receiver<String>,
+    }
+
+    impl Bench {
+        pub fn new(parameters: Parameters) -> Self {
+            let clock = ManualClock::new();
+            let mut ctrl = Controller::new(MemoryStorage::default(), clock.clone());
+            ctrl.parameters = parameters;
+            let display = ctrl.display_chanel();
+            let (_, replies) = ctrl.remote_chanel();
+            Self {
+                ctrl,
+                sensors: MockSensors::default(),
+                valve: MockValve::default(),
+                clock,
+                display,
+                replies,
+            }
+        }
+
+        pub fn poll(&mut self) {
+            self.ctrl.poll(&mut self.sensors, &mut self.valve);
+            // экран не читается, канал не должен переполняться
+            while self.display.try_recv().is_ok() {}
+        }
+
+        /// Команда как со строки UART, ответ контроллера
+        pub fn remote(&mut self, line: &str) -> String {
+            self.ctrl.remote_chanel().0.send(line.to_string()).unwrap();
+            self.poll();
+            self.replies.try_recv().unwrap()
+        }
+
+        /// Отсчет SCTB, затем часы вперед на период опроса
+        pub fn sample(&mut self, p: f32, f: [f32; MAX_DUTS]) {
+            self.ctrl
+                .sensor_chanel()
+                .send(SensorResult::SctbSensorResult { f, p, t: 25.0 })
+                .unwrap();
+            self.poll();
+            self.clock.advance(Duration::from_millis(
+                self.ctrl.parameters.update_period_ms as u64,
+            ));
+        }
+
+        /// Запрос Modbus, ответ контроллера
+        pub fn modbus(&mut self, request: Request) -> Result<Response, Exception> {
+            let (reply, response) = channel::bounded(1);
+            self.ctrl.modbus_chanel().send((request, reply)).unwrap();
+            self.poll();
+            response.try_recv().unwrap()
+        }
+
+        pub fn state(&self) -> State {
+            self.ctrl.current_state
+        }
+    }
+
+    /// Частота DUT с чувствительностью 2 Hz/mmHg
+    pub(crate) fn dut_f(p: f32) -> [f32; MAX_DUTS] {
+        [30000.0 + 2.0 * p; MAX_DUTS]
+    }
+
+    /// Откачка от атмосферы до `p_end` за `steps` отсчетов
+    fn pump_down(bench: &mut Bench, p_end: f32, steps: usize) {
+        for i in 0..=steps {
+            let p = 760.0 * (p_end / 760.0).powf(i as f32 / steps as f32);
+            bench.sample(p, dut_f(p));
+        }
+    }
+
+    /// Auto от атмосферы до конца выдержки
+    fn auto_run(bench: &mut Bench) {
+        bench.remote("START auto");
+        pump_down(bench, 0.9, 100);
+        while bench.state() == State::Measuring {
+            bench.sample(0.9, dut_f(0.9));
+        }
+    }
+
+    /// Датчик SCTB на шине мока с серийным номером `serial`
+    fn add_sctb(bench: &mut Bench, address: u8, version: u8, serial: u32) {
+        bench
+            .sensors
+            .sctb
+            .get_or_insert_with(MockI2c::default)
+            .add_sctb(
+                address,
+                version,
+                [f32::NAN, 25.0, 30000.0, f32::NAN],
+                SensorInfo {
+                    serial,
+                    firmware: (1, 4),
+                    period_ms: 100,
+                },
+                Calibration {
+                    f0: 30000.0,
+                    coeffs: [0.0, 0.5, 0.0, 0.0],
+                },
+            );
+    }
+
+    #[test]
+    fn auto_trigger_below_threshold() {
+        let mut bench = Bench::new(Parameters::default());
+        assert!(bench.remote("START auto").starts_with("OK"));
+        assert_eq!(bench.state(), State::Measuring);
+        assert!(bench.sensors.period.is_some());
+
+        pump_down(&mut bench, 2.0, 50);
+        assert_eq!(bench.valve.state, Some(KlapanState::Vacuum));
+        assert!(bench.ctrl.start_waiting_time.is_none());
+
+        bench.sample(0.9, dut_f(0.9));
+        assert!(bench.ctrl.start_waiting_time.is_some());
+    }
+
+    #[test]
+    fn manual_mode_does_not_trigger() {
+        let mut bench = Bench::new(Parameters::default());
+        bench.remote("START manual");
+        pump_down(&mut bench, 0.1, 50);
+        assert!(bench.ctrl.start_waiting_time.is_none());
+        assert_eq!(bench.state(), State::Measuring);
+    }
+
+    #[test]
+    fn modbus_coils_start_and_abort() {
+        let mut bench = Bench::new(Parameters::default());
+        let start_curve = Request::WriteSingleCoil {
+            address: 2,
+            value: true,
+        };
+        assert_eq!(
+            bench.modbus(start_curve.clone()),
+            Ok(Response::Written {
+                address: 2,
+                value: 0xFF00
+            })
+        );
+        assert_eq!(bench.state(), State::Measuring);
+        assert_eq!(
+            bench.modbus(Request::ReadCoils {
+                address: 0,
+                count: 4
+            }),
+            Ok(Response::Coils(vec![false, false, true, false]))
+        );
+        // параметры во время измерения не пишутся
+        assert_eq!(
+            bench.modbus(Request::WriteSingleRegister {
+                address: 0,
+                value: 0
+            }),
+            Err(Exception::SlaveDeviceBusy)
+        );
+        assert_eq!(bench.modbus(start_curve), Err(Exception::SlaveDeviceBusy));
+
+        bench
+            .modbus(Request::WriteSingleCoil {
+                address: 4,
+                value: true,
+            })
+            .unwrap();
+        assert_eq!(bench.state(), State::Title);
+        assert!(bench.sensors.period.is_none());
+        assert_eq!(
+            bench.modbus(Request::WriteMultipleCoils {
+                address: 4,
+                values: vec![true; 3]
+            }),
+            Err(Exception::IllegalDataAddress)
+        );
+    }
+
+    #[test]
+    fn gauge_command_only_when_stopped() {
+        let mut bench = Bench::new(Parameters::default());
+        bench.sensors.gauges = vec!["Thyracont VSP206 @001".to_string()];
+        // драйвер мока настройку не поддерживает
+        assert_eq!(bench.remote("GAUGE 0 INFO"), "ERR gauge unsupported");
+        assert_eq!(bench.remote("GAUGE 1 INFO"), "ERR unknown_gauge 1");
+
+        bench.remote("START manual");
+        assert_eq!(bench.remote("GAUGE 0 ZERO"), "ERR busy measuring");
+    }
+
+    #[test]
+    fn hold_lasts_wait_time() {
+        let parameters = Parameters::default();
+        let wait_time = Duration::from_secs(parameters.wait_time_s as u64);
+        let mut bench = Bench::new(parameters);
+        bench.remote("START auto");
+        pump_down(&mut bench, 0.9, 50);
+        let start = bench.ctrl.start_waiting_time.unwrap();
+
+        while bench.state() == State::Measuring {
+            assert!(bench.clock.now() - start <= wait_time);
+            bench.sample(0.5, dut_f(0.5));
+        }
+        assert_eq!(bench.state(), State::Result);
+        assert!(bench.sensors.period.is_none());
+        assert_eq!(bench.valve.state, Some(KlapanState::Vacuum));
+        bench.poll();
+        assert_eq!(bench.valve.state, Some(KlapanState::Atmosphere));
+
+        let RunResult::Sensivity { hold, .. } = bench.ctrl.last_results[0].result else {
+            panic!("not a sensivity result");
+        };
+        assert!(!hold.settled);
+        assert_eq!(hold.duration, wait_time);
+    }
+
+    #[test]
+    fn hold_ends_when_settled() {
+        let parameters = Parameters {
+            settle_enabled: true,
+            ..Parameters::default()
+        };
+        let window = Duration::from_secs(parameters.settle_window_s as u64);
+        let mut bench = Bench::new(parameters);
+        bench.remote("START auto");
+        pump_down(&mut bench, 0.9, 50);
+
+        // частота еще дрейфует быстрее settle_df_max
+        for i in 0..30 {
+            bench.sample(0.9, [30001.8 + 0.1 * i as f32; MAX_DUTS]);
+        }
+        assert_eq!(bench.state(), State::Measuring);
+
+        while bench.state() == State::Measuring {
+            bench.sample(0.9, [30004.8; MAX_DUTS]);
+        }
+        let RunResult::Sensivity { hold, .. } = bench.ctrl.last_results[0].result else {
+            panic!("not a sensivity result");
+        };
+        assert!(hold.settled);
+        assert!(hold.duration > Duration::from_secs(3) + window / 2);
+        assert!(hold.duration < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn sensivity_from_pump_down() {
+        let mut bench = Bench::new(Parameters::default());
+        bench.remote("START auto");
+        pump_down(&mut bench, 0.9, 100);
+        while bench.state() == State::Measuring {
+            bench.sample(0.9, dut_f(0.9));
+        }
+
+        let RunResult::Sensivity {
+            p, f, sensivity, ..
+        } = bench.ctrl.last_results[0].result
+        else {
+            panic!("not a sensivity result");
+        };
+        assert_eq!(p, 0.9);
+        assert_eq!(f, dut_f(0.9)[0]);
+        assert!((sensivity - 2.0).abs() < 1e-3, "{sensivity}");
+    }
+
+    /// Проверка на течь с ростом давления `rise` mmHg/s в отсеченной камере
+    fn leak_test(rise: f32) -> LeakResult {
+        let mut bench = Bench::new(Parameters::default());
+        bench.remote("START leak");
+        pump_down(&mut bench, 2.0, 50);
+
+        let mut p = 0.9;
+        while bench.state() == State::Measuring {
+            bench.sample(p, dut_f(p));
+            if bench.valve.state == Some(KlapanState::Isolate) {
+                p += rise * bench.ctrl.parameters.update_period_ms as f32 / 1000.0;
+            }
+        }
+        let RunResult::Leak(result) = bench.ctrl.last_results[0].result else {
+            panic!("not a leak result");
+        };
+        result
+    }
+
+    #[test]
+    fn leak_rate_from_pressure_rise() {
+        // 500 ml, 1e-3 mmHg/s = 1.333e-3 mbar/s -> 6.67e-4 mbar*l/s
+        let result = leak_test(1e-3);
+        assert!((result.dp_dt - 1.333e-3).abs() < 1e-5, "{}", result.dp_dt);
+        assert!(
+            (result.leak_rate - 6.67e-4).abs() < 1e-5,
+            "{}",
+            result.leak_rate
+        );
+        assert_eq!(result.p_start, 0.9);
+        assert!(result.passed);
+        assert_eq!(result.duration, Duration::from_secs(30));
+
+        assert!(!leak_test(2e-3).passed);
+    }
+
+    #[test]
+    fn curve_points_near_setpoints() {
+        let parameters = Parameters::default();
+        let setpoints = parameters.curve_points().to_vec();
+        let mut bench = Bench::new(parameters);
+        bench.remote("START curve");
+
+        // камера: на откачке давление падает, отсеченная - медленно растет
+        let mut p = 760.0f32;
+        for _ in 0..10_000 {
+            if bench.state() != State::Measuring {
+                break;
+            }
+            bench.sample(p, dut_f(p));
+            match bench.valve.state {
+                Some(KlapanState::Vacuum) => p *= 0.97,
+                Some(KlapanState::Isolate) => p += 1e-4,
+                _ => {}
+            }
+        }
+        assert_eq!(bench.state(), State::Result);
+
+        let RunResult::Curve(curve) = &bench.ctrl.last_results[0].result else {
+            panic!("not a curve result");
+        };
+        assert_eq!(curve.points.len(), setpoints.len());
+        for (point, setpoint) in curve.points.iter().zip(setpoints) {
+            assert!(
+                (point.p - setpoint).abs() < setpoint * 0.1,
+                "{} at {setpoint}",
+                point.p
+            );
+            assert!((point.f - dut_f(point.p)[0]).abs() < 1e-2);
+        }
+        for s in &curve.segments {
+            assert!((s - 2.0).abs() < 1e-2, "{s}");
+        }
+    }
+
+    #[test]
+    fn dut_info_attached_to_result() {
+        let mut bench = Bench::new(Parameters::default());
+        add_sctb(&mut bench, 11, 2, 0x0001_E240);
+        auto_run(&mut bench);
+
+        let dut = bench.ctrl.last_results[0].dut.unwrap();
+        assert_eq!(dut.address, 11);
+        assert_eq!(dut.info.serial, 0x0001_E240);
+        assert_eq!(dut.calibration.coeffs[1], 0.5);
+        let reply = bench.remote("RESULT");
+        assert!(
+            reply.contains(" dut_addr=11 dut_serial=123456 dut_fw=1.4 dut_period_ms=100"),
+            "{reply}"
+        );
+        assert_eq!(
+            bench.modbus(Request::ReadInputRegisters {
+                address: 40,
+                count: 4
+            }),
+            Ok(Response::Registers(vec![0x0001, 0xE240, 0x0104, 100]))
+        );
+
+        // штатная прошивка без блока 0x10: результат без DUT
+        add_sctb(&mut bench, 11, LEGACY_VERSION, 0);
+        bench.remote("ABORT");
+        auto_run(&mut bench);
+        assert!(bench.ctrl.last_results[0].dut.is_none());
+        assert!(!bench.remote("RESULT").contains("dut_serial"));
+        assert_eq!(
+            bench.modbus(Request::ReadInputRegisters {
+                address: 40,
+                count: 2
+            }),
+            Ok(Response::Registers(vec![0, 0]))
+        );
+    }
+
+    #[test]
+    fn calibration_written_after_run() {
+        let parameters = Parameters {
+            write_calibration: true,
+            ..Parameters::default()
+        };
+        let mut bench = Bench::new(parameters);
+        add_sctb(&mut bench, 11, 2, 1);
+        auto_run(&mut bench);
+
+        let Some(CalibrationOutcome::Written(fit)) = bench.ctrl.last_results[0].calibration else {
+            panic!("calibration not written");
+        };
+        // F = 30000 + 2P: P = 0.9 + 0.5 (F - F0)
+        assert_eq!(fit.calibration.f0, dut_f(0.9)[0]);
+        assert!((fit.calibration.coeffs[1] - 0.5).abs() < 1e-3);
+        let bus = bench.sensors.sctb.as_mut().unwrap();
+        assert_eq!(
+            I2CSensor::new(11).read_calibration(bus).unwrap(),
+            fit.calibration
+        );
+        assert!(bench
+            .remote("RESULT")
+            .contains(" calibration=written cal_f0="));
+        // страница калибровки перед результатом
+        assert_eq!(bench.ctrl.result_pages(), 2);
+    }
+
+    #[test]
+    fn calibration_read_back_mismatch() {
+        let parameters = Parameters {
+            write_calibration: true,
+            ..Parameters::default()
+        };
+        let mut bench = Bench::new(parameters);
+        add_sctb(&mut bench, 11, 2, 1);
+        bench.sensors.sctb.as_mut().unwrap().read_only.push(11);
+        auto_run(&mut bench);
+
+        let Some(CalibrationOutcome::Failed {
+            error: WriteError::Mismatch(read),
+            ..
+        }) = bench.ctrl.last_results[0].calibration
+        else {
+            panic!("mismatch not detected");
+        };
+        assert_eq!(read.coeffs, [0.0, 0.5, 0.0, 0.0]);
+        assert!(bench
+            .remote("RESULT")
+            .contains(" calibration=mismatch cal_f0="));
+
+        // без шины SCTB драйвер писать не умеет
+        let mut bench = Bench::new(bench.ctrl.parameters);
+        auto_run(&mut bench);
+        assert!(matches!(
+            bench.ctrl.last_results[0].calibration,
+            Some(CalibrationOutcome::Failed {
+                error: WriteError::Unsupported,
+                ..
+            })
+        ));
+    }
+
+    /// Поиск SCTB на следующем опросе
+    fn rescan(bench: &mut Bench) {
+        bench.clock.advance(SCAN_PERIOD);
+        bench.poll();
+    }
+
+    #[test]
+    fn scan_detects_dut_plug_and_removal() {
+        let mut bench = Bench::new(Parameters::default());
+        assert!(bench.remote("READ").ends_with(" dut=unknown"));
+
+        add_sctb(&mut bench, 15, LEGACY_VERSION, 0);
+        bench.poll();
+        assert!(bench.remote("READ").ends_with(" dut=absent"));
+
+        add_sctb(&mut bench, 11, 2, 1);
+        // до истечения периода шина не опрашивается
+        bench.clock.advance(SCAN_PERIOD / 2);
+        bench.poll();
+        assert_eq!(bench.ctrl.dut_present(), Some(false));
+        rescan(&mut bench);
+        assert!(bench.remote("READ").ends_with(" dut=present"));
+        assert_eq!(
+            bench.ctrl.sctb_devices.as_deref(),
+            Some(
+                &[
+                    SctbDevice {
+                        address: 11,
+                        version: 2
+                    },
+                    SctbDevice {
+                        address: 15,
+                        version: LEGACY_VERSION
+                    }
+                ][..]
+            )
+        );
+        assert_eq!(
+            bench.modbus(Request::ReadInputRegisters {
+                address: 14,
+                count: 1
+            }),
+            Ok(Response::Registers(vec![2]))
+        );
+        // auto_start выключен
+        assert_eq!(bench.state(), State::Title);
+
+        bench.sensors.sctb.as_mut().unwrap().devices.remove(&11);
+        rescan(&mut bench);
+        assert_eq!(bench.ctrl.dut_present(), Some(false));
+        assert_eq!(
+            bench.modbus(Request::ReadInputRegisters {
+                address: 14,
+                count: 1
+            }),
+            Ok(Response::Registers(vec![1]))
+        );
+    }
+
+    #[test]
+    fn auto_start_on_plugged_dut() {
+        let parameters = Parameters {
+            auto_start: true,
+            dut_count: 2,
+            ..Parameters::default()
+        };
+        let mut bench = Bench::new(parameters);
+        add_sctb(&mut bench, 11, 2, 1);
+        bench.poll();
+        assert_eq!(bench.ctrl.duts_found(), Some(1));
+
+        // DUT, который уже стоял при включении, Auto не запускает
+        let mut bench_present = Bench::new(parameters);
+        add_sctb(&mut bench_present, 11, 2, 1);
+        add_sctb(&mut bench_present, 12, 2, 2);
+        bench_present.poll();
+        assert_eq!(bench_present.ctrl.dut_present(), Some(true));
+        assert_eq!(bench_present.state(), State::Title);
+
+        add_sctb(&mut bench, 12, 2, 2);
+        rescan(&mut bench);
+        assert_eq!(bench.state(), State::Measuring);
+        assert!(bench.sensors.period.is_some());
+        assert_eq!(bench.ctrl.run_duts.len(), 2);
+        assert!(bench.ctrl.run_duts.iter().all(Option::is_some));
+
+        // во время измерения шина не сканируется
+        bench.sensors.sctb.as_mut().unwrap().devices.remove(&12);
+        rescan(&mut bench);
+        assert_eq!(bench.ctrl.dut_present(), Some(true));
+    }
+
+    /// Поворот энкодера на экране результата, номер DUT на показанной странице
+    fn flip(bench: &mut Bench, cmd: EncoderCommand) -> (Option<usize>, f32) {
+        bench.ctrl.command_chanel().send(cmd).unwrap();
+        bench.ctrl.poll(&mut bench.sensors, &mut bench.valve);
+        match bench.display.try_iter().last() {
+            Some(DisplayCommand::Result { dut, sensivity, .. }) => (dut, sensivity),
+            _ => panic!("not a result page"),
+        }
+    }
+
+    #[test]
+    fn parallel_duts_with_dead_channel() {
+        let parameters = Parameters {
+            dut_count: 3,
+            ..Parameters::default()
+        };
+        let mut bench = Bench::new(parameters);
+        // DUT 1 - 2 Hz/mmHg, DUT 2 не отвечает, DUT 3 - 3 Hz/mmHg, канал 4 не назначен
+        let f = |p: f32| [30000.0 + 2.0 * p, f32::NAN, 31000.0 + 3.0 * p, 0.0];
+        bench.remote("START auto");
+        for i in 0..=100 {
+            let p = 760.0 * (0.9f32 / 760.0).powf(i as f32 / 100.0);
+            bench.sample(p, f(p));
+        }
+        while bench.state() == State::Measuring {
+            bench.sample(0.9, f(0.9));
+        }
+
+        let results = &bench.ctrl.last_results;
+        assert_eq!(results.len(), 3);
+        let sensivities = results
+            .iter()
+            .map(|r| match r.result {
+                RunResult::Sensivity { sensivity, f, .. } => (sensivity, f),
+                _ => panic!("not a sensivity result"),
+            })
+            .collect::<Vec<_>>();
+        assert!((sensivities[0].0 - 2.0).abs() < 1e-3, "{sensivities:?}");
+        assert!(sensivities[1].0.is_nan() && sensivities[1].1.is_nan());
+        assert!((sensivities[2].0 - 3.0).abs() < 1e-3, "{sensivities:?}");
+        assert_eq!(sensivities[2].1, f(0.9)[2]);
+
+        let reply = bench.remote("RESULT 2");
+        assert!(reply.contains(" sensivity=NaN "), "{reply}");
+        assert!(reply.contains(" channel=2 channels=3"), "{reply}");
+        assert!(bench.remote("RESULT").contains(" channel=1 channels=3"));
+        assert!(bench.remote("RESULT 3").contains(" channel=3 channels=3"));
+        assert_eq!(bench.remote("RESULT 5"), "ERR unknown_dut 5");
+
+        // страницы по DUT по кругу
+        assert_eq!(bench.ctrl.result_pages(), 3);
+        let (dut, sensivity) = flip(&mut bench, EncoderCommand::Increment);
+        assert_eq!(dut, Some(2));
+        assert!(sensivity.is_nan());
+        assert_eq!(flip(&mut bench, EncoderCommand::Increment).0, Some(3));
+        assert_eq!(flip(&mut bench, EncoderCommand::Increment).0, Some(1));
+        assert_eq!(flip(&mut bench, EncoderCommand::Decrement).0, Some(3));
+    }
+}
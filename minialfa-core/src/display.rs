@@ -0,0 +1,1808 @@
+use std::collections::VecDeque;
+
+use embedded_graphics::{
+    mono_font::{self, MonoTextStyle, MonoTextStyleBuilder},
+    pixelcolor::BinaryColor,
+    prelude::{Dimensions, DrawTarget, Point, Size},
+    primitives::{Line, Primitive, PrimitiveStyle, Rectangle, Triangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+    Drawable,
+};
+
+use num::{rational::Ratio, Num};
+
+use ssd1309::prelude::GraphicsMode;
+
+use crate::calibration::{CalibrationOutcome, WriteError};
+use crate::controller::{
+    self, CurveResult, DisplayCommand, Drift, GaugeMenuItem, HoldResult, LeakResult,
+    SelectedParameter,
+};
+use crate::i2c_sensor::SctbInfo;
+use crate::linear_regression::RegressionFit;
+use crate::profile;
+use crate::stream::StreamFormat;
+use crate::verdict::Verdict;
+
+#[allow(unused)]
+use crate::support::print_time_of;
+
+/// Дисплей с буфером кадра, который нужно явно отправить на экран
+pub trait Display: DrawTarget<Color = BinaryColor> {
+    fn flush(&mut self) -> Result<(), Self::Error>;
+}
+
+impl<DI> Display for GraphicsMode<DI>
+where
+    DI: display_interface::WriteOnlyDataCommand,
+{
+    fn flush(&mut self) -> Result<(), display_interface::DisplayError> {
+        GraphicsMode::flush(self)
+    }
+}
+
+/// История измерений для графиков, живет между кадрами
+pub struct DisplayState {
+    history: VecDeque<f32>,
+    f_fistory: Vec<(f32, f32)>,
+}
+
+impl DisplayState {
+    pub fn new<D: Dimensions>(display: &D) -> Self {
+        Self {
+            history: VecDeque::with_capacity(display.bounding_box().size.width as usize),
+            f_fistory: Vec::new(),
+        }
+    }
+}
+
+pub fn dispaly_thread<D>(
+    mut disp: D,
+    disp_channel: crossbeam::channel::Receiver<DisplayCommand>,
+) -> !
+where
+    D: Display,
+    D::Error: std::fmt::Debug,
+{
+    let mut state = DisplayState::new(&disp);
+
+    loop {
+        match disp_channel.recv() {
+            Ok(cmd) => draw_frame(&mut disp, cmd, &mut state).and_then(|_| disp.flush()),
+            Err(e) => {
+                println!("Display cmd recive error: {}", e);
+                Ok(())
+            }
+        }
+        .expect("Failed to draw frame");
+    }
+}
+
+/// Отрисовать кадр в любой [`DrawTarget`], без отправки на экран
+pub fn draw_frame<D>(
+    disp: &mut D,
+    cmd: DisplayCommand,
+    state: &mut DisplayState,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    match cmd {
+        DisplayCommand::TitleScreen {
+            option,
+            selected,
+            dut,
+        } => draw_title_screen(disp, option, selected, dut),
+        DisplayCommand::SetupMenu {
+            values,
+            selected,
+            precision,
+            profile,
+            cursor,
+            sctb,
+        } => draw_menu(
+            disp,
+            values,
+            precision,
+            selected,
+            &profile,
+            cursor,
+            sctb.as_deref(),
+        ),
+        DisplayCommand::ProfileSelect {
+            name,
+            index,
+            active,
+        } => draw_profile_select(disp, &name, index, active),
+        DisplayCommand::Measure {
+            f,
+            p,
+            threashold,
+            wait_time,
+            drift,
+        } => {
+            state.f_fistory = draw_measure(
+                disp,
+                MeasureView {
+                    f,
+                    p,
+                    threashold,
+                    wait_time,
+                    drift,
+                },
+                &mut state.history,
+                std::mem::take(&mut state.f_fistory),
+            )?;
+            Ok(())
+        }
+        DisplayCommand::Result {
+            f,
+            p,
+            t,
+            threashold: _,
+            sensivity,
+            fit,
+            hold,
+            verdict,
+            dut,
+        } => {
+            state.f_fistory = draw_result(
+                disp,
+                ResultView {
+                    f,
+                    p,
+                    t,
+                    sensivity,
+                    fit,
+                    hold,
+                    verdict,
+                    dut,
+                },
+                std::mem::take(&mut state.f_fistory),
+            )?;
+            Ok(())
+        }
+        DisplayCommand::CurveResult { result, page, dut } => {
+            draw_curve_result(disp, &result, page, dut)
+        }
+        DisplayCommand::LeakResult { result } => draw_leak_result(disp, &result),
+        DisplayCommand::GaugeSetup {
+            gauge,
+            selected,
+            editing,
+            atmosphere,
+            switch_points,
+            message,
+        } => draw_gauge_menu(
+            disp,
+            gauge.as_deref(),
+            selected,
+            editing,
+            atmosphere,
+            &switch_points,
+            &message,
+        ),
+        DisplayCommand::Diagnostics { dut, channel } => {
+            draw_diagnostics(disp, dut.as_ref(), channel)
+        }
+        DisplayCommand::CalibrationResult { outcome, dut } => {
+            draw_calibration_result(disp, &outcome, dut)
+        }
+    }
+}
+
+fn draw_title_screen<D>(
+    display: &mut D,
+    text: &'static str,
+    selected: bool,
+    dut: Option<(usize, usize)>,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    display.clear(BinaryColor::Off)?;
+
+    let big_font = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_10X20)
+        .text_color(BinaryColor::On)
+        .build();
+
+    let small_font_italic = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X13_ITALIC)
+        .text_color(BinaryColor::On)
+        .build();
+
+    let (display_w, _display_h) = {
+        let d = display.bounding_box().size;
+        (d.width as i32, d.height as i32)
+    };
+
+    Text::with_baseline("Мини-Альфа", Point::new(18, -3), big_font, Baseline::Top).draw(display)?;
+    Text::with_alignment(
+        "v2.0",
+        Point::new(
+            display_w / 2,
+            big_font.font.character_size.height as i32 + 8,
+        ),
+        big_font,
+        Alignment::Center,
+    )
+    .draw(display)?;
+
+    // DUT на шине, справа от версии
+    if let Some((found, count)) = dut {
+        let label = match (found, count) {
+            (1, 1) => "DUT".to_string(),
+            (_, 1) => "нет DUT".to_string(),
+            _ => format!("DUT {found}/{count}"),
+        };
+        Text::new(
+            label.as_str(),
+            Point::new(
+                display_w - 6 * label.chars().count() as i32,
+                big_font.font.character_size.height as i32 + 8,
+            ),
+            MonoTextStyle::new(&mono_font::iso_8859_5::FONT_6X10, BinaryColor::On),
+        )
+        .draw(display)?;
+    }
+
+    let text_w = {
+        let mt = Text::with_baseline(text, Point::zero(), big_font, Baseline::Top);
+        mt.bounding_box().size.width / 2 /* russian */
+    } as i32;
+    let begin_text = Text::with_baseline(
+        text,
+        Point::new(display_w / 2 - text_w / 2, 31),
+        {
+            if selected {
+                MonoTextStyleBuilder::from(&big_font)
+                    .background_color(BinaryColor::On)
+                    .text_color(BinaryColor::Off)
+                    .build()
+            } else {
+                big_font
+            }
+        },
+        Baseline::Top,
+    );
+
+    let button = gen_text_bounding_rect(&begin_text, true);
+
+    draw_arrows_to_rect(
+        display,
+        &button,
+        5,
+        -1,
+        5,
+        PrimitiveStyle::with_fill(BinaryColor::On),
+    )?;
+
+    button
+        .into_styled(if selected {
+            PrimitiveStyle::with_fill(BinaryColor::On)
+        } else {
+            PrimitiveStyle::with_stroke(BinaryColor::On, 1)
+        })
+        .draw(display)?;
+
+    begin_text.draw(display)?;
+
+    Rectangle::new(
+        Point::new(
+            0,
+            display.bounding_box().size.height as i32
+                - small_font_italic.font.character_size.height as i32
+                + 1,
+        ),
+        Size::new(
+            display_w as u32,
+            small_font_italic.font.character_size.height - 1,
+        ),
+    )
+    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+    .draw(display)?;
+
+    Text::new(
+        "СКТБ ЭлПА(c)",
+        Point::new(
+            (Ratio::<i32>::new(1, 4) * display_w).to_integer(),
+            display.bounding_box().size.height as i32 - 2,
+        ),
+        MonoTextStyleBuilder::from(&small_font_italic)
+            .background_color(BinaryColor::On)
+            .text_color(BinaryColor::Off)
+            .build(),
+    )
+    .draw(display)?;
+
+    Ok(())
+}
+
+/// ```text
+///  < ПРОФИЛЬ1 >
+/// #### 1/4 * ####
+/// ```
+fn draw_profile_select<D>(
+    display: &mut D,
+    name: &str,
+    index: usize,
+    active: bool,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    display.clear(BinaryColor::Off)?;
+
+    let big_font = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_10X20)
+        .text_color(BinaryColor::On)
+        .build();
+    let small_font_italic = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X13_ITALIC)
+        .text_color(BinaryColor::On)
+        .build();
+
+    let (display_w, display_h) = {
+        let d = display.bounding_box().size;
+        (d.width as i32, d.height as i32)
+    };
+
+    let char_w = big_font.font.character_size.width as i32;
+    let name_w = char_w * name.chars().count() as i32;
+    let text = Text::with_baseline(
+        name,
+        Point::new(display_w / 2 - name_w / 2, 16),
+        big_font,
+        Baseline::Top,
+    );
+
+    let mut button = text.bounding_box();
+    button.size.width = name_w as u32;
+    let button = gen_text_bounding_rect(&button, false);
+
+    draw_arrows_to_rect(
+        display,
+        &button,
+        5,
+        -1,
+        5,
+        PrimitiveStyle::with_fill(BinaryColor::On),
+    )?;
+    button
+        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+        .draw(display)?;
+    text.draw(display)?;
+
+    Rectangle::new(
+        Point::new(
+            0,
+            display_h - small_font_italic.font.character_size.height as i32 + 1,
+        ),
+        Size::new(
+            display_w as u32,
+            small_font_italic.font.character_size.height - 1,
+        ),
+    )
+    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+    .draw(display)?;
+
+    Text::with_alignment(
+        format!(
+            "Профиль {}/{}{}",
+            index + 1,
+            profile::MAX_PROFILES,
+            if active { " *" } else { "" }
+        )
+        .as_str(),
+        Point::new(display_w / 2, display_h - 2),
+        MonoTextStyleBuilder::from(&small_font_italic)
+            .background_color(BinaryColor::On)
+            .text_color(BinaryColor::Off)
+            .build(),
+        Alignment::Center,
+    )
+    .draw(display)?;
+
+    Ok(())
+}
+
+/// ```text
+/// Порог      1 mmHg
+/// Период     100 мс
+/// Сохранить и выйти
+///
+///      Настройки
+/// ```
+fn draw_menu<D>(
+    display: &mut D,
+    values: controller::Parameters,
+    precission: controller::Precission,
+    selected_parameter: SelectedParameter,
+    profile_name: &str,
+    name_cursor: usize,
+    sctb: Option<&[u8]>,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    #[allow(unused_imports)]
+    use embedded_graphics::Pixel;
+
+    display.clear(BinaryColor::Off)?;
+
+    let small_font_italic = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X13_ITALIC)
+        .text_color(BinaryColor::On)
+        .build();
+    let small_font = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X13)
+        .text_color(BinaryColor::On)
+        .build();
+    let small_font_selected = MonoTextStyleBuilder::from(&small_font)
+        .background_color(BinaryColor::On)
+        .text_color(BinaryColor::Off)
+        .build();
+
+    let (display_w, _display_h) = {
+        let d = display.bounding_box().size;
+        (d.width as i32, d.height as i32)
+    };
+
+    const ITEMS_Y_OFFSET: i32 = 0;
+    const LINE_SHIFT: i32 = 0;
+    // 4 строки над заголовком
+    const VISIBLE_ROWS: usize = 4;
+
+    // выбор датчика (PSensorSelect) пока не показывается
+    let mut rows = vec![
+        // имя рисуется отдельно, с курсором
+        (SelectedParameter::ProfileName, " Имя ".to_string(), None),
+        (
+            SelectedParameter::Threshold,
+            " Порог ".to_string(),
+            Some(format!(
+                "{:0.prec$} mmHg",
+                values.threshold,
+                prec = precission.value()
+            )),
+        ),
+        (
+            SelectedParameter::UpdatePeriodMs,
+            " Интервал ".to_string(),
+            Some(format!("{} мс", values.update_period_ms)),
+        ),
+        (
+            SelectedParameter::WaitTimeS,
+            " Ожидание ".to_string(),
+            Some(format!("{} с", values.wait_time_s)),
+        ),
+        (
+            SelectedParameter::SettleEnabled,
+            " Стабилиз. ".to_string(),
+            Some(
+                if values.settle_enabled {
+                    "Вкл"
+                } else {
+                    "Выкл"
+                }
+                .to_string(),
+            ),
+        ),
+    ];
+    if values.settle_enabled {
+        rows.extend([
+            (
+                SelectedParameter::SettleWindowS,
+                " Окно ".to_string(),
+                Some(format!("{} с", values.settle_window_s)),
+            ),
+            (
+                SelectedParameter::SettleDfMax,
+                " dF/dt ".to_string(),
+                Some(format!(
+                    "{:0.prec$} Hz/s",
+                    values.settle_df_max,
+                    prec = controller::Precission::from(values.settle_df_max).value()
+                )),
+            ),
+            (
+                SelectedParameter::SettleDpMax,
+                " dP/dt ".to_string(),
+                Some(format!(
+                    "{:0.prec$} mmHg/s",
+                    values.settle_dp_max,
+                    prec = controller::Precission::from(values.settle_dp_max).value()
+                )),
+            ),
+        ]);
+    }
+    rows.push((
+        SelectedParameter::CurvePointsCount,
+        " Точек ".to_string(),
+        Some(format!("{}", values.curve_points_count)),
+    ));
+    rows.extend(values.curve_points().iter().enumerate().map(|(i, &v)| {
+        (
+            SelectedParameter::curve_point(i),
+            format!(" Точка {} ", i + 1),
+            Some(format!(
+                "{:0.prec$} mmHg",
+                v,
+                prec = controller::Precission::from(v).value()
+            )),
+        )
+    }));
+    rows.extend([
+        (
+            SelectedParameter::ChamberVolumeMl,
+            " Объем ".to_string(),
+            Some(format!("{} мл", values.chamber_volume_ml)),
+        ),
+        (
+            SelectedParameter::LeakTimeS,
+            " Время течи ".to_string(),
+            Some(format!("{} с", values.leak_time_s)),
+        ),
+        (
+            SelectedParameter::LeakLimit,
+            " Макс. течь ".to_string(),
+            Some(format!("{:.0e}", values.leak_limit)),
+        ),
+        (
+            SelectedParameter::TolerancesEnabled,
+            " Допуски ".to_string(),
+            Some(
+                if values.tolerances_enabled {
+                    "Вкл"
+                } else {
+                    "Выкл"
+                }
+                .to_string(),
+            ),
+        ),
+    ]);
+    if values.tolerances_enabled {
+        let tolerances = &values.tolerances;
+        rows.extend([
+            (
+                SelectedParameter::SensivityMin,
+                " S мин ".to_string(),
+                Some(format!("{:0.1}", tolerances.sensivity.min)),
+            ),
+            (
+                SelectedParameter::SensivityMax,
+                " S макс ".to_string(),
+                Some(format!("{:0.1}", tolerances.sensivity.max)),
+            ),
+            (
+                SelectedParameter::FAtmMin,
+                " Fатм мин ".to_string(),
+                Some(format!("{:0.0} Hz", tolerances.f_atm.min)),
+            ),
+            (
+                SelectedParameter::FAtmMax,
+                " Fатм макс ".to_string(),
+                Some(format!("{:0.0} Hz", tolerances.f_atm.max)),
+            ),
+            (
+                SelectedParameter::FVacMin,
+                " Fвак мин ".to_string(),
+                Some(format!("{:0.0} Hz", tolerances.f_vac.min)),
+            ),
+            (
+                SelectedParameter::FVacMax,
+                " Fвак макс ".to_string(),
+                Some(format!("{:0.0} Hz", tolerances.f_vac.max)),
+            ),
+            (
+                SelectedParameter::TMin,
+                " T мин ".to_string(),
+                Some(format!("{:0.0} *C", tolerances.t.min)),
+            ),
+            (
+                SelectedParameter::TMax,
+                " T макс ".to_string(),
+                Some(format!("{:0.0} *C", tolerances.t.max)),
+            ),
+        ]);
+    }
+    rows.push((
+        SelectedParameter::StreamFormat,
+        " Поток UART ".to_string(),
+        Some(
+            match values.stream_format {
+                StreamFormat::Off => "Выкл",
+                StreamFormat::Csv => "CSV",
+                StreamFormat::Json => "JSON",
+            }
+            .to_string(),
+        ),
+    ));
+    rows.push((
+        SelectedParameter::WriteCalibration,
+        " Калибр. DUT ".to_string(),
+        Some(
+            if values.write_calibration {
+                "Вкл"
+            } else {
+                "Выкл"
+            }
+            .to_string(),
+        ),
+    ));
+    // адрес с пометкой, если последний поиск его не нашел
+    let address = |a: u32| match sctb {
+        Some(found) if !found.iter().any(|&f| f as u32 == a) => format!("{a} нет"),
+        _ => a.to_string(),
+    };
+    rows.extend([
+        (
+            SelectedParameter::SctbReference,
+            " I2C обр. ".to_string(),
+            Some(address(values.sctb_reference)),
+        ),
+        (
+            SelectedParameter::DutCount,
+            " Число DUT ".to_string(),
+            Some(values.duts().len().to_string()),
+        ),
+    ]);
+    let duts = values.duts();
+    rows.extend(duts.iter().enumerate().map(|(i, &a)| {
+        (
+            SelectedParameter::sctb_dut(i),
+            if duts.len() > 1 {
+                format!(" I2C DUT{} ", i + 1)
+            } else {
+                " I2C DUT ".to_string()
+            },
+            Some(address(a)),
+        )
+    }));
+    rows.extend([(
+        SelectedParameter::AutoStart,
+        " Автостарт ".to_string(),
+        Some(
+            if values.auto_start {
+                "Вкл"
+            } else {
+                "Выкл"
+            }
+            .to_string(),
+        ),
+    )]);
+    rows.push((
+        SelectedParameter::Diagnostics,
+        " Диагностика ".to_string(),
+        Some(">".to_string()),
+    ));
+    rows.push((
+        SelectedParameter::ReferenceGauge,
+        " Образц. датчик ".to_string(),
+        Some(">".to_string()),
+    ));
+    rows.push((
+        SelectedParameter::SaveAndExit,
+        " Сохранить и выйти ".to_string(),
+        None,
+    ));
+
+    let selected_row = rows
+        .iter()
+        .position(|(p, _, _)| *p == selected_parameter)
+        .unwrap_or_default();
+    let first_row = selected_row.saturating_sub(VISIBLE_ROWS - 1);
+
+    for (n, (parameter, label, value)) in rows.iter().skip(first_row).take(VISIBLE_ROWS).enumerate()
+    {
+        let pos = Text::with_baseline(
+            label,
+            Point::new(
+                5,
+                ITEMS_Y_OFFSET
+                    + (small_font.font.character_size.height as i32 + LINE_SHIFT) * n as i32,
+            ),
+            if *parameter == selected_parameter {
+                small_font_selected
+            } else {
+                small_font
+            },
+            Baseline::Top,
+        )
+        .draw(display)?;
+
+        if let Some(value) = value {
+            let value = Text::with_text_style(
+                value,
+                Point::new(display_w - 10, pos.y),
+                small_font,
+                TextStyleBuilder::new()
+                    .alignment(Alignment::Right)
+                    .baseline(Baseline::Top)
+                    .build(),
+            );
+
+            if *parameter == selected_parameter {
+                let rect = gen_text_bounding_rect(&value, false);
+                draw_arrows_to_rect(
+                    display,
+                    &rect,
+                    3,
+                    -1,
+                    2,
+                    PrimitiveStyle::with_fill(BinaryColor::On),
+                )?;
+                rect.into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+                    .draw(display)?;
+            }
+
+            value.draw(display)?;
+        }
+
+        if *parameter == SelectedParameter::ProfileName {
+            let char_w = small_font.font.character_size.width as i32;
+            let x = display_w - 10 - char_w * profile::NAME_LEN as i32;
+
+            Text::with_baseline(
+                profile_name,
+                Point::new(x, pos.y),
+                small_font,
+                Baseline::Top,
+            )
+            .draw(display)?;
+
+            if selected_parameter == SelectedParameter::ProfileName {
+                let cursor_x = x + char_w * name_cursor as i32;
+                let cursor_y = pos.y + small_font.font.character_size.height as i32 - 1;
+                Line::new(
+                    Point::new(cursor_x, cursor_y),
+                    Point::new(cursor_x + char_w - 1, cursor_y),
+                )
+                .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+                .draw(display)?;
+            }
+        }
+    }
+
+    //-------------------------------------------------------------------------
+
+    Rectangle::new(
+        Point::new(
+            0,
+            display.bounding_box().size.height as i32
+                - small_font_italic.font.character_size.height as i32
+                + 1,
+        ),
+        Size::new(
+            display_w as u32,
+            small_font_italic.font.character_size.height - 1,
+        ),
+    )
+    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+    .draw(display)?;
+
+    Text::new(
+        "Настройки",
+        Point::new(
+            (Ratio::<i32>::new(1, 3) * display_w).to_integer(),
+            display.bounding_box().size.height as i32 - 2,
+        ),
+        MonoTextStyleBuilder::from(&small_font_italic)
+            .background_color(BinaryColor::On)
+            .text_color(BinaryColor::Off)
+            .build(),
+    )
+    .draw(display)?;
+
+    Ok(())
+}
+
+/// ```text
+/// VSP206 @001
+/// Информация
+/// Атм.        1013
+/// Точка 1   1.0e-2
+///
+///   Датчик, mbar
+/// ```
+/// Если есть сообщение (результат команды), вместо списка - его строки.
+fn draw_gauge_menu<D>(
+    display: &mut D,
+    gauge: Option<&str>,
+    selected: GaugeMenuItem,
+    editing: bool,
+    atmosphere: f32,
+    switch_points: &[Option<f32>],
+    message: &[String],
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    display.clear(BinaryColor::Off)?;
+
+    let small_font_italic = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X13_ITALIC)
+        .text_color(BinaryColor::On)
+        .build();
+    let small_font = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X13)
+        .text_color(BinaryColor::On)
+        .build();
+    let small_font_selected = MonoTextStyleBuilder::from(&small_font)
+        .background_color(BinaryColor::On)
+        .text_color(BinaryColor::Off)
+        .build();
+    let message_font = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X10)
+        .text_color(BinaryColor::On)
+        .build();
+
+    let (display_w, display_h) = {
+        let d = display.bounding_box().size;
+        (d.width as i32, d.height as i32)
+    };
+    let line_h = small_font.font.character_size.height as i32;
+
+    // 4 строки над заголовком
+    const VISIBLE_ROWS: usize = 4;
+
+    if message.is_empty() {
+        // производитель не помещается, достаточно модели и адреса
+        let gauge = gauge.map_or("Нет датчиков", |id| {
+            id.split_once(' ').map_or(id, |(_, m)| m)
+        });
+        let switch_point = |i: usize| {
+            switch_points
+                .get(i)
+                .copied()
+                .flatten()
+                .map_or("?".to_string(), |p| format!("{p:.1e}"))
+        };
+        let rows = [
+            (GaugeMenuItem::Gauge, format!(" {gauge} "), None),
+            (GaugeMenuItem::Info, " Информация ".to_string(), None),
+            (
+                GaugeMenuItem::AdjustZero,
+                " Ноль ".to_string(),
+                (editing && selected == GaugeMenuItem::AdjustZero).then(|| "Да?".to_string()),
+            ),
+            (
+                GaugeMenuItem::AdjustAtmosphere,
+                " Атм. ".to_string(),
+                Some(format!("{atmosphere:0.0}")),
+            ),
+            (
+                GaugeMenuItem::SwitchPoint1,
+                " Точка 1 ".to_string(),
+                Some(switch_point(0)),
+            ),
+            (
+                GaugeMenuItem::SwitchPoint2,
+                " Точка 2 ".to_string(),
+                Some(switch_point(1)),
+            ),
+            (GaugeMenuItem::Back, " Назад ".to_string(), None),
+        ];
+
+        let selected_row = rows
+            .iter()
+            .position(|(item, _, _)| *item == selected)
+            .unwrap_or_default();
+        let first_row = selected_row.saturating_sub(VISIBLE_ROWS - 1);
+
+        for (n, (item, label, value)) in rows.iter().skip(first_row).take(VISIBLE_ROWS).enumerate()
+        {
+            let pos = Text::with_baseline(
+                label,
+                Point::new(5, line_h * n as i32),
+                if *item == selected {
+                    small_font_selected
+                } else {
+                    small_font
+                },
+                Baseline::Top,
+            )
+            .draw(display)?;
+
+            if let Some(value) = value {
+                let value = Text::with_text_style(
+                    value,
+                    Point::new(display_w - 10, pos.y),
+                    small_font,
+                    TextStyleBuilder::new()
+                        .alignment(Alignment::Right)
+                        .baseline(Baseline::Top)
+                        .build(),
+                );
+
+                if *item == selected && editing {
+                    let rect = gen_text_bounding_rect(&value, false);
+                    draw_arrows_to_rect(
+                        display,
+                        &rect,
+                        3,
+                        -1,
+                        2,
+                        PrimitiveStyle::with_fill(BinaryColor::On),
+                    )?;
+                    rect.into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+                        .draw(display)?;
+                }
+
+                value.draw(display)?;
+            }
+        }
+    } else {
+        let line_h = message_font.font.character_size.height as i32 + 1;
+        for (n, line) in message.iter().enumerate() {
+            Text::with_baseline(
+                line,
+                Point::new(2, 1 + line_h * n as i32),
+                message_font,
+                Baseline::Top,
+            )
+            .draw(display)?;
+        }
+    }
+
+    //-------------------------------------------------------------------------
+
+    Rectangle::new(
+        Point::new(
+            0,
+            display_h - small_font_italic.font.character_size.height as i32 + 1,
+        ),
+        Size::new(
+            display_w as u32,
+            small_font_italic.font.character_size.height - 1,
+        ),
+    )
+    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+    .draw(display)?;
+
+    Text::with_alignment(
+        "Датчик, mbar",
+        Point::new(display_w / 2, display_h - 2),
+        MonoTextStyleBuilder::from(&small_font_italic)
+            .background_color(BinaryColor::On)
+            .text_color(BinaryColor::Off)
+            .build(),
+        Alignment::Center,
+    )
+    .draw(display)?;
+
+    Ok(())
+}
+
+/// Показания для экрана измерения, поля [`DisplayCommand::Measure`]
+struct MeasureView {
+    f: Option<f32>,
+    p: Option<f32>,
+    threashold: f32,
+    wait_time: Option<core::time::Duration>,
+    drift: Option<Drift>,
+}
+
+/// ```text
+/// P: 123.456      F: 123.456
+/// <график Y=[P[0]...Threshhold]>
+/// __________________________
+/// ```
+fn draw_measure<D>(
+    display: &mut D,
+    view: MeasureView,
+    history: &mut VecDeque<f32>,
+    mut f_history: Vec<(f32, f32)>,
+) -> Result<Vec<(f32, f32)>, D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let MeasureView {
+        f,
+        p,
+        threashold,
+        wait_time,
+        drift,
+    } = view;
+    const PLOT_Y_OFFSER: i32 = 20;
+
+    display.clear(BinaryColor::Off)?;
+
+    let small_font = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X13)
+        .text_color(BinaryColor::On)
+        .build();
+
+    let small_font_selected = MonoTextStyleBuilder::from(&small_font)
+        .background_color(BinaryColor::On)
+        .text_color(BinaryColor::Off)
+        .build();
+
+    let (display_w, display_h) = {
+        let d = display.bounding_box().size;
+        (d.width as i32, d.height as i32)
+    };
+
+    if f.is_none() && p.is_none() {
+        // reset
+        history.clear();
+        f_history.clear();
+    } else {
+        if history.len() == display_w as usize {
+            history.pop_front();
+        }
+
+        let p = p.unwrap_or_default();
+        history.push_back(p);
+        f_history.push((p, f.unwrap_or_default()));
+
+        if f_history.len() > 1000 {
+            f_history = f_history[500..].to_vec();
+        }
+    }
+
+    let mut range = history
+        .iter()
+        .max_by_key(|v| ordered_float::OrderedFloat(**v))
+        .unwrap_or(&800.0)
+        - threashold;
+
+    if range < threashold * 2.0 {
+        range = threashold * 2.0;
+    }
+
+    let max_y = (display_h - PLOT_Y_OFFSER) as u32;
+
+    let line_style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+    for line_n in 0..display_w {
+        if let Some(element) = history.get(line_n as usize) {
+            let stroke_len = transform_size(*element, max_y as f32, range) as i32;
+            Line::new(
+                Point::new(line_n, display_h - stroke_len),
+                Point::new(line_n, display_h),
+            )
+            .into_styled(line_style)
+            .draw(display)?;
+        } else {
+            Line::new(
+                Point::new(0, display_h - 1),
+                Point::new(display_w, display_h - 1),
+            )
+            .into_styled(line_style)
+            .draw(display)?;
+
+            break;
+        }
+    }
+
+    Text::with_text_style(
+        format!("P: {:0.2}", p.unwrap_or(f32::NAN)).as_str(),
+        Point::new(2, 2),
+        match p {
+            Some(p) if p < threashold => small_font_selected,
+            _ => small_font,
+        },
+        TextStyleBuilder::new()
+            .alignment(Alignment::Left)
+            .baseline(Baseline::Top)
+            .build(),
+    )
+    .draw(display)?;
+
+    Text::with_text_style(
+        format!("F: {:0.2}", f.unwrap_or(f32::NAN)).as_str(),
+        Point::new(display_w - 2, 2),
+        small_font,
+        TextStyleBuilder::new()
+            .alignment(Alignment::Right)
+            .baseline(Baseline::Top)
+            .build(),
+    )
+    .draw(display)?;
+
+    if let Some(wait_time) = wait_time {
+        Text::with_text_style(
+            format!("{:} c.", wait_time.as_secs()).as_str(),
+            Point::new(
+                display_w - 2,
+                2 + small_font.font.character_size.height as i32,
+            ),
+            small_font_selected,
+            TextStyleBuilder::new()
+                .alignment(Alignment::Right)
+                .baseline(Baseline::Top)
+                .build(),
+        )
+        .draw(display)?;
+    }
+
+    if let Some(drift) = drift {
+        // поверх графика
+        let style = MonoTextStyleBuilder::from(&small_font)
+            .background_color(BinaryColor::Off)
+            .build();
+        let line_h = small_font.font.character_size.height as i32;
+        Text::with_baseline(
+            format!("dF {:+0.2} Hz/s", drift.df_dt).as_str(),
+            Point::new(2, 2 + line_h),
+            style,
+            Baseline::Top,
+        )
+        .draw(display)?;
+        Text::with_baseline(
+            format!("dP {:+0.3} mmHg/s", drift.dp_dt).as_str(),
+            Point::new(2, 2 + line_h * 2),
+            style,
+            Baseline::Top,
+        )
+        .draw(display)?;
+    }
+
+    Ok(f_history)
+}
+
+/// Результат одного DUT для экрана, поля [`DisplayCommand::Result`]
+struct ResultView {
+    f: f32,
+    p: f32,
+    t: Option<f32>,
+    sensivity: f32,
+    fit: Option<RegressionFit>,
+    hold: Option<HoldResult>,
+    verdict: Option<Verdict>,
+    dut: Option<usize>,
+}
+
+fn draw_result<D>(
+    display: &mut D,
+    view: ResultView,
+    mut f_history: Vec<(f32, f32)>,
+) -> Result<Vec<(f32, f32)>, D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let ResultView {
+        f,
+        p,
+        t,
+        sensivity,
+        fit,
+        hold,
+        verdict,
+        dut,
+    } = view;
+    display.clear(BinaryColor::Off)?;
+    // история экрана измерения следующему измерению не нужна
+    f_history.clear();
+
+    let small_font = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X10)
+        .text_color(BinaryColor::On)
+        .build();
+
+    let display_w = display.bounding_box().size.width as i32;
+
+    if let Some(verdict) = verdict {
+        let banner = match verdict {
+            Verdict::Pass => "ГОДЕН".to_string(),
+            Verdict::Fail(criterion) => format!("БРАК:{}", criterion.name()),
+        };
+        return draw_result_verdict(display, f, sensivity, t, fit, hold, &with_dut(dut, &banner))
+            .map(|_| f_history);
+    }
+
+    let line_h = small_font.font.character_size.height as i32 + 2;
+
+    draw_label_value(
+        display,
+        &with_dut(dut, "Давление:"),
+        format!("{:0.02} mmHg", p).as_str(),
+        1,
+        display_w,
+        small_font,
+    )?;
+
+    draw_label_value(
+        display,
+        "Частота:",
+        format!("{:0.02} Hz", f).as_str(),
+        1 + line_h,
+        display_w,
+        small_font,
+    )?;
+
+    draw_label_value(
+        display,
+        "Чувст.:",
+        format!("{:0.02} Hz/mmHg", sensivity).as_str(),
+        1 + line_h * 2,
+        display_w,
+        small_font,
+    )?;
+
+    if let Some(fit) = fit {
+        draw_label_value(
+            display,
+            format!("R2={:0.4}", fit.r2).as_str(),
+            format!("s={:0.02} n={}", fit.k_std_err, fit.points).as_str(),
+            1 + line_h * 3,
+            display_w,
+            small_font,
+        )?;
+    }
+
+    match (t, hold) {
+        (Some(t), Some(hold)) => draw_label_value(
+            display,
+            format!("T={:0.01} *C", t).as_str(),
+            hold_status(&hold).as_str(),
+            1 + line_h * 4,
+            display_w,
+            small_font,
+        )?,
+        (Some(t), None) => draw_label_value(
+            display,
+            "Температура:",
+            format!("{:0.01} *C", t).as_str(),
+            1 + line_h * 4,
+            display_w,
+            small_font,
+        )?,
+        _ => {}
+    }
+
+    Ok(f_history)
+}
+
+/// Подпись слева, значение справа
+/// ```text
+/// ######## ГОДЕН ########
+/// Чувст.:  3.98 Hz/mmHg
+/// F вак:    30002.00 Hz
+/// R2=0.9998  s=0.02 n=123
+/// T=23.5 *C  стаб. 6 с
+/// ```
+fn draw_result_verdict<D>(
+    display: &mut D,
+    f: f32,
+    sensivity: f32,
+    t: Option<f32>,
+    fit: Option<RegressionFit>,
+    hold: Option<HoldResult>,
+    banner: &str,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let small_font = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X10)
+        .text_color(BinaryColor::On)
+        .build();
+    let display_w = display.bounding_box().size.width as i32;
+    // "2:БРАК:Чувст." с номером DUT не помещается шрифтом 10x20
+    let big_font = MonoTextStyleBuilder::new()
+        .font(if banner.chars().count() as i32 * 10 > display_w {
+            &mono_font::iso_8859_5::FONT_9X18
+        } else {
+            &mono_font::iso_8859_5::FONT_10X20
+        })
+        .text_color(BinaryColor::Off)
+        .background_color(BinaryColor::On)
+        .build();
+
+    let banner_h = big_font.font.character_size.height as i32;
+
+    Rectangle::new(Point::zero(), Size::new(display_w as u32, banner_h as u32))
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(display)?;
+
+    Text::with_text_style(
+        banner,
+        Point::new(display_w / 2, 0),
+        big_font,
+        TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Top)
+            .build(),
+    )
+    .draw(display)?;
+
+    let line_h = small_font.font.character_size.height as i32 + 1;
+    let y = banner_h + 1;
+
+    draw_label_value(
+        display,
+        "Чувст.:",
+        format!("{:0.02} Hz/mmHg", sensivity).as_str(),
+        y,
+        display_w,
+        small_font,
+    )?;
+
+    draw_label_value(
+        display,
+        "F вак:",
+        format!("{:0.02} Hz", f).as_str(),
+        y + line_h,
+        display_w,
+        small_font,
+    )?;
+
+    if let Some(fit) = fit {
+        draw_label_value(
+            display,
+            format!("R2={:0.4}", fit.r2).as_str(),
+            format!("s={:0.02} n={}", fit.k_std_err, fit.points).as_str(),
+            y + line_h * 2,
+            display_w,
+            small_font,
+        )?;
+    }
+
+    if let Some(t) = t {
+        draw_label_value(
+            display,
+            format!("T={:0.01} *C", t).as_str(),
+            hold.map(|hold| hold_status(&hold))
+                .unwrap_or_default()
+                .as_str(),
+            y + line_h * 3,
+            display_w,
+            small_font,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// ```text
+/// 1: P, F, T точек | 2: чувствительность участков | 3: коэффициенты F(P)
+/// ```
+fn draw_curve_result<D>(
+    display: &mut D,
+    result: &CurveResult,
+    page: usize,
+    dut: Option<usize>,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    display.clear(BinaryColor::Off)?;
+
+    let small_font = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X10)
+        .text_color(BinaryColor::On)
+        .build();
+
+    let (display_w, display_h) = {
+        let d = display.bounding_box().size;
+        (d.width as i32, d.height as i32)
+    };
+
+    let line_h = small_font.font.character_size.height as i32;
+
+    let (title, rows) = match page {
+        0 => (
+            "Точки",
+            result
+                .points
+                .iter()
+                .map(|p| {
+                    (
+                        format!(
+                            "{:0.prec$}",
+                            p.p,
+                            prec = controller::Precission::from(p.p).value()
+                        ),
+                        format!(
+                            "{:0.01} {:0.01}{}",
+                            p.f,
+                            p.t,
+                            // удержание не стабилизировалось
+                            if p.hold.is_none_or(|h| h.settled) {
+                                ""
+                            } else {
+                                "!"
+                            }
+                        ),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        ),
+        1 => (
+            "Hz/mmHg",
+            result
+                .segments
+                .iter()
+                .zip(result.points.windows(2))
+                .map(|(s, w)| {
+                    (
+                        format!(
+                            "{:0.p1$}-{:0.p2$}",
+                            w[0].p,
+                            w[1].p,
+                            p1 = controller::Precission::from(w[0].p).value(),
+                            p2 = controller::Precission::from(w[1].p).value()
+                        ),
+                        format!("{:0.03}", s),
+                    )
+                })
+                .collect(),
+        ),
+        _ => (
+            "F(P)",
+            result.poly.as_ref().map_or_else(
+                || vec![("Нет".to_string(), "-".to_string())],
+                |poly| {
+                    poly.iter()
+                        .enumerate()
+                        .map(|(i, c)| (format!("c{}:", i), format!("{:.4e}", c)))
+                        .collect()
+                },
+            ),
+        ),
+    };
+
+    for (n, (label, value)) in rows.iter().enumerate() {
+        draw_label_value(
+            display,
+            label,
+            value,
+            1 + line_h * n as i32,
+            display_w,
+            small_font,
+        )?;
+    }
+
+    // заголовок со страницей
+    Rectangle::new(
+        Point::new(0, display_h - line_h),
+        Size::new(display_w as u32, line_h as u32),
+    )
+    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+    .draw(display)?;
+
+    Text::with_text_style(
+        with_dut(
+            dut,
+            &format!("{} {}/{}", title, page + 1, CurveResult::PAGES),
+        )
+        .as_str(),
+        Point::new(display_w / 2, display_h - line_h),
+        MonoTextStyleBuilder::from(&small_font)
+            .background_color(BinaryColor::On)
+            .text_color(BinaryColor::Off)
+            .build(),
+        TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Top)
+            .build(),
+    )
+    .draw(display)?;
+
+    Ok(())
+}
+
+/// ```text
+/// P: 0.50->0.52 mmHg
+/// dP/dt, течь в mbar·l/s, предел, итог
+/// ```
+fn draw_leak_result<D>(display: &mut D, result: &LeakResult) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    display.clear(BinaryColor::Off)?;
+
+    let small_font = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X10)
+        .text_color(BinaryColor::On)
+        .build();
+
+    let display_w = display.bounding_box().size.width as i32;
+    let line_h = small_font.font.character_size.height as i32 + 2;
+
+    draw_label_value(
+        display,
+        "P:",
+        format!("{:0.03}->{:0.03} mmHg", result.p_start, result.p_end).as_str(),
+        1,
+        display_w,
+        small_font,
+    )?;
+
+    draw_label_value(
+        display,
+        "dP/dt:",
+        format!("{:.2e} mbar/s", result.dp_dt).as_str(),
+        1 + line_h,
+        display_w,
+        small_font,
+    )?;
+
+    draw_label_value(
+        display,
+        "Течь:",
+        format!("{:.2e} mbar*l/s", result.leak_rate).as_str(),
+        1 + line_h * 2,
+        display_w,
+        small_font,
+    )?;
+
+    draw_label_value(
+        display,
+        "Предел:",
+        format!("{:.0e} за {} с", result.limit, result.duration.as_secs()).as_str(),
+        1 + line_h * 3,
+        display_w,
+        small_font,
+    )?;
+
+    draw_label_value(
+        display,
+        "Итог:",
+        if result.passed {
+            "Норма"
+        } else {
+            "Течь!"
+        },
+        1 + line_h * 4,
+        display_w,
+        small_font,
+    )?;
+
+    Ok(())
+}
+
+fn hold_status(hold: &HoldResult) -> String {
+    if hold.settled {
+        format!("стаб. {} с", hold.duration.as_secs())
+    } else {
+        "тайм-аут".to_string()
+    }
+}
+
+/// ```text
+/// S/N:       12345678 @11
+/// ПО/период:  1.4 / 100 ms
+/// F0:           30000.0 Hz
+/// c0 c1:    0.00e0 2.50e-1
+/// c2 c3:     0.00e0 0.00e0
+/// ```
+fn draw_diagnostics<D>(
+    display: &mut D,
+    dut: Option<&SctbInfo>,
+    channel: Option<usize>,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    display.clear(BinaryColor::Off)?;
+
+    let small_font = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X10)
+        .text_color(BinaryColor::On)
+        .build();
+
+    let display_w = display.bounding_box().size.width as i32;
+    let line_h = small_font.font.character_size.height as i32 + 2;
+
+    let Some(dut) = dut else {
+        let label = with_dut(channel, "DUT:");
+        return draw_label_value(display, &label, "нет ответа", 1, display_w, small_font);
+    };
+
+    let coeff = |c: f32| {
+        if c.is_nan() {
+            "-".to_string()
+        } else {
+            format!("{c:.2e}")
+        }
+    };
+    let c = &dut.calibration.coeffs;
+    let serial = with_dut(channel, "S/N:");
+    let lines = [
+        (
+            serial.as_str(),
+            format!("{} @{}", dut.info.serial, dut.address),
+        ),
+        (
+            "ПО/период:",
+            format!(
+                "{}.{} / {} ms",
+                dut.info.firmware.0, dut.info.firmware.1, dut.info.period_ms
+            ),
+        ),
+        ("F0:", format!("{:.1} Hz", dut.calibration.f0)),
+        ("c0 c1:", format!("{} {}", coeff(c[0]), coeff(c[1]))),
+        ("c2 c3:", format!("{} {}", coeff(c[2]), coeff(c[3]))),
+    ];
+    for (n, (label, value)) in lines.iter().enumerate() {
+        draw_label_value(
+            display,
+            label,
+            value,
+            1 + line_h * n as i32,
+            display_w,
+            small_font,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// ```text
+/// #### Калибр. записана ####
+/// F0:           30012.3 Hz
+/// c0 c1:   -1.20e-2 2.49e-1
+/// c2 c3:    3.10e-6 -2.0e-9
+/// Откл. n=412  0.0123 mmHg
+/// ```
+fn draw_calibration_result<D>(
+    display: &mut D,
+    outcome: &CalibrationOutcome,
+    dut: Option<usize>,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    display.clear(BinaryColor::Off)?;
+
+    let small_font = MonoTextStyleBuilder::new()
+        .font(&mono_font::iso_8859_5::FONT_6X10)
+        .text_color(BinaryColor::On)
+        .build();
+
+    let display_w = display.bounding_box().size.width as i32;
+    let line_h = small_font.font.character_size.height as i32 + 2;
+
+    Rectangle::new(Point::zero(), Size::new(display_w as u32, line_h as u32))
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(display)?;
+
+    let status = match outcome {
+        CalibrationOutcome::NoFit => "мало точек",
+        CalibrationOutcome::Written(_) => "записана",
+        CalibrationOutcome::Failed { error, .. } => match error {
+            WriteError::Unsupported => "не поддерж.",
+            WriteError::Bus => "ошибка шины",
+            WriteError::Decode(_) => "не прочитана",
+            WriteError::Mismatch(_) => "не совпала",
+        },
+    };
+    Text::with_text_style(
+        with_dut(dut, &format!("Калибр. {status}")).as_str(),
+        Point::new(display_w / 2, 1),
+        MonoTextStyleBuilder::from(&small_font)
+            .background_color(BinaryColor::On)
+            .text_color(BinaryColor::Off)
+            .build(),
+        TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Top)
+            .build(),
+    )
+    .draw(display)?;
+
+    let Some(fit) = outcome.fit() else {
+        return Ok(());
+    };
+
+    let c = &fit.calibration.coeffs;
+    let deviation = format!("Откл. n={}", fit.points);
+    let lines = [
+        ("F0:", format!("{:.1} Hz", fit.calibration.f0)),
+        ("c0 c1:", format!("{:.2e} {:.2e}", c[0], c[1])),
+        ("c2 c3:", format!("{:.2e} {:.2e}", c[2], c[3])),
+        (deviation.as_str(), format!("{:.4} mmHg", fit.max_error)),
+    ];
+    for (n, (label, value)) in lines.iter().enumerate() {
+        draw_label_value(
+            display,
+            label,
+            value,
+            line_h * (n as i32 + 1),
+            display_w,
+            small_font,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Номер DUT перед подписью, если их несколько
+fn with_dut(dut: Option<usize>, label: &str) -> String {
+    match dut {
+        Some(n) => format!("{n}:{label}"),
+        None => label.to_string(),
+    }
+}
+
+fn draw_label_value<D>(
+    display: &mut D,
+    label: &str,
+    value: &str,
+    y: i32,
+    display_w: i32,
+    font: MonoTextStyle<'_, BinaryColor>,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    Text::with_baseline(label, Point::new(1, y), font, Baseline::Top).draw(display)?;
+
+    Text::with_text_style(
+        value,
+        Point::new(display_w - 1, y),
+        font,
+        TextStyleBuilder::new()
+            .alignment(Alignment::Right)
+            .baseline(Baseline::Top)
+            .build(),
+    )
+    .draw(display)?;
+
+    Ok(())
+}
+
+fn gen_text_bounding_rect<T: Dimensions>(text: &T, is_russian_text: bool) -> Rectangle {
+    let mut bb = text.bounding_box();
+
+    if is_russian_text {
+        bb.size.width /= 2;
+    }
+    bb.top_left.x -= 2;
+    bb.size.width += 3 * 2;
+
+    bb
+}
+
+fn draw_arrows_to_rect<D>(
+    display: &mut D,
+    rect: &Rectangle,
+    arrow_len: i32,
+    vertical_resize: i32,
+    h_offset: i32,
+    style: PrimitiveStyle<BinaryColor>,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let center_line = rect.center().y;
+    let button_heigh_half = (rect.size.height / 2) as i32;
+    let left = rect.top_left.x;
+    let right = rect.bottom_right().map_or_else(|| left, |p| p.x);
+
+    Triangle::new(
+        Point::new(left - h_offset - arrow_len, center_line),
+        Point::new(
+            left - h_offset,
+            center_line - (button_heigh_half + vertical_resize),
+        ),
+        Point::new(
+            left - h_offset,
+            center_line + (button_heigh_half + vertical_resize),
+        ),
+    )
+    .into_styled(style)
+    .draw(display)?;
+    Triangle::new(
+        Point::new(right + h_offset + arrow_len, center_line),
+        Point::new(
+            right + h_offset,
+            center_line - (button_heigh_half + vertical_resize),
+        ),
+        Point::new(
+            right + h_offset,
+            center_line + (button_heigh_half + vertical_resize),
+        ),
+    )
+    .into_styled(style)
+    .draw(display)?;
+
+    Ok(())
+}
+
+fn transform_size<T: Num>(current: T, target_max: T, max_value: T) -> T {
+    (target_max * current) / max_value
+}
@@ -0,0 +1,105 @@
+//! Кадр 128x64 в памяти: отрисовка экранов на хосте и сравнение с эталонами (PBM)
+
+use std::convert::Infallible;
+
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::{DrawTarget, OriginDimensions, Size},
+    Pixel,
+};
+
+use crate::display::Display;
+
+pub const WIDTH: usize = 128;
+pub const HEIGHT: usize = 64;
+
+#[derive(Clone, PartialEq)]
+pub struct FrameBuffer {
+    pixels: [bool; WIDTH * HEIGHT],
+}
+
+impl FrameBuffer {
+    pub fn new() -> Self {
+        Self {
+            pixels: [false; WIDTH * HEIGHT],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.pixels[y * WIDTH + x]
+    }
+
+    /// Количество различающихся пикселей
+    pub fn diff(&self, other: &FrameBuffer) -> usize {
+        self.pixels
+            .iter()
+            .zip(other.pixels.iter())
+            .filter(|(a, b)| a != b)
+            .count()
+    }
+
+    /// Бинарный PBM (P4), 1 - черный, т.е. включенный пиксель
+    pub fn to_pbm(&self) -> Vec<u8> {
+        let mut res = format!("P4\n{WIDTH} {HEIGHT}\n").into_bytes();
+        for row in self.pixels.chunks(WIDTH) {
+            for byte in row.chunks(8) {
+                res.push(
+                    byte.iter()
+                        .enumerate()
+                        .fold(0u8, |acc, (i, p)| acc | ((*p as u8) << (7 - i))),
+                );
+            }
+        }
+        res
+    }
+
+    /// Разбор PBM (P4) размером 128x64, сохраненного [`FrameBuffer::to_pbm`]
+    pub fn from_pbm(data: &[u8]) -> Option<Self> {
+        let header = format!("P4\n{WIDTH} {HEIGHT}\n").into_bytes();
+        let body = data.strip_prefix(header.as_slice())?;
+        if body.len() != WIDTH * HEIGHT / 8 {
+            return None;
+        }
+
+        let mut res = Self::new();
+        for (i, p) in res.pixels.iter_mut().enumerate() {
+            *p = body[i / 8] & (1 << (7 - i % 8)) != 0;
+        }
+        Some(res)
+    }
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OriginDimensions for FrameBuffer {
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, HEIGHT as u32)
+    }
+}
+
+impl DrawTarget for FrameBuffer {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Infallible>
+    where
+        I: IntoIterator<Item = Pixel<BinaryColor>>,
+    {
+        for Pixel(point, color) in pixels {
+            if (0..WIDTH as i32).contains(&point.x) && (0..HEIGHT as i32).contains(&point.y) {
+                self.pixels[point.y as usize * WIDTH + point.x as usize] = color.is_on();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for FrameBuffer {
+    fn flush(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+//! Образцовые датчики давления за общим трейтом [`PressureGauge`].
+//!
+//! Платформа опрашивает найденные датчики и отдает контроллеру их список
+//! ([`SensorsControl::gauges`](crate::platform::SensorsControl::gauges)) и показания
+//! [`SensorResult::GaugeResult`](crate::controller::SensorResult::GaugeResult) с номером
+//! датчика в этом списке. Новый прибор - это только новая реализация трейта.
+//!
+//! Настройка прибора ([`GaugeCommand`]) необязательна: по умолчанию драйвер отвечает
+//! [`GaugeError::Unsupported`].
+
+use std::sync::{Arc, Mutex};
+
+/// 1 mbar в mmHg
+pub const MBAR_TO_MM_HG: f32 = 0.7500616;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PressureUnit {
+    MmHg,
+    Mbar,
+    Pa,
+}
+
+impl PressureUnit {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PressureUnit::MmHg => "mmHg",
+            PressureUnit::Mbar => "mbar",
+            PressureUnit::Pa => "Pa",
+        }
+    }
+
+    /// Сколько mbar в единице
+    fn mbar(&self) -> f32 {
+        match self {
+            PressureUnit::MmHg => 1.0 / MBAR_TO_MM_HG,
+            PressureUnit::Mbar => 1.0,
+            PressureUnit::Pa => 0.01,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pressure {
+    pub value: f32,
+    pub unit: PressureUnit,
+}
+
+impl Pressure {
+    pub fn new(value: f32, unit: PressureUnit) -> Self {
+        Self { value, unit }
+    }
+
+    pub fn to(&self, unit: PressureUnit) -> f32 {
+        if self.unit == unit {
+            self.value
+        } else {
+            self.value * self.unit.mbar() / unit.mbar()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GaugeStatus {
+    Ok,
+    /// Ответа нет или он не разобран (CRC, обрыв)
+    NoResponse,
+    /// Ошибка шины или сам прибор сообщил об ошибке
+    Fault,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GaugeReading {
+    /// NaN, если статус не [`GaugeStatus::Ok`]
+    pub pressure: Pressure,
+    pub status: GaugeStatus,
+}
+
+impl GaugeReading {
+    pub fn ok(pressure: Pressure) -> Self {
+        Self {
+            pressure,
+            status: GaugeStatus::Ok,
+        }
+    }
+
+    pub fn failed(status: GaugeStatus, unit: PressureUnit) -> Self {
+        Self {
+            pressure: Pressure::new(f32::NAN, unit),
+            status,
+        }
+    }
+
+    /// Давление в `unit`, только для исправного отсчета
+    pub fn value(&self, unit: PressureUnit) -> Option<f32> {
+        (self.status == GaugeStatus::Ok).then(|| self.pressure.to(unit))
+    }
+}
+
+/// Точек переключения у прибора
+pub const SWITCH_POINTS: u8 = 2;
+
+/// Настройка образцового датчика, давления в mbar
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GaugeCommand {
+    /// Серийный номер, прошивка, наработка, единица индикации
+    Info,
+    /// Юстировка нуля, датчик должен быть под глубоким вакуумом
+    AdjustZero,
+    /// Юстировка атмосферы при известном давлении
+    AdjustAtmosphere(f32),
+    /// Точка переключения 1..=[`SWITCH_POINTS`]
+    GetSwitchPoint(u8),
+    SetSwitchPoint(u8, f32),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GaugeInfo {
+    pub serial: Option<String>,
+    pub firmware: Option<String>,
+    /// Наработка, часы
+    pub hours: Option<u32>,
+    /// Единица индикации на самом приборе, как ее называет прибор
+    pub unit: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GaugeReply {
+    Done,
+    Info(GaugeInfo),
+    SwitchPoint { index: u8, p_mbar: f32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GaugeError {
+    Unsupported,
+    NoResponse,
+    /// Ошибка линии
+    Fault,
+}
+
+impl GaugeError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GaugeError::Unsupported => "unsupported",
+            GaugeError::NoResponse => "no_response",
+            GaugeError::Fault => "fault",
+        }
+    }
+}
+
+/// Счетчики обмена с прибором, по одному на каждую попытку запроса
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LinkStats {
+    pub ok: u32,
+    pub crc_errors: u32,
+    pub timeouts: u32,
+    /// Ответ с верной суммой, но не разобран
+    pub invalid: u32,
+}
+
+/// Счетчики, которые драйвер обновляет из потока опроса, а платформа читает
+pub type SharedLinkStats = Arc<Mutex<LinkStats>>;
+
+pub trait PressureGauge {
+    /// Модель и адрес прибора, для журнала
+    fn id(&self) -> String;
+
+    /// Опросить прибор. Блокирует поток на время обмена.
+    fn read(&mut self) -> GaugeReading;
+
+    /// Счетчики обмена, если драйвер их ведет
+    fn link_stats(&self) -> Option<SharedLinkStats> {
+        None
+    }
+
+    /// Настройка прибора. Блокирует поток на время обмена.
+    fn command(&mut self, _cmd: GaugeCommand) -> Result<GaugeReply, GaugeError> {
+        Err(GaugeError::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() <= b.abs() * 1e-6
+    }
+
+    #[test]
+    fn pressure_conversion() {
+        let atm = Pressure::new(1013.25, PressureUnit::Mbar);
+        assert!(
+            close(atm.to(PressureUnit::MmHg), 760.0),
+            "{}",
+            atm.to(PressureUnit::MmHg)
+        );
+        assert!(close(atm.to(PressureUnit::Pa), 101325.0));
+        assert_eq!(atm.to(PressureUnit::Mbar), 1013.25);
+
+        let mm_hg = Pressure::new(1.0, PressureUnit::MmHg);
+        assert!(close(mm_hg.to(PressureUnit::Mbar), 1.0 / MBAR_TO_MM_HG));
+        let back = Pressure::new(mm_hg.to(PressureUnit::Pa), PressureUnit::Pa);
+        assert!(close(back.to(PressureUnit::MmHg), 1.0));
+    }
+
+    #[test]
+    fn failed_reading_has_no_value() {
+        let ok = GaugeReading::ok(Pressure::new(100.0, PressureUnit::Pa));
+        assert_eq!(ok.value(PressureUnit::Mbar), Some(1.0));
+
+        let failed = GaugeReading::failed(GaugeStatus::NoResponse, PressureUnit::Mbar);
+        assert!(failed.pressure.value.is_nan());
+        assert_eq!(failed.value(PressureUnit::Mbar), None);
+    }
+}
@@ -0,0 +1,674 @@
+//! Датчики SCTB на шине I2C.
+//!
+//! Чтение блока: запись адреса регистра, затем чтение блока целиком. Запись блока
+//! калибровки: адрес регистра, блок и CRC одной транзакцией.
+//!
+//! Штатная прошивка SCTB отдает только блок показаний 0x00: четыре f32 в порядке P, T,
+//! F_P, F_T. Первая версия прибора читала его `transmute` в структуру на ESP32, то есть
+//! little-endian. Такой датчик работает как раньше, версия карты для него
+//! [`LEGACY_VERSION`]. Остальные блоки - расширение карты регистров: датчик с ним
+//! отвечает "SCTB" по адресу 0xF0.
+//!
+//! ```text
+//! адрес  байт    содержимое
+//! 0x00   16(+1)  P mmHg, T *C, F_P Hz, F_T Hz - f32; NaN - канала нет в датчике
+//! 0x10   8(+1)   серийный номер u32, версия ПО u8.u8, период измерения u16 ms
+//! 0x20   20(+1)  калибровка F0 Hz, c0..c3 - f32: P = c0 + c1*(F-F0) + c2*(F-F0)^2 + c3*(F-F0)^3 mmHg
+//! 0xF0   5       "SCTB", версия карты регистров; в штатной прошивке нет
+//! ```
+//!
+//! Многобайтные поля little-endian. CRC-8 (полином 0x31, начальное значение 0xFF) всех
+//! байт блока идет после каждого блока, кроме идентификации, только с версии 2. Блоков
+//! 0x10 и 0x20 до версии 2 нет. Блок 0x20 доступен на запись, датчик принимает его только
+//! с верной CRC. Показания любой версии проверяются на бесконечность и диапазон канала.
+
+use crate::gauge::{GaugeReading, GaugeStatus, Pressure, PressureGauge, PressureUnit};
+use crate::verdict::Range;
+
+pub const OUTPUT_VALUES_ADDR: u8 = 0x00;
+pub const INFO_ADDR: u8 = 0x10;
+pub const CALIBRATION_ADDR: u8 = 0x20;
+pub const IDENT_ADDR: u8 = 0xF0;
+pub const IDENT_MAGIC: &[u8; 4] = b"SCTB";
+/// Штатная прошивка без блока идентификации: только показания, без CRC
+pub const LEGACY_VERSION: u8 = 0;
+/// Поддерживаемые версии карты регистров
+pub const MAP_VERSIONS: std::ops::RangeInclusive<u8> = 1..=2;
+/// Первая версия с CRC
+pub const CRC_VERSION: u8 = 2;
+/// Первая версия с блоками идентификации датчика и калибровки
+pub const INFO_VERSION: u8 = 2;
+
+pub const CALIBRATION_COEFFS: usize = 4;
+
+/// Адреса, на которых ищутся датчики, без зарезервированных I2C
+pub const SCAN_ADDRESSES: std::ops::RangeInclusive<u8> = 0x08..=0x77;
+
+const OUTPUT_VALUES_LEN: usize = 4 * std::mem::size_of::<f32>();
+const INFO_LEN: usize = 8;
+const CALIBRATION_LEN: usize = (1 + CALIBRATION_COEFFS) * std::mem::size_of::<f32>();
+
+/// Допустимые значения каналов, все остальное - мусор на шине
+pub const PRESSURE_RANGE: Range = Range::new(-1.0, 1500.0);
+pub const TEMPERATURE_RANGE: Range = Range::new(-40.0, 125.0);
+pub const FREQUENCY_RANGE: Range = Range::new(0.0, 1.0e6);
+
+/// Каналы датчика, None - канала в этом датчике нет
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SensorResult {
+    pub pressure: Option<f32>,
+    pub temperature: Option<f32>,
+    pub f_p: Option<f32>,
+    pub f_t: Option<f32>,
+}
+
+/// Блок [`INFO_ADDR`]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SensorInfo {
+    pub serial: u32,
+    /// Старшая и младшая версия ПО
+    pub firmware: (u8, u8),
+    pub period_ms: u16,
+}
+
+/// Блок [`CALIBRATION_ADDR`], NaN - коэффициент не записан
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Calibration {
+    pub f0: f32,
+    pub coeffs: [f32; CALIBRATION_COEFFS],
+}
+
+impl Calibration {
+    /// Давление по калибровке, mmHg
+    pub fn pressure(&self, f: f32) -> f32 {
+        let x = f - self.f0;
+        self.coeffs.iter().rev().fold(0.0, |acc, c| acc * x + c)
+    }
+}
+
+/// Все, что прочитано из датчика кроме показаний
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SctbInfo {
+    pub address: u8,
+    pub version: u8,
+    pub info: SensorInfo,
+    pub calibration: Calibration,
+}
+
+/// Датчик SCTB, найденный на шине
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SctbDevice {
+    pub address: u8,
+    pub version: u8,
+}
+
+/// Ответ прочитан, но не разобран
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodeError {
+    /// По адресу идентификации не SCTB
+    Id([u8; 4]),
+    /// Версия не поддерживается или в ней нет такого блока
+    Version(u8),
+    /// Длина блока не совпадает с картой регистров
+    Length(usize),
+    Crc {
+        msg: u8,
+        actual: u8,
+    },
+    /// Бесконечность или значение вне диапазона канала
+    OutOfRange {
+        channel: &'static str,
+        value: f32,
+    },
+}
+
+#[derive(Debug)]
+pub enum ReadError<E> {
+    Bus(E),
+    Decode(DecodeError),
+}
+
+impl<E> From<DecodeError> for ReadError<E> {
+    fn from(e: DecodeError) -> Self {
+        ReadError::Decode(e)
+    }
+}
+
+pub struct I2CSensor {
+    addr: u8,
+    /// Версия карты регистров, None - датчик еще не опознан
+    version: Option<u8>,
+}
+
+impl I2CSensor {
+    pub fn new(i2c_addr: u8) -> Self {
+        Self {
+            addr: i2c_addr,
+            version: None,
+        }
+    }
+
+    /// Читает идентификацию и запоминает версию карты регистров. Датчик без блока
+    /// идентификации опознается по блоку показаний, его версия - [`LEGACY_VERSION`].
+    pub fn identify<I2C, E>(&mut self, i2c_bus: &mut I2C) -> Result<u8, ReadError<E>>
+    where
+        I2C: embedded_hal::blocking::i2c::WriteRead<Error = E>,
+    {
+        let mut ident = [0u8; IDENT_MAGIC.len() + 1];
+        let version = match i2c_bus.write_read(self.addr, &[IDENT_ADDR], &mut ident) {
+            Ok(()) if ident.starts_with(IDENT_MAGIC) => decode_ident(&ident)?,
+            // штатная прошивка на 0xF0 не отвечает или отдает что попало
+            _ => {
+                let mut values = [0u8; OUTPUT_VALUES_LEN];
+                i2c_bus
+                    .write_read(self.addr, &[OUTPUT_VALUES_ADDR], &mut values)
+                    .map_err(ReadError::Bus)?;
+                decode_values(&values, LEGACY_VERSION)?;
+                LEGACY_VERSION
+            }
+        };
+        self.version = Some(version);
+        Ok(version)
+    }
+
+    /// Версия карты регистров, датчик опознается при первом обращении
+    fn identified<I2C, E>(&mut self, i2c_bus: &mut I2C) -> Result<u8, ReadError<E>>
+    where
+        I2C: embedded_hal::blocking::i2c::WriteRead<Error = E>,
+    {
+        match self.version {
+            Some(v) => Ok(v),
+            None => self.identify(i2c_bus),
+        }
+    }
+
+    /// Блок регистров с CRC, если он есть в этой версии. После ошибки шины датчик
+    /// опознается заново, его могли заменить.
+    fn read_block<I2C, E>(
+        &mut self,
+        i2c_bus: &mut I2C,
+        reg: u8,
+        len: usize,
+    ) -> Result<(Vec<u8>, u8), ReadError<E>>
+    where
+        I2C: embedded_hal::blocking::i2c::WriteRead<Error = E>,
+    {
+        let version = self.identified(i2c_bus)?;
+
+        let mut dest = vec![0u8; len + usize::from(version >= CRC_VERSION)];
+        if let Err(e) = i2c_bus.write_read(self.addr, &[reg], &mut dest) {
+            self.version = None;
+            return Err(ReadError::Bus(e));
+        }
+        Ok((dest, version))
+    }
+
+    pub fn read<I2C, E>(&mut self, i2c_bus: &mut I2C) -> Result<SensorResult, ReadError<E>>
+    where
+        I2C: embedded_hal::blocking::i2c::WriteRead<Error = E>,
+    {
+        let (data, version) = self.read_block(i2c_bus, OUTPUT_VALUES_ADDR, OUTPUT_VALUES_LEN)?;
+        Ok(decode_values(&data, version)?)
+    }
+
+    /// Серийный номер, ПО, период измерения и калибровка
+    pub fn read_info<I2C, E>(&mut self, i2c_bus: &mut I2C) -> Result<SctbInfo, ReadError<E>>
+    where
+        I2C: embedded_hal::blocking::i2c::WriteRead<Error = E>,
+    {
+        let version = self.identified(i2c_bus)?;
+        if version < INFO_VERSION {
+            return Err(DecodeError::Version(version).into());
+        }
+
+        let (data, version) = self.read_block(i2c_bus, INFO_ADDR, INFO_LEN)?;
+        let info = decode_info(&data, version)?;
+        let (data, version) = self.read_block(i2c_bus, CALIBRATION_ADDR, CALIBRATION_LEN)?;
+        let calibration = decode_calibration(&data, version)?;
+        Ok(SctbInfo {
+            address: self.addr,
+            version,
+            info,
+            calibration,
+        })
+    }
+
+    pub fn read_calibration<I2C, E>(
+        &mut self,
+        i2c_bus: &mut I2C,
+    ) -> Result<Calibration, ReadError<E>>
+    where
+        I2C: embedded_hal::blocking::i2c::WriteRead<Error = E>,
+    {
+        let version = self.identified(i2c_bus)?;
+        if version < INFO_VERSION {
+            return Err(DecodeError::Version(version).into());
+        }
+
+        let (data, version) = self.read_block(i2c_bus, CALIBRATION_ADDR, CALIBRATION_LEN)?;
+        Ok(decode_calibration(&data, version)?)
+    }
+
+    /// Запись блока калибровки. Датчик не отвечает ничем, проверка - обратным чтением.
+    pub fn write_calibration<I2C, E>(
+        &mut self,
+        i2c_bus: &mut I2C,
+        calibration: &Calibration,
+    ) -> Result<(), ReadError<E>>
+    where
+        I2C: embedded_hal::blocking::i2c::WriteRead<Error = E>
+            + embedded_hal::blocking::i2c::Write<Error = E>,
+    {
+        let version = self.identified(i2c_bus)?;
+        if version < INFO_VERSION {
+            return Err(DecodeError::Version(version).into());
+        }
+
+        let mut frame = vec![CALIBRATION_ADDR];
+        frame.extend(encode_calibration(calibration, version));
+        if let Err(e) = i2c_bus.write(self.addr, &frame) {
+            self.version = None;
+            return Err(ReadError::Bus(e));
+        }
+        Ok(())
+    }
+
+    pub fn address(&self) -> u8 {
+        self.addr
+    }
+
+    pub fn version(&self) -> Option<u8> {
+        self.version
+    }
+}
+
+/// Опрос блока идентификации по всем `addresses`. Адреса без ответа пропускаются молча,
+/// ответившие не как SCTB - с сообщением.
+pub fn scan<I2C, E>(i2c_bus: &mut I2C, addresses: std::ops::RangeInclusive<u8>) -> Vec<SctbDevice>
+where
+    I2C: embedded_hal::blocking::i2c::WriteRead<Error = E>,
+    E: std::fmt::Debug,
+{
+    addresses
+        .filter_map(|address| match I2CSensor::new(address).identify(i2c_bus) {
+            Ok(version) => Some(SctbDevice { address, version }),
+            Err(ReadError::Bus(_)) => None,
+            Err(ReadError::Decode(e)) => {
+                println!("Not a SCTB sensor at {address}: {e:?}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// CRC-8, полином 0x31, начальное значение 0xFF
+pub fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0xFF, |crc, &b| {
+        (0..8).fold(crc ^ b, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x31
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Версия карты регистров из блока идентификации
+pub fn decode_ident(data: &[u8; IDENT_MAGIC.len() + 1]) -> Result<u8, DecodeError> {
+    let (magic, version) = data.split_at(IDENT_MAGIC.len());
+    if magic != IDENT_MAGIC {
+        let mut id = [0u8; 4];
+        id.copy_from_slice(magic);
+        return Err(DecodeError::Id(id));
+    }
+    if !MAP_VERSIONS.contains(&version[0]) {
+        return Err(DecodeError::Version(version[0]));
+    }
+    Ok(version[0])
+}
+
+/// Снимает и проверяет CRC блока, если он есть в этой версии
+pub fn check_crc(data: &[u8], version: u8) -> Result<&[u8], DecodeError> {
+    if version < CRC_VERSION {
+        return Ok(data);
+    }
+    let Some((&msg, body)) = data.split_last() else {
+        return Err(DecodeError::Length(0));
+    };
+    let actual = crc8(body);
+    if msg != actual {
+        return Err(DecodeError::Crc { msg, actual });
+    }
+    Ok(body)
+}
+
+/// Тело блока без CRC точно ожидаемой длины
+fn block<const N: usize>(data: &[u8], version: u8) -> Result<[u8; N], DecodeError> {
+    let body = check_crc(data, version)?;
+    body.try_into().map_err(|_| DecodeError::Length(data.len()))
+}
+
+/// Разбор блока [`INFO_ADDR`]
+pub fn decode_info(data: &[u8], version: u8) -> Result<SensorInfo, DecodeError> {
+    let body = block::<INFO_LEN>(data, version)?;
+    Ok(SensorInfo {
+        serial: u32::from_le_bytes([body[0], body[1], body[2], body[3]]),
+        firmware: (body[4], body[5]),
+        period_ms: u16::from_le_bytes([body[6], body[7]]),
+    })
+}
+
+/// Разбор блока [`CALIBRATION_ADDR`]
+pub fn decode_calibration(data: &[u8], version: u8) -> Result<Calibration, DecodeError> {
+    let body = block::<CALIBRATION_LEN>(data, version)?;
+    let mut values = body
+        .chunks_exact(std::mem::size_of::<f32>())
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]));
+    let f0 = values.next().unwrap_or(f32::NAN);
+    let mut coeffs = [f32::NAN; CALIBRATION_COEFFS];
+    for (c, v) in coeffs.iter_mut().zip(values) {
+        *c = v;
+    }
+    Ok(Calibration { f0, coeffs })
+}
+
+/// Блок [`CALIBRATION_ADDR`] для записи, с CRC, если она есть в этой версии
+pub fn encode_calibration(calibration: &Calibration, version: u8) -> Vec<u8> {
+    let mut data = std::iter::once(calibration.f0)
+        .chain(calibration.coeffs)
+        .flat_map(f32::to_le_bytes)
+        .collect::<Vec<_>>();
+    if version >= CRC_VERSION {
+        data.push(crc8(&data));
+    }
+    data
+}
+
+/// Разбор блока [`OUTPUT_VALUES_ADDR`]
+pub fn decode_values(data: &[u8], version: u8) -> Result<SensorResult, DecodeError> {
+    let body = block::<OUTPUT_VALUES_LEN>(data, version)?;
+
+    let channel = |n: usize, name, range: Range| -> Result<Option<f32>, DecodeError> {
+        const SIZE: usize = std::mem::size_of::<f32>();
+        let mut bytes = [0u8; SIZE];
+        bytes.copy_from_slice(&body[n * SIZE..(n + 1) * SIZE]);
+        let value = f32::from_le_bytes(bytes);
+        if value.is_nan() {
+            Ok(None)
+        } else if range.contains(value) {
+            Ok(Some(value))
+        } else {
+            Err(DecodeError::OutOfRange {
+                channel: name,
+                value,
+            })
+        }
+    };
+
+    Ok(SensorResult {
+        pressure: channel(0, "pressure", PRESSURE_RANGE)?,
+        temperature: channel(1, "temperature", TEMPERATURE_RANGE)?,
+        f_p: channel(2, "f_p", FREQUENCY_RANGE)?,
+        f_t: channel(3, "f_t", FREQUENCY_RANGE)?,
+    })
+}
+
+/// Датчик давления SCTB на своей шине I2C как [`PressureGauge`]. Шина доступна
+/// через [`I2CGauge::bus`] для других датчиков на ней.
+pub struct I2CGauge<I2C> {
+    sensor: I2CSensor,
+    bus: I2C,
+    temperature: f32,
+}
+
+impl<I2C> I2CGauge<I2C> {
+    pub fn new(i2c_addr: u8, bus: I2C) -> Self {
+        Self {
+            sensor: I2CSensor::new(i2c_addr),
+            bus,
+            temperature: f32::NAN,
+        }
+    }
+
+    pub fn bus(&mut self) -> &mut I2C {
+        &mut self.bus
+    }
+
+    /// Датчик на другом адресе той же шины, опознается заново
+    pub fn set_address(&mut self, i2c_addr: u8) {
+        if self.sensor.address() != i2c_addr {
+            self.sensor = I2CSensor::new(i2c_addr);
+        }
+    }
+
+    /// Температура из последнего опроса, *C
+    pub fn temperature(&self) -> f32 {
+        self.temperature
+    }
+}
+
+impl<I2C, E> PressureGauge for I2CGauge<I2C>
+where
+    I2C: embedded_hal::blocking::i2c::WriteRead<Error = E>,
+    E: std::fmt::Debug,
+{
+    fn id(&self) -> String {
+        format!("SCTB I2C {}", self.sensor.address())
+    }
+
+    fn read(&mut self) -> GaugeReading {
+        let result = self.sensor.read(&mut self.bus);
+        self.temperature = match &result {
+            Ok(v) => v.temperature.unwrap_or(f32::NAN),
+            Err(_) => f32::NAN,
+        };
+        match result {
+            Ok(SensorResult {
+                pressure: Some(p), ..
+            }) => GaugeReading::ok(Pressure::new(p, PressureUnit::MmHg)),
+            Ok(_) => GaugeReading::failed(GaugeStatus::Fault, PressureUnit::MmHg),
+            Err(e) => {
+                println!(
+                    "Failed to read I2C sensor at {}: {e:?}",
+                    self.sensor.address()
+                );
+                let status = match e {
+                    ReadError::Bus(_) => GaugeStatus::Fault,
+                    ReadError::Decode(_) => GaugeStatus::NoResponse,
+                };
+                GaugeReading::failed(status, PressureUnit::MmHg)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockI2c, Nack};
+
+    const INFO: SensorInfo = SensorInfo {
+        serial: 21_000_011,
+        firmware: (1, 4),
+        period_ms: 100,
+    };
+    const CALIBRATION: Calibration = Calibration {
+        f0: 30000.0,
+        coeffs: [0.0, 0.25, 0.0, 0.0],
+    };
+    const NAN: f32 = f32::NAN;
+
+    fn bus(version: u8, values: [f32; 4]) -> MockI2c {
+        let mut bus = MockI2c::default();
+        bus.add_sctb(11, version, values, INFO, CALIBRATION);
+        bus
+    }
+
+    fn le(values: [f32; 4]) -> Vec<u8> {
+        values.into_iter().flat_map(f32::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn crc8_known_vector() {
+        assert_eq!(crc8(b"123456789"), 0xF7);
+        assert_eq!(crc8(&[]), 0xFF);
+    }
+
+    #[test]
+    fn legacy_values_little_endian() {
+        let data = le([12.5, 23.0, NAN, NAN]);
+        assert_eq!(&data[..4], [0x00, 0x00, 0x48, 0x41]);
+        assert_eq!(
+            decode_values(&data, LEGACY_VERSION),
+            Ok(SensorResult {
+                pressure: Some(12.5),
+                temperature: Some(23.0),
+                f_p: None,
+                f_t: None,
+            })
+        );
+
+        // без CRC блок длиннее 16 байт - ошибка длины
+        let mut long = data.clone();
+        long.push(crc8(&data));
+        assert_eq!(
+            decode_values(&long, LEGACY_VERSION),
+            Err(DecodeError::Length(17))
+        );
+        assert!(decode_values(&long, CRC_VERSION).is_ok());
+
+        for (values, channel) in [
+            ([f32::INFINITY, 23.0, NAN, NAN], "pressure"),
+            ([NAN, 300.0, NAN, NAN], "temperature"),
+            ([NAN, NAN, -1.0, NAN], "f_p"),
+        ] {
+            assert!(matches!(
+                decode_values(&le(values), LEGACY_VERSION),
+                Err(DecodeError::OutOfRange { channel: c, .. }) if c == channel
+            ));
+        }
+    }
+
+    #[test]
+    fn crc_checked_from_version_2() {
+        let mut data = le([NAN, 25.0, 30002.0, NAN]);
+        data.push(crc8(&data) ^ 1);
+        assert!(matches!(
+            decode_values(&data, CRC_VERSION),
+            Err(DecodeError::Crc { .. })
+        ));
+        // версия 1 без CRC: лишний байт - ошибка длины, а не CRC
+        assert_eq!(decode_values(&data, 1), Err(DecodeError::Length(17)));
+    }
+
+    #[test]
+    fn ident_block() {
+        assert_eq!(decode_ident(b"SCTB\x02"), Ok(2));
+        assert_eq!(decode_ident(b"SCTB\x07"), Err(DecodeError::Version(7)));
+        assert_eq!(decode_ident(b"ABCD\x01"), Err(DecodeError::Id(*b"ABCD")));
+    }
+
+    #[test]
+    fn info_and_calibration_round_trip() {
+        let data = encode_calibration(&CALIBRATION, 2);
+        assert_eq!(data.len(), CALIBRATION_LEN + 1);
+        assert_eq!(decode_calibration(&data, 2), Ok(CALIBRATION));
+        assert_eq!(CALIBRATION.pressure(30400.0), 100.0);
+
+        let mut bus = bus(2, [NAN, 25.0, 30400.0, NAN]);
+        let mut sensor = I2CSensor::new(11);
+        let info = sensor.read_info(&mut bus).unwrap();
+        assert_eq!(info.version, 2);
+        assert_eq!(info.info, INFO);
+        assert_eq!(info.calibration, CALIBRATION);
+        assert_eq!(sensor.read(&mut bus).unwrap().f_p, Some(30400.0));
+    }
+
+    #[test]
+    fn sensor_without_ident_block() {
+        let mut bus = bus(LEGACY_VERSION, [NAN, 24.0, 30100.0, NAN]);
+        let mut sensor = I2CSensor::new(11);
+        assert_eq!(sensor.identify(&mut bus).unwrap(), LEGACY_VERSION);
+        assert_eq!(sensor.read(&mut bus).unwrap().f_p, Some(30100.0));
+        assert!(matches!(
+            sensor.read_info(&mut bus),
+            Err(ReadError::Decode(DecodeError::Version(LEGACY_VERSION)))
+        ));
+
+        // на 0xF0 не "SCTB" - тоже штатная прошивка
+        bus.devices
+            .get_mut(&11)
+            .unwrap()
+            .insert(IDENT_ADDR, vec![0; 5]);
+        assert_eq!(
+            I2CSensor::new(11).identify(&mut bus).unwrap(),
+            LEGACY_VERSION
+        );
+
+        // мусор в показаниях - не датчик
+        bus.devices
+            .get_mut(&11)
+            .unwrap()
+            .insert(OUTPUT_VALUES_ADDR, le([1e9, 24.0, NAN, NAN]));
+        assert!(matches!(
+            I2CSensor::new(11).identify(&mut bus),
+            Err(ReadError::Decode(DecodeError::OutOfRange { .. }))
+        ));
+        assert!(matches!(
+            I2CSensor::new(12).identify(&mut bus),
+            Err(ReadError::Bus(Nack(12)))
+        ));
+    }
+
+    #[test]
+    fn scan_finds_both_maps() {
+        let mut bus = bus(2, [NAN, 25.0, 30000.0, NAN]);
+        bus.add_sctb(
+            15,
+            LEGACY_VERSION,
+            [760.0, 25.0, NAN, NAN],
+            INFO,
+            CALIBRATION,
+        );
+        bus.add_sctb(20, 1, [NAN, 25.0, 30000.0, NAN], INFO, CALIBRATION);
+        assert_eq!(
+            scan(&mut bus, SCAN_ADDRESSES),
+            [
+                SctbDevice {
+                    address: 11,
+                    version: 2
+                },
+                SctbDevice {
+                    address: 15,
+                    version: LEGACY_VERSION
+                },
+                SctbDevice {
+                    address: 20,
+                    version: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn bus_error_reidentifies() {
+        let mut bus = bus(2, [NAN, 25.0, 30000.0, NAN]);
+        let mut sensor = I2CSensor::new(11);
+        assert!(sensor.read(&mut bus).is_ok());
+        assert_eq!(sensor.version(), Some(2));
+
+        // датчик заменили на штатный
+        let registers = bus.devices.remove(&11).unwrap();
+        assert!(matches!(sensor.read(&mut bus), Err(ReadError::Bus(_))));
+        assert_eq!(sensor.version(), None);
+        bus.devices.insert(11, registers);
+        bus.devices.get_mut(&11).unwrap().remove(&IDENT_ADDR);
+        bus.devices
+            .get_mut(&11)
+            .unwrap()
+            .insert(OUTPUT_VALUES_ADDR, le([NAN, 25.0, 30000.0, NAN]));
+        assert!(sensor.read(&mut bus).is_ok());
+        assert_eq!(sensor.version(), Some(LEGACY_VERSION));
+    }
+}
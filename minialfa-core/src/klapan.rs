@@ -0,0 +1,57 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KlapanState {
+    Atmosphere,
+    Vacuum,
+    /// Камера отсечена и от насоса, и от атмосферы (проверка на течь)
+    Isolate,
+}
+
+pub struct Klapan<PIN> {
+    pin: PIN,
+    isolate_pin: Option<PIN>,
+}
+
+impl<E, PIN: embedded_hal::digital::v2::OutputPin<Error = E>> Klapan<PIN> {
+    pub fn new(pin: PIN) -> Self {
+        Self {
+            pin,
+            isolate_pin: None,
+        }
+    }
+
+    /// С отсечным клапаном, high - камера отсечена
+    pub fn with_isolation(pin: PIN, isolate_pin: PIN) -> Self {
+        Self {
+            pin,
+            isolate_pin: Some(isolate_pin),
+        }
+    }
+
+    /// Без отсечного клапана [`KlapanState::Isolate`] оставляет камеру на откачке
+    pub fn set_state(&mut self, state: KlapanState) -> Result<(), E> {
+        if let Some(isolate_pin) = &mut self.isolate_pin {
+            if state == KlapanState::Isolate {
+                isolate_pin.set_high()?;
+            } else {
+                isolate_pin.set_low()?;
+            }
+        }
+
+        match state {
+            KlapanState::Atmosphere => self.pin.set_low(),
+            KlapanState::Vacuum | KlapanState::Isolate => self.pin.set_high(),
+        }
+    }
+}
+
+impl<E, PIN> crate::platform::Valve for Klapan<PIN>
+where
+    PIN: embedded_hal::digital::v2::OutputPin<Error = E>,
+    E: std::fmt::Debug,
+{
+    type Error = E;
+
+    fn set_state(&mut self, state: KlapanState) -> Result<(), E> {
+        Klapan::set_state(self, state)
+    }
+}
@@ -0,0 +1,25 @@
+//! Аппаратно-независимая часть прошивки Мини-Альфа.
+//!
+//! Стейт-машина контроллера работает через трейты из [`platform`], поэтому её можно
+//! запускать как на ESP32 (адаптеры в `main.rs` прошивки), так и на хосте ([`mock`]).
+
+pub mod calibration;
+pub mod controller;
+pub mod display;
+pub mod framebuffer;
+pub mod gauge;
+pub mod i2c_sensor;
+pub mod klapan;
+pub mod linear_regression;
+pub mod mock;
+pub mod modbus;
+pub mod pfeiffer_sensor;
+pub mod physics;
+pub mod platform;
+pub mod profile;
+pub mod remote;
+pub mod settings;
+pub mod stream;
+pub mod support;
+pub mod thyracont_sensor;
+pub mod verdict;
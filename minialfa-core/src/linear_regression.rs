@@ -0,0 +1,223 @@
+#![allow(dead_code)]
+
+use std::ops::Add;
+
+#[derive(Clone, Copy, Debug)]
+pub struct LinearRegressionResult {
+    pub k: f32,
+    pub b: f32,
+}
+
+impl LinearRegressionResult {
+    pub fn calc(&self, x: f32) -> f32 {
+        self.k * x + self.b
+    }
+}
+
+/// Прямая с оценкой качества аппроксимации
+#[derive(Clone, Copy, Debug)]
+pub struct RegressionFit {
+    pub line: LinearRegressionResult,
+    /// Коэффициент детерминации
+    pub r2: f32,
+    /// Стандартная ошибка наклона k
+    pub k_std_err: f32,
+    /// Точек использовано
+    pub points: usize,
+    /// Точек отброшено как выбросы
+    pub rejected: usize,
+}
+
+// https://www.freecodecamp.org/news/the-least-squares-regression-method-explained/
+// k = sum((x - x_av) * (y - y_av))/sum((x - x_av)^2)
+pub fn linear_regression(data: &[(f32, f32)]) -> LinearRegressionResult {
+    let av = data
+        .iter()
+        .fold((0.0, 0.0), |acc, &p| (acc.0.add(p.0), acc.1.add(p.1)));
+
+    let av = (av.0 / data.len() as f32, av.1 / data.len() as f32);
+
+    let sums = data.iter().fold((0.0, 0.0), |acc, &p| {
+        (
+            acc.0.add((p.0 - av.0) * (p.1 - av.1)),
+            acc.1.add((p.0 - av.0).powi(2)),
+        )
+    });
+
+    let k = sums.0 / sums.1;
+    let b = av.1 - k * av.0;
+    LinearRegressionResult { k, b }
+}
+
+// R2 = 1 - SSE / SST
+// se(k) = sqrt(SSE / (n - 2) / sum((x - x_av)^2))
+pub fn fit_quality(data: &[(f32, f32)], line: &LinearRegressionResult) -> (f32, f32) {
+    let n = data.len() as f32;
+    let av = data
+        .iter()
+        .fold((0.0, 0.0), |acc, &p| (acc.0 + p.0 / n, acc.1 + p.1 / n));
+
+    let (sse, sst, sxx) = data.iter().fold((0.0, 0.0, 0.0), |acc, &p| {
+        (
+            acc.0 + (p.1 - line.calc(p.0)).powi(2),
+            acc.1 + (p.1 - av.1).powi(2),
+            acc.2 + (p.0 - av.0).powi(2),
+        )
+    });
+
+    let r2 = if sst > 0.0 { 1.0 - sse / sst } else { 1.0 };
+    let k_std_err = (sse / (n - 2.0) / sxx).sqrt();
+    (r2, k_std_err)
+}
+
+/// Регрессия с отбрасыванием выбросов: точки с остатком больше `max_sigma` СКО остатков
+/// удаляются, прямая пересчитывается, пока выбросы не кончатся.
+/// None, если для оценки осталось меньше 3 точек или все x одинаковы.
+pub fn robust_linear_regression(data: &[(f32, f32)], max_sigma: f32) -> Option<RegressionFit> {
+    const MAX_ITERATIONS: usize = 5;
+
+    let mut points = data
+        .iter()
+        .copied()
+        .filter(|p| p.0.is_finite() && p.1.is_finite())
+        .collect::<Vec<_>>();
+
+    let mut iteration = 0;
+    loop {
+        if points.len() < 3 {
+            return None;
+        }
+
+        let line = linear_regression(&points);
+        if !line.k.is_finite() {
+            return None;
+        }
+
+        let sigma = (points
+            .iter()
+            .map(|p| (p.1 - line.calc(p.0)).powi(2))
+            .sum::<f32>()
+            / (points.len() - 2) as f32)
+            .sqrt();
+
+        let before = points.len();
+        if iteration < MAX_ITERATIONS && sigma > 0.0 {
+            points.retain(|p| (p.1 - line.calc(p.0)).abs() <= max_sigma * sigma);
+        }
+        iteration += 1;
+
+        if points.len() == before {
+            let (r2, k_std_err) = fit_quality(&points, &line);
+            return Some(RegressionFit {
+                line,
+                r2,
+                k_std_err,
+                points: points.len(),
+                rejected: data.len() - points.len(),
+            });
+        }
+    }
+}
+
+/// МНК-полином степени `degree`: коэффициенты [c0, c1, ... cn] для y = c0 + c1 * x + ...
+/// Нормальные уравнения решаются в f64 методом Гаусса с выбором ведущего элемента.
+/// None, если точек не больше степени или система вырождена.
+pub fn polynomial_regression(data: &[(f32, f32)], degree: usize) -> Option<Vec<f32>> {
+    let n = degree + 1;
+    if data.len() < n {
+        return None;
+    }
+
+    // [A | b], A[i][j] = sum(x^(i+j)), b[i] = sum(y * x^i)
+    let mut m = vec![vec![0.0f64; n + 1]; n];
+    for &(x, y) in data {
+        let (x, y) = (x as f64, y as f64);
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, cell) in row[..n].iter_mut().enumerate() {
+                *cell += x.powi((i + j) as i32);
+            }
+            row[n] += y * x.powi(i as i32);
+        }
+    }
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))?;
+        if m[pivot][col].abs() < f64::EPSILON {
+            return None;
+        }
+        m.swap(col, pivot);
+
+        let (top, rest) = m.split_at_mut(col + 1);
+        let pivot_row = &top[col];
+        for row in rest.iter_mut() {
+            let k = row[col] / pivot_row[col];
+            for (cell, p) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *cell -= k * p;
+            }
+        }
+    }
+
+    let mut c = vec![0.0f64; n];
+    for i in (0..n).rev() {
+        let s = ((i + 1)..n).map(|j| m[i][j] * c[j]).sum::<f64>();
+        c[i] = (m[i][n] - s) / m[i][i];
+    }
+
+    c.iter()
+        .all(|c| c.is_finite())
+        .then(|| c.into_iter().map(|c| c as f32).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(k: f32, b: f32) -> Vec<(f32, f32)> {
+        (0..30).map(|i| (i as f32, k * i as f32 + b)).collect()
+    }
+
+    #[test]
+    fn exact_line() {
+        let fit = robust_linear_regression(&line(4.0, 30000.0), 3.0).unwrap();
+        assert!((fit.line.k - 4.0).abs() < 1e-3);
+        assert!((fit.line.b - 30000.0).abs() < 0.1);
+        assert!(fit.r2 > 0.9999);
+        assert_eq!((fit.points, fit.rejected), (30, 0));
+    }
+
+    #[test]
+    fn rejects_outlier() {
+        let mut data = line(2.0, 100.0);
+        // небольшой шум, чтобы СКО остатков не было нулевым
+        for (i, p) in data.iter_mut().enumerate() {
+            p.1 += if i % 2 == 0 { 0.01 } else { -0.01 };
+        }
+        data[10].1 += 50.0;
+        data.push((f32::NAN, 1.0));
+
+        let fit = robust_linear_regression(&data, 3.0).unwrap();
+        assert_eq!(fit.rejected, 2);
+        assert!((fit.line.k - 2.0).abs() < 1e-3, "{}", fit.line.k);
+    }
+
+    #[test]
+    fn polynomial_exact() {
+        let data = [100.0f32, 30.0, 10.0, 3.0, 1.0]
+            .iter()
+            .map(|&p| (p, 30000.0 + 4.0 * p - 0.01 * p * p))
+            .collect::<Vec<_>>();
+        let c = polynomial_regression(&data, 2).unwrap();
+        assert!((c[0] - 30000.0).abs() < 0.05, "{c:?}");
+        assert!((c[1] - 4.0).abs() < 1e-3, "{c:?}");
+        assert!((c[2] + 0.01).abs() < 1e-5, "{c:?}");
+
+        assert!(polynomial_regression(&data[..2], 2).is_none());
+        assert!(polynomial_regression(&[(1.0, 1.0), (1.0, 2.0), (1.0, 3.0)], 2).is_none());
+    }
+
+    #[test]
+    fn degenerate_data() {
+        assert!(robust_linear_regression(&line(1.0, 0.0)[..2], 3.0).is_none());
+        assert!(robust_linear_regression(&[(1.0, 1.0), (1.0, 2.0), (1.0, 3.0)], 3.0).is_none());
+    }
+}
@@ -0,0 +1,385 @@
+//! Адаптеры [`crate::platform`] в памяти для запуска контроллера на хосте.
+
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    calibration::{self, WriteError},
+    i2c_sensor::{self, Calibration, I2CSensor, SctbDevice, SctbInfo, SensorInfo},
+    klapan::KlapanState,
+    pfeiffer_sensor::{self, Telegram},
+    platform::{Clock, SensorsControl, SettingsStorage, Valve},
+    thyracont_sensor::{self, encode_frame},
+};
+
+/// Часы, которые идут только по команде. Клоны разделяют одно время.
+#[derive(Clone, Default)]
+pub struct ManualClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, dt: Duration) {
+        self.now.set(self.now.get() + dt);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+/// Клапан, запоминающий последнее состояние.
+#[derive(Default)]
+pub struct MockValve {
+    pub state: Option<KlapanState>,
+}
+
+impl Valve for MockValve {
+    type Error = Infallible;
+
+    fn set_state(&mut self, state: KlapanState) -> Result<(), Infallible> {
+        self.state = Some(state);
+        Ok(())
+    }
+}
+
+/// Опрос датчиков: запоминает, запущен ли он и с каким периодом. Датчики SCTB - на
+/// шине [`MockI2c`], если она задана.
+#[derive(Default)]
+pub struct MockSensors {
+    pub period: Option<Duration>,
+    /// Идентификаторы образцовых датчиков
+    pub gauges: Vec<String>,
+    /// Шина SCTB, None - драйвер без датчиков SCTB
+    pub sctb: Option<MockI2c>,
+    /// Испытуемые датчики по [`SensorsControl::assign_sctb`]
+    pub duts: Vec<I2CSensor>,
+}
+
+impl SensorsControl for MockSensors {
+    type Error = Infallible;
+
+    fn start(&mut self, period: Duration) -> Result<(), Infallible> {
+        self.period = Some(period);
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), Infallible> {
+        self.period = None;
+        Ok(())
+    }
+
+    fn gauges(&self) -> &[String] {
+        &self.gauges
+    }
+
+    fn dut_info(&mut self, dut: usize) -> Option<SctbInfo> {
+        let bus = self.sctb.as_mut()?;
+        self.duts.get_mut(dut)?.read_info(bus).ok()
+    }
+
+    fn write_dut_calibration(
+        &mut self,
+        dut: usize,
+        calibration: &Calibration,
+    ) -> Result<(), WriteError> {
+        let bus = self.sctb.as_mut().ok_or(WriteError::Unsupported)?;
+        let dut = self.duts.get_mut(dut).ok_or(WriteError::Bus)?;
+        calibration::program(dut, bus, calibration)
+    }
+
+    fn scan_sctb(&mut self) -> Option<Vec<SctbDevice>> {
+        let bus = self.sctb.as_mut()?;
+        Some(i2c_sensor::scan(bus, i2c_sensor::SCAN_ADDRESSES))
+    }
+
+    fn assign_sctb(&mut self, _reference: u8, duts: &[u8]) {
+        self.duts = duts
+            .iter()
+            .map(|&address| I2CSensor::new(address))
+            .collect();
+    }
+}
+
+/// Хранилище настроек в памяти.
+#[derive(Default)]
+pub struct MemoryStorage {
+    u8_values: HashMap<String, u8>,
+    u32_values: HashMap<String, u32>,
+    str_values: HashMap<String, String>,
+    blob_values: HashMap<String, Vec<u8>>,
+}
+
+impl SettingsStorage for MemoryStorage {
+    type Error = Infallible;
+
+    fn get_u8(&self, key: &str) -> Result<Option<u8>, Infallible> {
+        Ok(self.u8_values.get(key).copied())
+    }
+
+    fn set_u8(&mut self, key: &str, value: u8) -> Result<(), Infallible> {
+        self.u8_values.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn get_u32(&self, key: &str) -> Result<Option<u32>, Infallible> {
+        Ok(self.u32_values.get(key).copied())
+    }
+
+    fn set_u32(&mut self, key: &str, value: u32) -> Result<(), Infallible> {
+        self.u32_values.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn get_str(&self, key: &str) -> Result<Option<String>, Infallible> {
+        Ok(self.str_values.get(key).cloned())
+    }
+
+    fn set_str(&mut self, key: &str, value: &str) -> Result<(), Infallible> {
+        self.str_values.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn get_blob(&self, key: &str) -> Result<Option<Vec<u8>>, Infallible> {
+        Ok(self.blob_values.get(key).cloned())
+    }
+
+    fn set_blob(&mut self, key: &str, value: &[u8]) -> Result<(), Infallible> {
+        self.blob_values.insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+}
+
+/// Неподключенный выход (RE/DE линии RS485).
+pub struct MockPin;
+
+impl embedded_hal::digital::v2::OutputPin for MockPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// Устройство на линии: запрос целиком, ответ или молчание
+pub type SerialDevice = Box<dyn FnMut(&[u8]) -> Option<Vec<u8>> + Send>;
+
+/// Последовательный порт с устройством на другом конце: запрос до `\r` передается
+/// в `device`, его ответ отдается при чтении.
+pub struct MockSerial {
+    device: SerialDevice,
+    request: Vec<u8>,
+    response: VecDeque<u8>,
+    /// Все запросы, для проверки
+    pub requests: Vec<Vec<u8>>,
+}
+
+impl MockSerial {
+    pub fn new(device: impl FnMut(&[u8]) -> Option<Vec<u8>> + Send + 'static) -> Self {
+        Self {
+            device: Box::new(device),
+            request: Vec::new(),
+            response: VecDeque::new(),
+            requests: Vec::new(),
+        }
+    }
+}
+
+impl embedded_hal::serial::Read<u8> for MockSerial {
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Infallible> {
+        self.response.pop_front().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl embedded_hal::serial::Write<u8> for MockSerial {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Infallible> {
+        self.request.push(word);
+        if word == b'\r' {
+            let request = std::mem::take(&mut self.request);
+            if let Some(response) = (self.device)(&request) {
+                self.response.extend(response);
+            }
+            self.requests.push(request);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// Датчик Pfeiffer для [`MockSerial`]: отвечает на запросы типа и давления (hPa),
+/// на остальные параметры - NO_DEF.
+pub fn pfeiffer_device(
+    addr: u8,
+    id: &str,
+    p_hpa: Arc<Mutex<f32>>,
+) -> impl FnMut(&[u8]) -> Option<Vec<u8>> + Send {
+    let id = id.to_string();
+    move |request| {
+        let query = Telegram::decode(request).ok()?;
+        if query.addr != addr {
+            return None;
+        }
+        let data = match query.param {
+            pfeiffer_sensor::PARAM_TYPE => id.clone(),
+            pfeiffer_sensor::PARAM_PRESSURE => {
+                pfeiffer_sensor::encode_pressure(*p_hpa.lock().unwrap())
+            }
+            _ => "NO_DEF".to_string(),
+        };
+        Some(Telegram::reply(addr, query.param, &data).encode())
+    }
+}
+
+/// Датчик Thyracont для [`MockSerial`]: модель, давление (mbar), юстировка, две точки
+/// переключения и сведения о приборе. Неизвестные коды и чужие адреса - молчание.
+pub fn thyracont_device(
+    addr: u8,
+    model: &str,
+    p_mbar: Arc<Mutex<f32>>,
+) -> impl FnMut(&[u8]) -> Option<Vec<u8>> + Send {
+    let model = model.to_string();
+    let mut switch_points = [1e-2, 1.0];
+    move |request| {
+        // адрес, код, сумма, \r
+        if request.len() < 6 || request[..3] != *format!("{addr:03}").as_bytes() {
+            return None;
+        }
+        let (code, data) = (request[3], &request[4..request.len() - 2]);
+        let reply = match code {
+            thyracont_sensor::CODE_ID => model.clone().into_bytes(),
+            thyracont_sensor::CODE_MEASURE => {
+                thyracont_sensor::encode_pressure(*p_mbar.lock().unwrap()).into_bytes()
+            }
+            // юстировка только подтверждается
+            thyracont_sensor::CODE_ADJUST => data.to_vec(),
+            thyracont_sensor::CODE_GET_SWITCH_POINT | thyracont_sensor::CODE_SET_SWITCH_POINT => {
+                let (&n, p) = data.split_first()?;
+                let point = switch_points.get_mut(n.checked_sub(b'1')? as usize)?;
+                if code == thyracont_sensor::CODE_SET_SWITCH_POINT {
+                    *point = thyracont_sensor::decode_pressure(p)?;
+                }
+                let mut reply = vec![n];
+                reply.extend(thyracont_sensor::encode_pressure(*point).bytes());
+                reply
+            }
+            thyracont_sensor::CODE_UNIT => b"mbar".to_vec(),
+            thyracont_sensor::CODE_SERIAL => format!("SN{addr:05}").into_bytes(),
+            thyracont_sensor::CODE_FIRMWARE => b"2.1".to_vec(),
+            thyracont_sensor::CODE_HOURS => b"1234".to_vec(),
+            _ => return None,
+        };
+        Some(encode_frame(addr, code, &reply))
+    }
+}
+
+/// Несколько устройств на одной линии, отвечает первое отозвавшееся
+pub fn bus(mut devices: Vec<SerialDevice>) -> impl FnMut(&[u8]) -> Option<Vec<u8>> + Send {
+    move |request| devices.iter_mut().find_map(|device| device(request))
+}
+
+/// Адрес I2C не ответил
+#[derive(Debug, PartialEq)]
+pub struct Nack(pub u8);
+
+/// Шина I2C в памяти: у каждого адреса блоки регистров, чтение отдает начало блока.
+/// Нет адреса или блока - NACK. Запись заменяет блок, если датчик не в `read_only`.
+#[derive(Default)]
+pub struct MockI2c {
+    pub devices: HashMap<u8, HashMap<u8, Vec<u8>>>,
+    /// Датчики, которые принимают запись, но ничего не меняют
+    pub read_only: Vec<u8>,
+}
+
+impl MockI2c {
+    /// Датчик SCTB с картой `version`, [`i2c_sensor::LEGACY_VERSION`] - штатная прошивка
+    /// только с показаниями. Показания: P, T, F_P, F_T.
+    pub fn add_sctb(
+        &mut self,
+        address: u8,
+        version: u8,
+        values: [f32; 4],
+        info: SensorInfo,
+        calibration: Calibration,
+    ) {
+        let with_crc = |mut block: Vec<u8>| {
+            if version >= i2c_sensor::CRC_VERSION {
+                block.push(i2c_sensor::crc8(&block));
+            }
+            block
+        };
+        let mut registers = HashMap::new();
+        registers.insert(
+            i2c_sensor::OUTPUT_VALUES_ADDR,
+            with_crc(values.into_iter().flat_map(f32::to_le_bytes).collect()),
+        );
+        if version != i2c_sensor::LEGACY_VERSION {
+            let mut ident = i2c_sensor::IDENT_MAGIC.to_vec();
+            ident.push(version);
+            registers.insert(i2c_sensor::IDENT_ADDR, ident);
+        }
+        if version >= i2c_sensor::INFO_VERSION {
+            let mut block = info.serial.to_le_bytes().to_vec();
+            block.extend([info.firmware.0, info.firmware.1]);
+            block.extend(info.period_ms.to_le_bytes());
+            registers.insert(i2c_sensor::INFO_ADDR, with_crc(block));
+            registers.insert(
+                i2c_sensor::CALIBRATION_ADDR,
+                i2c_sensor::encode_calibration(&calibration, version),
+            );
+        }
+        self.devices.insert(address, registers);
+    }
+}
+
+impl embedded_hal::blocking::i2c::WriteRead for MockI2c {
+    type Error = Nack;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Nack> {
+        let block = self
+            .devices
+            .get(&address)
+            .and_then(|registers| registers.get(bytes.first()?))
+            .ok_or(Nack(address))?;
+        // за концом блока шина читает 0xFF
+        buffer.fill(0xFF);
+        let n = block.len().min(buffer.len());
+        buffer[..n].copy_from_slice(&block[..n]);
+        Ok(())
+    }
+}
+
+impl embedded_hal::blocking::i2c::Write for MockI2c {
+    type Error = Nack;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Nack> {
+        let registers = self.devices.get_mut(&address).ok_or(Nack(address))?;
+        let (&reg, block) = bytes.split_first().ok_or(Nack(address))?;
+        if !self.read_only.contains(&address) {
+            registers.insert(reg, block.to_vec());
+        }
+        Ok(())
+    }
+}
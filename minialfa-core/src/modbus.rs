@@ -0,0 +1,762 @@
+//! Modbus RTU slave на отдельном UART: кадры, CRC и карта регистров.
+//!
+//! f32 занимает два регистра, старшее слово первым. Значения, которых нет в текущем
+//! результате, читаются как NaN.
+//!
+//! ```text
+//! Coils (0x01 чтение, 0x05/0x0F запись 1 - выполнить)
+//!   0..3   START AUTO/MANUAL/CURVE/LEAK, при чтении 1 - идет измерение в этом режиме
+//!   4      ABORT
+//!   5      SAVE
+//!
+//! Holding (0x03 чтение, 0x10 запись парами) - параметры remote::PARAMETER_NAMES
+//!   2*i    f32 параметра i: 0 threshold, 2 update_period_ms ... 52 stream, 54 write_calibration,
+//!          56 sctb_reference, 58 sctb_dut, 60 auto_start, 62 dut_count, 64..68 sctb_dut2..4
+//!
+//! Input (0x04)
+//!   0      состояние: 0 title, 1 profile, 2 setup, 3 measuring, 4 result, 5 gauge_setup,
+//!          6 diagnostics
+//!   1      режим: 0 auto, 1 manual, 2 curve, 3 leak
+//!   2      f32 P, mmHg          4  f32 F, Hz          6  f32 T, *C
+//!   8      f32 порог, mmHg      10 f32 таймер удержания или отсечки, s
+//!   12     счетчик законченных измерений
+//!   13     активный профиль
+//!   14     DUT на шине I2C: 0 не опрашивается, 1 нет, 2 подключены все
+//!   15     DUT в последнем результате
+//!   20     результат: 0 нет, 1 чувствительность, 2 кривая, 3 течь
+//!   21     вердикт: 0 нет, 1 ГОДЕН, 2 БРАК
+//!   22     критерий БРАК: 1 чувств., 2 F атм, 3 F вак, 4 T, 5 течь
+//!   23     точек кривой
+//!   24     f32 P (течь - в конце)   26 f32 F          28 f32 T
+//!   30     f32 чувствительность     32 f32 F атм      34 f32 удержание/отсечка, s
+//!   36     f32 течь, mbar·l/s       38 f32 dP/dt, mbar/s
+//!   40     u32 серийный номер DUT результата, 0 - не прочитан
+//!   42     версия ПО DUT: старший байт - major, младший - minor
+//!   43     период измерения DUT, ms
+//!   44     f32 F0 калибровки DUT  46..52 f32 c0..c3
+//!   60..   то же для DUT 2, 100.. DUT 3, 140.. DUT 4 (20 + 40*i)
+//! ```
+
+use crossbeam::channel::Sender;
+
+use crate::controller::{Parameters, MAX_DUTS};
+use crate::i2c_sensor::{self, SctbInfo};
+use crate::remote::{
+    DeviceState, DutResult, RemoteError, RunMode, RunResult, Status, PARAMETER_NAMES,
+};
+use crate::verdict::{Criterion, Verdict};
+
+/// Адрес прибора на шине
+pub const DEFAULT_ADDRESS: u8 = 1;
+/// Максимальная длина кадра RTU
+pub const MAX_FRAME: usize = 256;
+
+/// Запрос и канал для ответа контроллера
+pub type Transaction = (Request, Sender<Result<Response, Exception>>);
+
+const MAX_READ_BITS: u16 = 2000;
+const MAX_READ_REGISTERS: u16 = 125;
+const MAX_WRITE_BITS: u16 = 1968;
+const MAX_WRITE_REGISTERS: u16 = 123;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    ReadCoils { address: u16, count: u16 },
+    ReadHoldingRegisters { address: u16, count: u16 },
+    ReadInputRegisters { address: u16, count: u16 },
+    WriteSingleCoil { address: u16, value: bool },
+    WriteSingleRegister { address: u16, value: u16 },
+    WriteMultipleCoils { address: u16, values: Vec<bool> },
+    WriteMultipleRegisters { address: u16, values: Vec<u16> },
+}
+
+impl Request {
+    pub fn function(&self) -> u8 {
+        match self {
+            Request::ReadCoils { .. } => 0x01,
+            Request::ReadHoldingRegisters { .. } => 0x03,
+            Request::ReadInputRegisters { .. } => 0x04,
+            Request::WriteSingleCoil { .. } => 0x05,
+            Request::WriteSingleRegister { .. } => 0x06,
+            Request::WriteMultipleCoils { .. } => 0x0F,
+            Request::WriteMultipleRegisters { .. } => 0x10,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Response {
+    Coils(Vec<bool>),
+    Registers(Vec<u16>),
+    /// Эхо записи: адрес и значение (0x05/0x06) или количество (0x0F/0x10)
+    Written {
+        address: u16,
+        value: u16,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Exception {
+    IllegalFunction = 1,
+    IllegalDataAddress = 2,
+    IllegalDataValue = 3,
+    SlaveDeviceFailure = 4,
+    SlaveDeviceBusy = 6,
+}
+
+impl From<RemoteError> for Exception {
+    fn from(e: RemoteError) -> Self {
+        match e {
+            RemoteError::UnknownParameter(_) => Exception::IllegalDataAddress,
+            RemoteError::BadValue(_) | RemoteError::OutOfRange(_) => Exception::IllegalDataValue,
+            RemoteError::Busy(_) => Exception::SlaveDeviceBusy,
+            _ => Exception::SlaveDeviceFailure,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodeError {
+    /// Кадр короче минимального
+    TooShort,
+    /// Помеха или коллизия, на такой кадр не отвечают
+    BadCrc { expected: u16, actual: u16 },
+    /// Кадр цел, но запрос не поддерживается - ответ исключением
+    Unsupported {
+        address: u8,
+        function: u8,
+        exception: Exception,
+    },
+}
+
+/// CRC-16/MODBUS, в кадре передается младшим байтом вперед
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &b in data {
+        crc ^= b as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn with_crc(mut frame: Vec<u8>) -> Vec<u8> {
+    let crc = crc16(&frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame
+}
+
+fn u16_at(data: &[u8], i: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*data.get(i)?, *data.get(i + 1)?]))
+}
+
+/// Разбор кадра запроса: адрес прибора и запрос
+pub fn decode_request(frame: &[u8]) -> Result<(u8, Request), DecodeError> {
+    if frame.len() < 4 {
+        return Err(DecodeError::TooShort);
+    }
+    let (body, crc) = frame.split_at(frame.len() - 2);
+    let expected = u16::from_le_bytes([crc[0], crc[1]]);
+    let actual = crc16(body);
+    if expected != actual {
+        return Err(DecodeError::BadCrc { expected, actual });
+    }
+
+    let (address, function, data) = (body[0], body[1], &body[2..]);
+    let error = |exception| DecodeError::Unsupported {
+        address,
+        function,
+        exception,
+    };
+    let bad_value = || error(Exception::IllegalDataValue);
+
+    match function {
+        0x01 | 0x03 | 0x04 | 0x05 | 0x06 if data.len() != 4 => return Err(bad_value()),
+        0x01 | 0x03 | 0x04 | 0x05 | 0x06 | 0x0F | 0x10 => {}
+        _ => return Err(error(Exception::IllegalFunction)),
+    }
+    let first = u16_at(data, 0).ok_or_else(bad_value)?;
+    let second = u16_at(data, 2).ok_or_else(bad_value)?;
+    let check_count = |count: u16, max: u16| {
+        if (1..=max).contains(&count) {
+            Ok(count)
+        } else {
+            Err(bad_value())
+        }
+    };
+    // 0x0F/0x10: адрес, количество, байт данных, данные
+    let payload = |count: u16, bytes: usize| {
+        let n = *data.get(4).ok_or_else(bad_value)? as usize;
+        match data.get(5..) {
+            Some(payload) if n == bytes && payload.len() == n => Ok((count, payload)),
+            _ => Err(bad_value()),
+        }
+    };
+
+    let request = match function {
+        0x01 => Request::ReadCoils {
+            address: first,
+            count: check_count(second, MAX_READ_BITS)?,
+        },
+        0x03 => Request::ReadHoldingRegisters {
+            address: first,
+            count: check_count(second, MAX_READ_REGISTERS)?,
+        },
+        0x04 => Request::ReadInputRegisters {
+            address: first,
+            count: check_count(second, MAX_READ_REGISTERS)?,
+        },
+        0x05 => Request::WriteSingleCoil {
+            address: first,
+            value: match second {
+                0xFF00 => true,
+                0x0000 => false,
+                _ => return Err(bad_value()),
+            },
+        },
+        0x06 => Request::WriteSingleRegister {
+            address: first,
+            value: second,
+        },
+        0x0F => {
+            let count = check_count(second, MAX_WRITE_BITS)?;
+            let (count, bits) = payload(count, (count as usize).div_ceil(8))?;
+            Request::WriteMultipleCoils {
+                address: first,
+                values: (0..count as usize)
+                    .map(|i| bits[i / 8] & (1 << (i % 8)) != 0)
+                    .collect(),
+            }
+        }
+        0x10 => {
+            let count = check_count(second, MAX_WRITE_REGISTERS)?;
+            let (_, words) = payload(count, count as usize * 2)?;
+            Request::WriteMultipleRegisters {
+                address: first,
+                values: words
+                    .chunks(2)
+                    .map(|w| u16::from_be_bytes([w[0], w[1]]))
+                    .collect(),
+            }
+        }
+        _ => return Err(error(Exception::IllegalFunction)),
+    };
+    Ok((address, request))
+}
+
+/// Кадр запроса, для мастера и проверок на хосте
+pub fn encode_request(address: u8, request: &Request) -> Vec<u8> {
+    let mut frame = vec![address, request.function()];
+    let mut push = |v: u16| frame.extend_from_slice(&v.to_be_bytes());
+    match request {
+        Request::ReadCoils { address, count }
+        | Request::ReadHoldingRegisters { address, count }
+        | Request::ReadInputRegisters { address, count } => {
+            push(*address);
+            push(*count);
+        }
+        Request::WriteSingleCoil { address, value } => {
+            push(*address);
+            push(if *value { 0xFF00 } else { 0 });
+        }
+        Request::WriteSingleRegister { address, value } => {
+            push(*address);
+            push(*value);
+        }
+        Request::WriteMultipleCoils { address, values } => {
+            push(*address);
+            push(values.len() as u16);
+            let bits = pack_bits(values);
+            frame.push(bits.len() as u8);
+            frame.extend_from_slice(&bits);
+        }
+        Request::WriteMultipleRegisters { address, values } => {
+            push(*address);
+            push(values.len() as u16);
+            frame.push((values.len() * 2) as u8);
+            for v in values {
+                frame.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+    }
+    with_crc(frame)
+}
+
+fn pack_bits(values: &[bool]) -> Vec<u8> {
+    let mut bytes = vec![0u8; values.len().div_ceil(8)];
+    for (i, _) in values.iter().enumerate().filter(|(_, v)| **v) {
+        bytes[i / 8] |= 1 << (i % 8);
+    }
+    bytes
+}
+
+/// Кадр ответа на запрос с кодом функции `function`
+pub fn encode_response(
+    address: u8,
+    function: u8,
+    response: &Result<Response, Exception>,
+) -> Vec<u8> {
+    let frame = match response {
+        Err(exception) => vec![address, function | 0x80, *exception as u8],
+        Ok(Response::Coils(values)) => {
+            let bits = pack_bits(values);
+            let mut frame = vec![address, function, bits.len() as u8];
+            frame.extend_from_slice(&bits);
+            frame
+        }
+        Ok(Response::Registers(values)) => {
+            let mut frame = vec![address, function, (values.len() * 2) as u8];
+            for v in values {
+                frame.extend_from_slice(&v.to_be_bytes());
+            }
+            frame
+        }
+        Ok(Response::Written { address: a, value }) => {
+            let mut frame = vec![address, function];
+            frame.extend_from_slice(&a.to_be_bytes());
+            frame.extend_from_slice(&value.to_be_bytes());
+            frame
+        }
+    };
+    with_crc(frame)
+}
+
+fn push_f32(registers: &mut Vec<u16>, v: f32) {
+    let bits = v.to_bits();
+    registers.push((bits >> 16) as u16);
+    registers.push(bits as u16);
+}
+
+/// Окно `count` регистров с `address`, целиком внутри карты
+fn window<T: Clone>(map: &[T], address: u16, count: u16) -> Result<Vec<T>, Exception> {
+    let start = address as usize;
+    map.get(start..start + count as usize)
+        .map(<[T]>::to_vec)
+        .ok_or(Exception::IllegalDataAddress)
+}
+
+/// Команда, которую выполняет запись 1 в coil
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coil {
+    Start(RunMode),
+    Abort,
+    Save,
+}
+
+impl Coil {
+    const ALL: [Coil; 6] = [
+        Coil::Start(RunMode::Auto),
+        Coil::Start(RunMode::Manual),
+        Coil::Start(RunMode::Curve),
+        Coil::Start(RunMode::Leak),
+        Coil::Abort,
+        Coil::Save,
+    ];
+
+    /// Все coils с `address` по `address + count`
+    pub fn range(address: u16, count: u16) -> Result<Vec<Coil>, Exception> {
+        window(&Self::ALL, address, count)
+    }
+}
+
+pub fn read_coils(status: &Status, address: u16, count: u16) -> Result<Vec<bool>, Exception> {
+    Ok(Coil::range(address, count)?
+        .into_iter()
+        .map(|coil| match coil {
+            Coil::Start(mode) => status.state == DeviceState::Measuring && status.mode == mode,
+            Coil::Abort | Coil::Save => false,
+        })
+        .collect())
+}
+
+pub fn read_holding(
+    parameters: &Parameters,
+    address: u16,
+    count: u16,
+) -> Result<Vec<u16>, Exception> {
+    let mut map = Vec::with_capacity(PARAMETER_NAMES.len() * 2);
+    for name in PARAMETER_NAMES {
+        push_f32(&mut map, parameters.get_f32(name).unwrap_or(f32::NAN));
+    }
+    window(&map, address, count)
+}
+
+/// Параметры меняются только все вместе: при ошибке в любом не меняется ни один
+pub fn write_holding(
+    parameters: &mut Parameters,
+    address: u16,
+    values: &[u16],
+) -> Result<(), Exception> {
+    if !address.is_multiple_of(2) || !values.len().is_multiple_of(2) {
+        return Err(Exception::IllegalDataAddress);
+    }
+    let first = address as usize / 2;
+    let names = PARAMETER_NAMES
+        .get(first..first + values.len() / 2)
+        .ok_or(Exception::IllegalDataAddress)?;
+
+    let mut updated = *parameters;
+    for (name, words) in names.iter().zip(values.chunks(2)) {
+        let value = f32::from_bits((words[0] as u32) << 16 | words[1] as u32);
+        updated.set_f32(name, value)?;
+    }
+    *parameters = updated;
+    Ok(())
+}
+
+/// Шаг блоков результата DUT во входных регистрах
+const RESULT_STRIDE: usize = 40;
+
+pub fn read_input(
+    status: &Status,
+    results: &[DutResult],
+    address: u16,
+    count: u16,
+) -> Result<Vec<u16>, Exception> {
+    let mut map = vec![status.state as u16, status.mode as u16];
+    for v in [
+        status.p,
+        status.f,
+        status.t,
+        status.threshold,
+        status.timer_s,
+    ] {
+        push_f32(&mut map, v);
+    }
+    map.push(status.results);
+    map.push(status.profile as u16);
+    map.push(match status.dut {
+        None => 0,
+        Some(false) => 1,
+        Some(true) => 2,
+    });
+    map.push(results.len() as u16);
+
+    // каналы без результата - нули и NaN, карта не зависит от числа DUT
+    for i in 0..MAX_DUTS {
+        map.resize(20 + RESULT_STRIDE * i, 0);
+        let result = results.get(i);
+        push_result(
+            &mut map,
+            result.map(|r| &r.result),
+            result.and_then(|r| r.dut.as_ref()),
+        );
+    }
+    window(&map, address, count)
+}
+
+/// Результат и DUT одного канала, 34 регистра
+fn push_result(map: &mut Vec<u16>, result: Option<&RunResult>, dut: Option<&SctbInfo>) {
+    let criterion = |c: Criterion| match c {
+        Criterion::Sensivity => 1,
+        Criterion::FAtmosphere => 2,
+        Criterion::FVacuum => 3,
+        Criterion::Temperature => 4,
+    };
+    let nan = f32::NAN;
+    // вид, вердикт, критерий, точек кривой; P, F, T, чувств., F атм, время, течь, dP/dt
+    let (head, values) = match result {
+        None => ([0, 0, 0, 0], [nan; 8]),
+        Some(RunResult::Sensivity {
+            p,
+            f,
+            t,
+            sensivity,
+            f_atm,
+            hold,
+            verdict,
+            ..
+        }) => (
+            match verdict {
+                None => [1, 0, 0, 0],
+                Some(Verdict::Pass) => [1, 1, 0, 0],
+                Some(Verdict::Fail(c)) => [1, 2, criterion(*c), 0],
+            },
+            [
+                *p,
+                *f,
+                *t,
+                *sensivity,
+                *f_atm,
+                hold.duration.as_secs_f32(),
+                nan,
+                nan,
+            ],
+        ),
+        Some(RunResult::Curve(curve)) => {
+            let last = curve.points.last();
+            (
+                [2, 0, 0, curve.points.len() as u16],
+                [
+                    last.map_or(nan, |p| p.p),
+                    last.map_or(nan, |p| p.f),
+                    last.map_or(nan, |p| p.t),
+                    nan,
+                    nan,
+                    nan,
+                    nan,
+                    nan,
+                ],
+            )
+        }
+        Some(RunResult::Leak(leak)) => (
+            if leak.passed {
+                [3, 1, 0, 0]
+            } else {
+                [3, 2, 5, 0]
+            },
+            [
+                leak.p_end,
+                nan,
+                nan,
+                nan,
+                nan,
+                leak.duration.as_secs_f32(),
+                leak.leak_rate,
+                leak.dp_dt,
+            ],
+        ),
+    };
+    map.extend_from_slice(&head);
+    for v in values {
+        push_f32(map, v);
+    }
+
+    let serial = dut.map_or(0, |d| d.info.serial);
+    map.push((serial >> 16) as u16);
+    map.push(serial as u16);
+    map.push(dut.map_or(0, |d| {
+        u16::from_be_bytes([d.info.firmware.0, d.info.firmware.1])
+    }));
+    map.push(dut.map_or(0, |d| d.info.period_ms));
+    push_f32(map, dut.map_or(nan, |d| d.calibration.f0));
+    for i in 0..i2c_sensor::CALIBRATION_COEFFS {
+        push_f32(map, dut.map_or(nan, |d| d.calibration.coeffs[i]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(state: DeviceState, mode: RunMode) -> Status {
+        Status {
+            state,
+            mode,
+            p: 760.0,
+            f: 30000.0,
+            t: 25.0,
+            threshold: 1.0,
+            timer_s: 0.0,
+            profile: 0,
+            results: 0,
+            dut: None,
+        }
+    }
+
+    #[test]
+    fn crc_known_vectors() {
+        assert_eq!(crc16(b"123456789"), 0x4B37);
+        assert_eq!(crc16(&[]), 0xFFFF);
+        // чтение одного регистра у прибора 1, CRC в кадре 84 0A
+        let frame = encode_request(
+            1,
+            &Request::ReadHoldingRegisters {
+                address: 0,
+                count: 1,
+            },
+        );
+        assert_eq!(frame, [0x01, 0x03, 0x00, 0x00, 0x00, 0x01, 0x84, 0x0A]);
+    }
+
+    #[test]
+    fn request_round_trip() {
+        let requests = [
+            Request::ReadCoils {
+                address: 0,
+                count: 6,
+            },
+            Request::ReadHoldingRegisters {
+                address: 4,
+                count: 10,
+            },
+            Request::ReadInputRegisters {
+                address: 20,
+                count: 125,
+            },
+            Request::WriteSingleCoil {
+                address: 4,
+                value: true,
+            },
+            Request::WriteSingleCoil {
+                address: 0,
+                value: false,
+            },
+            Request::WriteSingleRegister {
+                address: 2,
+                value: 0x1234,
+            },
+            Request::WriteMultipleCoils {
+                address: 0,
+                values: vec![true, false, false, true, false, true, false, false, true],
+            },
+            Request::WriteMultipleRegisters {
+                address: 0,
+                values: vec![0x3F80, 0x0000, 0x4120, 0x0000],
+            },
+        ];
+        for request in requests {
+            let frame = encode_request(7, &request);
+            assert_eq!(decode_request(&frame), Ok((7, request)));
+        }
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(decode_request(&[1, 3, 0]), Err(DecodeError::TooShort));
+
+        let mut frame = encode_request(
+            1,
+            &Request::ReadCoils {
+                address: 0,
+                count: 1,
+            },
+        );
+        frame[3] ^= 1;
+        assert!(matches!(
+            decode_request(&frame),
+            Err(DecodeError::BadCrc { .. })
+        ));
+
+        let unsupported = |body: &[u8]| match decode_request(&with_crc(body.to_vec())) {
+            Err(DecodeError::Unsupported { exception, .. }) => exception,
+            other => panic!("{other:?}"),
+        };
+        // 0x02 чтение дискретных входов не поддерживается
+        assert_eq!(
+            unsupported(&[1, 0x02, 0, 0, 0, 1]),
+            Exception::IllegalFunction
+        );
+        assert_eq!(
+            unsupported(&[1, 0x03, 0, 0, 0, 0]),
+            Exception::IllegalDataValue
+        );
+        assert_eq!(
+            unsupported(&[1, 0x03, 0, 0, 0, 126]),
+            Exception::IllegalDataValue
+        );
+        assert_eq!(
+            unsupported(&[1, 0x05, 0, 4, 0x12, 0x34]),
+            Exception::IllegalDataValue
+        );
+        assert_eq!(
+            unsupported(&[1, 0x03, 0, 0, 0, 1, 0]),
+            Exception::IllegalDataValue
+        );
+        // число байт не совпадает с количеством регистров
+        assert_eq!(
+            unsupported(&[1, 0x10, 0, 0, 0, 2, 2, 0, 1]),
+            Exception::IllegalDataValue
+        );
+    }
+
+    #[test]
+    fn response_frames() {
+        let coils = encode_response(1, 0x01, &Ok(Response::Coils(vec![true, false, true])));
+        assert_eq!(&coils[..4], [1, 0x01, 1, 0b101]);
+
+        let registers = encode_response(1, 0x03, &Ok(Response::Registers(vec![0x1234, 0xABCD])));
+        assert_eq!(&registers[..7], [1, 0x03, 4, 0x12, 0x34, 0xAB, 0xCD]);
+
+        let written = encode_response(
+            1,
+            0x05,
+            &Ok(Response::Written {
+                address: 4,
+                value: 0xFF00,
+            }),
+        );
+        // эхо записи coil повторяет кадр запроса
+        assert_eq!(
+            written,
+            encode_request(
+                1,
+                &Request::WriteSingleCoil {
+                    address: 4,
+                    value: true
+                }
+            )
+        );
+
+        let exception = encode_response(1, 0x03, &Err(Exception::IllegalDataAddress));
+        assert_eq!(exception.len(), 5);
+        assert_eq!(&exception[..3], [1, 0x83, 2]);
+        assert_eq!(crc16(&exception), 0, "CRC кадра вместе с CRC равен нулю");
+    }
+
+    #[test]
+    fn coils_and_windows() {
+        let measuring = status(DeviceState::Measuring, RunMode::Curve);
+        assert_eq!(
+            read_coils(&measuring, 0, 6),
+            Ok(vec![false, false, true, false, false, false])
+        );
+        let title = status(DeviceState::Title, RunMode::Curve);
+        assert_eq!(read_coils(&title, 2, 1), Ok(vec![false]));
+        assert_eq!(read_coils(&title, 5, 2), Err(Exception::IllegalDataAddress));
+        assert_eq!(Coil::range(4, 1), Ok(vec![Coil::Abort]));
+
+        let parameters = Parameters::default();
+        let map = read_holding(&parameters, 0, 2).unwrap();
+        let threshold = f32::from_bits((map[0] as u32) << 16 | map[1] as u32);
+        assert_eq!(threshold, parameters.threshold);
+        let end = PARAMETER_NAMES.len() as u16 * 2;
+        assert!(read_holding(&parameters, end - 2, 2).is_ok());
+        assert_eq!(
+            read_holding(&parameters, end - 1, 2),
+            Err(Exception::IllegalDataAddress)
+        );
+
+        // последний регистр - c3 DUT 4
+        let end = (20 + RESULT_STRIDE * (MAX_DUTS - 1) + 34) as u16;
+        let input = read_input(&title, &[], 0, end).unwrap();
+        assert_eq!(input[0], DeviceState::Title as u16);
+        assert_eq!(input[15], 0);
+        assert!(f32::from_bits((input[24] as u32) << 16 | input[25] as u32).is_nan());
+        assert_eq!(
+            read_input(&title, &[], 1, end),
+            Err(Exception::IllegalDataAddress)
+        );
+    }
+
+    #[test]
+    fn write_holding_all_or_nothing() {
+        let mut parameters = Parameters::default();
+        let bits = 2.5f32.to_bits();
+        let words = [(bits >> 16) as u16, bits as u16];
+        write_holding(&mut parameters, 0, &words).unwrap();
+        assert_eq!(parameters.threshold, 2.5);
+
+        assert_eq!(
+            write_holding(&mut parameters, 1, &words),
+            Err(Exception::IllegalDataAddress)
+        );
+        assert_eq!(
+            write_holding(&mut parameters, 0, &words[..1]),
+            Err(Exception::IllegalDataAddress)
+        );
+
+        // второй параметр вне диапазона - первый тоже не меняется
+        let nan = f32::NAN.to_bits();
+        let before = parameters;
+        let mut values = vec![0x4000, 0x0000];
+        values.extend([(nan >> 16) as u16, nan as u16]);
+        assert!(write_holding(&mut parameters, 0, &values).is_err());
+        assert_eq!(parameters, before);
+    }
+}
@@ -0,0 +1,462 @@
+//! Датчики Pfeiffer (PKR, TPR, PCR ...) по протоколу Pfeiffer Vacuum на RS485.
+//!
+//! Телеграмма - ASCII строка:
+//!
+//! ```text
+//! aaa  адрес 001..255
+//! ac   действие: 00 - запрос, 10 - ответ или запись
+//! ppp  номер параметра
+//! ll   длина данных
+//! ...  данные, в запросе значения - "=?"
+//! ccc  сумма кодов всех предыдущих символов по модулю 256
+//! \r
+//! ```
+//!
+//! Давление (параметр 740) передается в формате `mmmmee`: p = mmmm / 1000 * 10^(ee - 20) hPa.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{io::Write, thread};
+
+use crate::gauge::{
+    GaugeReading, GaugeStatus, LinkStats, Pressure, PressureGauge, PressureUnit, SharedLinkStats,
+};
+
+/// Давление, hPa
+pub const PARAM_PRESSURE: u16 = 740;
+/// Тип датчика ("ElecName")
+pub const PARAM_TYPE: u16 = 349;
+
+const ACTION_QUERY: u8 = 0;
+const ACTION_REPLY: u8 = 10;
+const QUERY: &str = "=?";
+
+/// Сколько ждать `\r` в конце ответа
+const REPLY_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Telegram {
+    pub addr: u8,
+    pub action: u8,
+    pub param: u16,
+    pub data: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TelegramError {
+    /// Нет `\r` или телеграмма короче минимальной
+    Incomplete,
+    Checksum {
+        msg: u8,
+        actual: u8,
+    },
+    /// Поля не цифры или длина не совпадает с данными
+    Format,
+    /// Датчик отклонил запрос: NO_DEF, _RANGE или _LOGIC
+    Rejected(String),
+}
+
+impl Telegram {
+    pub fn query(addr: u8, param: u16) -> Self {
+        Self {
+            addr,
+            action: ACTION_QUERY,
+            param,
+            data: QUERY.to_string(),
+        }
+    }
+
+    pub fn reply(addr: u8, param: u16, data: &str) -> Self {
+        Self {
+            addr,
+            action: ACTION_REPLY,
+            param,
+            data: data.to_string(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut frame = vec![];
+        write!(
+            &mut frame,
+            "{:03}{:02}{:03}{:02}{}",
+            self.addr,
+            self.action,
+            self.param,
+            self.data.len(),
+            self.data
+        )
+        .unwrap();
+        let crc = checksum(&frame);
+        write!(&mut frame, "{crc:03}\r").unwrap();
+        frame
+    }
+
+    pub fn decode(frame: &[u8]) -> Result<Self, TelegramError> {
+        // адрес, действие, параметр, длина, сумма, \r
+        const MIN_LEN: usize = 3 + 2 + 3 + 2 + 3 + 1;
+        if frame.len() < MIN_LEN || frame.last() != Some(&b'\r') {
+            return Err(TelegramError::Incomplete);
+        }
+
+        let body = &frame[..frame.len() - 4];
+        let msg = number(&frame[frame.len() - 4..frame.len() - 1])
+            .and_then(|v| u8::try_from(v).ok())
+            .ok_or(TelegramError::Format)?;
+        let actual = checksum(body);
+        if msg != actual {
+            return Err(TelegramError::Checksum { msg, actual });
+        }
+
+        let field =
+            |range: std::ops::Range<usize>| number(&body[range]).ok_or(TelegramError::Format);
+        let addr = u8::try_from(field(0..3)?).map_err(|_| TelegramError::Format)?;
+        let action = field(3..5)? as u8;
+        let param = field(5..8)? as u16;
+        let len = field(8..10)? as usize;
+
+        let data = std::str::from_utf8(&body[10..]).map_err(|_| TelegramError::Format)?;
+        if data.len() != len {
+            return Err(TelegramError::Format);
+        }
+        if matches!(data, "NO_DEF" | "_RANGE" | "_LOGIC") {
+            return Err(TelegramError::Rejected(data.to_string()));
+        }
+
+        Ok(Self {
+            addr,
+            action,
+            param,
+            data: data.to_string(),
+        })
+    }
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    (data.iter().fold(0u32, |a, b| a + *b as u32) % 256) as u8
+}
+
+/// Десятичное число из ASCII цифр
+fn number(digits: &[u8]) -> Option<u32> {
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+/// `mmmmee` в hPa
+pub fn decode_pressure(data: &str) -> Option<f32> {
+    let data = data.as_bytes();
+    if data.len() != 6 {
+        return None;
+    }
+    let m = number(&data[..4])?;
+    let exp = number(&data[4..])? as i32;
+    Some(m as f32 / 1_000.0 * 10.0f32.powi(exp - 20))
+}
+
+/// hPa в `mmmmee`
+pub fn encode_pressure(p_hpa: f32) -> String {
+    let p = p_hpa.max(1e-9);
+    let mut exp = p.log10().floor() as i32 + 20;
+    let mut m = (p / 10.0f32.powi(exp - 20) * 1000.0).round() as u32;
+    if m > 9999 {
+        m /= 10;
+        exp += 1;
+    }
+    format!("{m:04}{exp:02}")
+}
+
+pub struct PfeifferSensor {
+    addr: u8,
+    stats: SharedLinkStats,
+}
+
+impl PfeifferSensor {
+    pub fn new(addr: u8) -> Self {
+        Self {
+            addr,
+            stats: Arc::new(Mutex::new(LinkStats::default())),
+        }
+    }
+
+    pub fn get_id<P, E, PIN, PINE>(
+        &self,
+        port: &mut P,
+        re_de: &mut PIN,
+    ) -> Result<Option<String>, E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+        PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+    {
+        Ok(self
+            .query(port, re_de, PARAM_TYPE, |data| {
+                Some(data.trim().to_string())
+            })?
+            .ok())
+    }
+
+    /// Давление, hPa (= mbar), или статус неудачного запроса
+    pub fn read<P, E, PIN, PINE>(
+        &self,
+        port: &mut P,
+        re_de: &mut PIN,
+    ) -> Result<Result<f32, GaugeStatus>, E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+        PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+    {
+        self.query(port, re_de, PARAM_PRESSURE, decode_pressure)
+    }
+
+    /// Ответ на запрос параметра, разобранный `parse`. Каждый запрос учитывается в
+    /// [`LinkStats`]: нет ответа - NoResponse, датчик отклонил запрос - Fault.
+    fn query<P, E, PIN, PINE, T>(
+        &self,
+        port: &mut P,
+        re_de: &mut PIN,
+        param: u16,
+        parse: impl FnOnce(&str) -> Option<T>,
+    ) -> Result<Result<T, GaugeStatus>, E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+        PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+    {
+        // остатки прошлого ответа
+        while port.read().is_ok() {}
+
+        let _ = re_de.set_high();
+        for b in Telegram::query(self.addr, param).encode() {
+            nb::block!(port.write(b))?;
+        }
+        nb::block!(port.flush())?;
+        let _ = re_de.set_low();
+
+        let resp = Self::read_response(port)?;
+        let mut stats = self.stats.lock().unwrap();
+        let Some(resp) = resp else {
+            stats.timeouts += 1;
+            return Ok(Err(GaugeStatus::NoResponse));
+        };
+
+        let data = match Telegram::decode(&resp) {
+            Ok(t) if t.addr == self.addr && t.param == param && t.action == ACTION_REPLY => {
+                parse(&t.data)
+            }
+            Ok(_) | Err(TelegramError::Incomplete | TelegramError::Format) => None,
+            Err(TelegramError::Checksum { .. }) => {
+                stats.crc_errors += 1;
+                return Ok(Err(GaugeStatus::NoResponse));
+            }
+            Err(TelegramError::Rejected(_)) => {
+                stats.ok += 1;
+                return Ok(Err(GaugeStatus::Fault));
+            }
+        };
+        Ok(match data {
+            Some(data) => {
+                stats.ok += 1;
+                Ok(data)
+            }
+            None => {
+                stats.invalid += 1;
+                Err(GaugeStatus::NoResponse)
+            }
+        })
+    }
+
+    /// Байты до `\r` включительно, None - таймаут
+    fn read_response<P, E>(port: &mut P) -> Result<Option<Vec<u8>>, E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E>,
+    {
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        let mut result = vec![];
+        loop {
+            match port.read() {
+                Ok(b) => {
+                    result.push(b);
+                    if b == b'\r' {
+                        return Ok(Some(result));
+                    }
+                }
+                Err(nb::Error::WouldBlock) if Instant::now() < deadline => {
+                    thread::sleep(Duration::from_millis(1))
+                }
+                Err(nb::Error::WouldBlock) => return Ok(None),
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Датчик Pfeiffer на линии RS485 как [`PressureGauge`], давление в mbar
+pub struct PfeifferGauge<P, PIN> {
+    sensor: PfeifferSensor,
+    port: P,
+    re_de: PIN,
+    id: Option<String>,
+}
+
+impl<P, E, PIN, PINE> PfeifferGauge<P, PIN>
+where
+    P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+    PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+{
+    pub fn new(addr: u8, port: P, re_de: PIN) -> Self {
+        Self {
+            sensor: PfeifferSensor::new(addr),
+            port,
+            re_de,
+            id: None,
+        }
+    }
+
+    /// Запрашивает тип датчика, false - датчик не ответил
+    pub fn probe(&mut self) -> Result<bool, E> {
+        self.id = self.sensor.get_id(&mut self.port, &mut self.re_de)?;
+        Ok(self.id.is_some())
+    }
+
+    /// Вернуть линию, например чтобы искать на ней датчик другого типа
+    pub fn release(self) -> (P, PIN) {
+        (self.port, self.re_de)
+    }
+}
+
+impl<P, E, PIN, PINE> PressureGauge for PfeifferGauge<P, PIN>
+where
+    P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+    PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+    E: std::fmt::Debug,
+{
+    fn id(&self) -> String {
+        format!(
+            "Pfeiffer {} @{:03}",
+            self.id.as_deref().unwrap_or("?"),
+            self.sensor.addr
+        )
+    }
+
+    fn read(&mut self) -> GaugeReading {
+        match self.sensor.read(&mut self.port, &mut self.re_de) {
+            Ok(Ok(p)) => GaugeReading::ok(Pressure::new(p, PressureUnit::Mbar)),
+            Ok(Err(status)) => GaugeReading::failed(status, PressureUnit::Mbar),
+            Err(_) => GaugeReading::failed(GaugeStatus::Fault, PressureUnit::Mbar),
+        }
+    }
+
+    fn link_stats(&self) -> Option<SharedLinkStats> {
+        Some(self.sensor.stats.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{pfeiffer_device, MockPin, MockSerial};
+
+    fn gauge(
+        device: impl FnMut(&[u8]) -> Option<Vec<u8>> + Send + 'static,
+    ) -> PfeifferGauge<MockSerial, MockPin> {
+        PfeifferGauge::new(1, MockSerial::new(device), MockPin)
+    }
+
+    fn stats(gauge: &PfeifferGauge<MockSerial, MockPin>) -> LinkStats {
+        *gauge.link_stats().unwrap().lock().unwrap()
+    }
+
+    #[test]
+    fn telegram_round_trip() {
+        let query = Telegram::query(1, PARAM_PRESSURE);
+        assert_eq!(query.encode(), b"0010074002=?106\r");
+        assert_eq!(Telegram::decode(&query.encode()), Ok(query));
+
+        let reply = Telegram::reply(122, PARAM_TYPE, "TPR280");
+        assert_eq!(Telegram::decode(&reply.encode()), Ok(reply));
+        assert_eq!(
+            Telegram::decode(b"0011074006100023"),
+            Err(TelegramError::Incomplete)
+        );
+    }
+
+    #[test]
+    fn bad_checksum() {
+        let mut frame = Telegram::reply(1, PARAM_PRESSURE, "100023").encode();
+        frame[10] = b'2';
+        assert!(matches!(
+            Telegram::decode(&frame),
+            Err(TelegramError::Checksum { .. })
+        ));
+
+        let p = Arc::new(Mutex::new(1013.0));
+        let mut device = pfeiffer_device(1, "TPR280", p);
+        let mut gauge = gauge(move |request| {
+            let mut reply = device(request)?;
+            reply[10] ^= 1;
+            Some(reply)
+        });
+        assert_eq!(gauge.read().status, GaugeStatus::NoResponse);
+        assert_eq!(stats(&gauge).crc_errors, 1);
+        assert_eq!(stats(&gauge).ok, 0);
+    }
+
+    #[test]
+    fn no_def_rejected() {
+        let reply = Telegram::reply(1, 999, "NO_DEF").encode();
+        assert_eq!(
+            Telegram::decode(&reply),
+            Err(TelegramError::Rejected("NO_DEF".to_string()))
+        );
+
+        // датчик не знает параметра давления
+        let mut gauge = gauge(|request| {
+            let query = Telegram::decode(request).ok()?;
+            Some(Telegram::reply(query.addr, query.param, "NO_DEF").encode())
+        });
+        assert_eq!(gauge.read().status, GaugeStatus::Fault);
+        assert_eq!(stats(&gauge).ok, 1);
+    }
+
+    #[test]
+    fn pressure_round_trip() {
+        assert_eq!(encode_pressure(1000.0), "100023");
+        assert_eq!(decode_pressure("100023"), Some(1000.0));
+        let p = decode_pressure("123417").unwrap();
+        assert!((p - 1.234e-3).abs() < 1e-9, "{p}");
+        assert_eq!(decode_pressure("12341"), None);
+        assert_eq!(decode_pressure("12a417"), None);
+        for p in [1.5e-8, 2.5e-3, 0.1, 1.0, 47.11, 1013.0, 1999.0] {
+            let back = decode_pressure(&encode_pressure(p)).unwrap();
+            assert!((back - p).abs() <= p * 5e-4, "{p} -> {back}");
+        }
+    }
+
+    #[test]
+    fn mantissa_carry() {
+        // 9.9996 округляется до 10000 - переносится в порядок
+        assert_eq!(encode_pressure(9.9996), "100021");
+        assert_eq!(decode_pressure("100021"), Some(10.0));
+    }
+
+    #[test]
+    fn gauge_reads_mock_device() {
+        let p = Arc::new(Mutex::new(2.5e-3));
+        let mut gauge = gauge(pfeiffer_device(1, " TPR280 ", p.clone()));
+        assert_eq!(gauge.probe(), Ok(true));
+        assert_eq!(gauge.id(), "Pfeiffer TPR280 @001");
+
+        let reading = gauge.read();
+        assert_eq!(reading.status, GaugeStatus::Ok);
+        assert!((reading.pressure.to(PressureUnit::Mbar) - 2.5e-3).abs() < 1e-7);
+        *p.lock().unwrap() = 800.0;
+        assert_eq!(gauge.read().value(PressureUnit::Mbar), Some(800.0));
+        assert_eq!(stats(&gauge).ok, 3);
+
+        // другой адрес на линии молчит
+        let mut other = PfeifferGauge::new(2, gauge.release().0, MockPin);
+        assert_eq!(other.probe(), Ok(false));
+        assert_eq!(other.read().status, GaugeStatus::NoResponse);
+        assert_eq!(stats(&other).timeouts, 2);
+    }
+}
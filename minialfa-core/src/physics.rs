@@ -0,0 +1,198 @@
+//! Модель вакуумной камеры с образцовым датчиком и испытуемыми датчиками (DUT) для
+//! симулятора и прибора без железа в `minialfa-cli`
+
+use std::time::Duration;
+
+use crate::klapan::KlapanState;
+use crate::platform::{Clock, StdClock};
+
+pub const ATMOSPHERE_MM_HG: f32 = 760.0;
+
+/// Предельное давление насоса, mmHg
+const ULTIMATE_PRESSURE: f32 = 0.005;
+/// Постоянная времени откачки, с
+const PUMP_TAU_S: f32 = 12.0;
+/// Постоянная времени напуска атмосферы, с
+const VENT_TAU_S: f32 = 0.5;
+/// Натекание в отсеченную камеру, mmHg/s
+const LEAK_RATE: f32 = 0.0005;
+
+/// Частота DUT при нулевом давлении, Hz
+const DUT_F0: f32 = 30000.0;
+/// Чувствительность DUT вблизи нуля, Hz/mmHg
+const DUT_SENSIVITY: f32 = 4.0;
+/// Давление, на котором чувствительность DUT падает вдвое, mmHg
+const DUT_SATURATION: f32 = 2000.0;
+/// Разброс F0 и чувствительности между гнездами, относительный на гнездо
+const DUT_SPREAD: f32 = 0.05;
+
+const PRESSURE_NOISE: f32 = 0.002; // относительный
+const FREQUENCY_NOISE: f32 = 0.05; // Hz
+const TEMPERATURE_NOISE: f32 = 0.05; // *C
+
+/// Время модели идет по часам `C`, прибор без железа может их ускорять
+pub struct Chamber<C = StdClock> {
+    pressure: f32,
+    temperature: f32,
+    pumping: bool,
+    isolated: bool,
+    /// Испытуемые датчики вставлены в гнезда и отвечают на шине
+    dut_connected: bool,
+
+    clock: C,
+    last_update: Duration,
+    rng: u32,
+}
+
+impl Chamber {
+    pub fn new() -> Self {
+        Self::with_clock(StdClock::new())
+    }
+}
+
+impl<C: Clock> Chamber<C> {
+    pub fn with_clock(clock: C) -> Self {
+        Self {
+            pressure: ATMOSPHERE_MM_HG,
+            temperature: 23.5,
+            pumping: false,
+            isolated: false,
+            dut_connected: true,
+
+            last_update: clock.now(),
+            clock,
+            rng: 0x1234_5678,
+        }
+    }
+
+    /// true - камера подключена к насосу, false - к атмосфере
+    pub fn set_pumping(&mut self, pumping: bool) {
+        self.update();
+        self.pumping = pumping;
+    }
+
+    pub fn pumping(&self) -> bool {
+        self.pumping
+    }
+
+    /// true - камера отсечена отсечным клапаном
+    pub fn set_isolated(&mut self, isolated: bool) {
+        self.update();
+        self.isolated = isolated;
+    }
+
+    pub fn isolated(&self) -> bool {
+        self.isolated
+    }
+
+    /// Состояние клапанов как у [`crate::klapan::Klapan`] с отсечным клапаном
+    pub fn set_valve(&mut self, state: KlapanState) {
+        self.set_isolated(state == KlapanState::Isolate);
+        self.set_pumping(state != KlapanState::Atmosphere);
+    }
+
+    pub fn set_dut_connected(&mut self, connected: bool) {
+        self.dut_connected = connected;
+    }
+
+    pub fn dut_connected(&self) -> bool {
+        self.dut_connected
+    }
+
+    /// Истинное давление в камере, mmHg
+    pub fn pressure(&mut self) -> f32 {
+        self.update();
+        self.pressure
+    }
+
+    /// Показания образцового датчика давления, mmHg
+    pub fn measure_pressure(&mut self) -> f32 {
+        let p = self.pressure();
+        p * (1.0 + PRESSURE_NOISE * self.noise())
+    }
+
+    pub fn measure_temperature(&mut self) -> f32 {
+        self.temperature + TEMPERATURE_NOISE * self.noise()
+    }
+
+    /// Частота испытуемого датчика в гнезде `socket`, Hz
+    pub fn measure_dut_frequency(&mut self, socket: usize) -> f32 {
+        dut_frequency(self.pressure(), socket) + FREQUENCY_NOISE * self.noise()
+    }
+
+    fn update(&mut self) {
+        let now = self.clock.now();
+        let dt = now.saturating_sub(self.last_update).as_secs_f32();
+        self.last_update = now;
+
+        if self.isolated {
+            self.pressure = (self.pressure + LEAK_RATE * dt).min(ATMOSPHERE_MM_HG);
+            return;
+        }
+
+        let (target, tau) = if self.pumping {
+            (ULTIMATE_PRESSURE, PUMP_TAU_S)
+        } else {
+            (ATMOSPHERE_MM_HG, VENT_TAU_S)
+        };
+        self.pressure = target + (self.pressure - target) * (-dt / tau).exp();
+    }
+
+    /// Равномерный шум [-1; 1], xorshift32
+    fn noise(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+/// Частота испытуемого датчика в гнезде `socket` без шума при давлении `p`, Hz
+pub fn dut_frequency(p: f32, socket: usize) -> f32 {
+    let k = 1.0 + DUT_SPREAD * socket as f32;
+    DUT_F0 * k + DUT_SENSIVITY * k * p / (1.0 + p / DUT_SATURATION)
+}
+
+impl Default for Chamber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::ManualClock;
+
+    #[test]
+    fn valve_states() {
+        let clock = ManualClock::new();
+        let mut chamber = Chamber::with_clock(clock.clone());
+        chamber.set_valve(KlapanState::Vacuum);
+        assert!(chamber.pumping() && !chamber.isolated());
+
+        clock.advance(Duration::from_secs_f32(PUMP_TAU_S));
+        let p = chamber.pressure();
+        let expected = ULTIMATE_PRESSURE + (ATMOSPHERE_MM_HG - ULTIMATE_PRESSURE) / 1f32.exp();
+        assert!((p - expected).abs() < 1e-2, "{p}");
+
+        // отсеченная камера медленно натекает
+        chamber.set_valve(KlapanState::Isolate);
+        assert!(chamber.pumping() && chamber.isolated());
+        clock.advance(Duration::from_secs(100));
+        assert!((chamber.pressure() - (p + LEAK_RATE * 100.0)).abs() < 1e-3);
+
+        chamber.set_valve(KlapanState::Atmosphere);
+        clock.advance(Duration::from_secs(10));
+        assert!((chamber.pressure() - ATMOSPHERE_MM_HG).abs() < 1e-3);
+    }
+
+    #[test]
+    fn dut_frequency_by_socket() {
+        assert_eq!(dut_frequency(0.0, 0), DUT_F0);
+        // на 1 mmHg от нуля
+        let sensivity = dut_frequency(1.0, 0) - dut_frequency(0.0, 0);
+        assert!((sensivity - DUT_SENSIVITY).abs() < 1e-2);
+        assert!(dut_frequency(0.0, 1) > DUT_F0);
+    }
+}
@@ -0,0 +1,112 @@
+//! Трейты, через которые контроллер обращается к железу.
+
+use std::time::{Duration, Instant};
+
+use crate::calibration::WriteError;
+use crate::gauge::{GaugeCommand, GaugeError, GaugeReply, LinkStats};
+use crate::i2c_sensor::{Calibration, SctbDevice, SctbInfo};
+use crate::klapan::KlapanState;
+
+/// Монотонные часы, время от произвольной точки отсчета.
+pub trait Clock {
+    fn now(&self) -> Duration;
+}
+
+/// Клапан вакуумной камеры.
+pub trait Valve {
+    type Error: std::fmt::Debug;
+
+    fn set_state(&mut self, state: KlapanState) -> Result<(), Self::Error>;
+}
+
+/// Запуск и остановка периодического опроса датчиков.
+pub trait SensorsControl {
+    type Error: std::fmt::Debug;
+
+    fn start(&mut self, period: Duration) -> Result<(), Self::Error>;
+    fn stop(&mut self) -> Result<(), Self::Error>;
+
+    /// Идентификаторы найденных образцовых датчиков, см. [`crate::gauge`]. Номер в
+    /// списке - `gauge` в [`SensorResult::GaugeResult`](crate::controller::SensorResult::GaugeResult).
+    fn gauges(&self) -> &[String];
+
+    /// Счетчики обмена с датчиком номер `gauge`, если драйвер их ведет
+    fn gauge_stats(&self, _gauge: usize) -> Option<LinkStats> {
+        None
+    }
+
+    /// Настройка датчика номер `gauge`, вызывается только при остановленном опросе
+    fn gauge_command(
+        &mut self,
+        _gauge: usize,
+        _cmd: GaugeCommand,
+    ) -> Result<GaugeReply, GaugeError> {
+        Err(GaugeError::Unsupported)
+    }
+
+    /// Серийный номер, ПО и калибровка испытуемого датчика SCTB номер `dut` в порядке
+    /// [`assign_sctb`](Self::assign_sctb), None - не прочитаны.
+    /// Вызывается только при остановленном опросе.
+    fn dut_info(&mut self, _dut: usize) -> Option<SctbInfo> {
+        None
+    }
+
+    /// Запись калибровки в испытуемый датчик с проверкой обратным чтением, см.
+    /// [`crate::calibration::program`]. Вызывается только при остановленном опросе.
+    fn write_dut_calibration(
+        &mut self,
+        _dut: usize,
+        _calibration: &Calibration,
+    ) -> Result<(), WriteError> {
+        Err(WriteError::Unsupported)
+    }
+
+    /// Датчики SCTB на шине I2C, None - драйвер не умеет искать.
+    /// Вызывается только при остановленном опросе.
+    fn scan_sctb(&mut self) -> Option<Vec<SctbDevice>> {
+        None
+    }
+
+    /// Адреса образцового и испытуемых датчиков SCTB, до запуска опроса. Частоты DUT в
+    /// [`SensorResult::SctbSensorResult`](crate::controller::SensorResult) - в том же порядке.
+    fn assign_sctb(&mut self, _reference: u8, _duts: &[u8]) {}
+}
+
+/// Key-value хранилище настроек, повторяет интерфейс NVS.
+pub trait SettingsStorage {
+    type Error: std::fmt::Debug;
+
+    fn get_u8(&self, key: &str) -> Result<Option<u8>, Self::Error>;
+    fn set_u8(&mut self, key: &str, value: u8) -> Result<(), Self::Error>;
+    fn get_u32(&self, key: &str) -> Result<Option<u32>, Self::Error>;
+    fn set_u32(&mut self, key: &str, value: u32) -> Result<(), Self::Error>;
+    fn get_str(&self, key: &str) -> Result<Option<String>, Self::Error>;
+    fn set_str(&mut self, key: &str, value: &str) -> Result<(), Self::Error>;
+    fn get_blob(&self, key: &str) -> Result<Option<Vec<u8>>, Self::Error>;
+    fn set_blob(&mut self, key: &str, value: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// Часы на основе [`std::time::Instant`].
+pub struct StdClock {
+    start: Instant,
+}
+
+impl StdClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for StdClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
@@ -0,0 +1,181 @@
+//! Именованные профили настроек для разных моделей датчиков.
+//!
+//! Параметры профиля хранятся под своими ключами NVS (см. [`crate::settings`]),
+//! профиль 0 использует ключи без префикса, как до появления профилей.
+
+use crate::platform::SettingsStorage;
+
+pub const MAX_PROFILES: usize = 4;
+/// Длина имени профиля, имя дополняется пробелами
+pub const NAME_LEN: usize = 8;
+/// Символы, из которых энкодером набирается имя профиля
+pub const NAME_CHARSET: &str =
+    " АБВГДЕЖЗИЙКЛМНОПРСТУФХЦЧШЩЫЬЭЮЯ0123456789-ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+const ACTIVE_PROFILE: &str = "profile";
+const NAMES: [&str; MAX_PROFILES] = ["prof_name0", "prof_name1", "prof_name2", "prof_name3"];
+
+/// Ключ NVS параметра профиля
+pub fn key(profile: usize, key: &str) -> String {
+    if profile == 0 {
+        key.to_string()
+    } else {
+        format!("p{profile}_{key}")
+    }
+}
+
+pub struct Profiles {
+    pub active: usize,
+    pub names: [String; MAX_PROFILES],
+}
+
+impl Profiles {
+    pub fn load(nvs: &impl SettingsStorage) -> Self {
+        let active = nvs
+            .get_u8(ACTIVE_PROFILE)
+            .ok()
+            .flatten()
+            .map_or(0, |v| v as usize)
+            .min(MAX_PROFILES - 1);
+
+        let mut names = Self::default_names();
+        for (name, key) in names.iter_mut().zip(NAMES) {
+            if let Ok(Some(v)) = nvs.get_str(key) {
+                *name = normalize_name(&v);
+            }
+        }
+
+        Self { active, names }
+    }
+
+    pub fn store(&self, nvs: &mut impl SettingsStorage) {
+        let res = nvs.set_u8(ACTIVE_PROFILE, self.active as u8).and_then(|_| {
+            self.names
+                .iter()
+                .zip(NAMES)
+                .try_for_each(|(name, key)| nvs.set_str(key, name))
+        });
+        if let Err(e) = res {
+            println!("Failed to store profiles: {e:?}");
+        }
+    }
+
+    pub fn active_name(&self) -> &str {
+        self.names[self.active].trim_end()
+    }
+
+    fn default_names() -> [String; MAX_PROFILES] {
+        std::array::from_fn(|i| format!("ПРОФИЛЬ{}", i + 1))
+    }
+}
+
+/// Ровно [`NAME_LEN`] символов из [`NAME_CHARSET`]
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if NAME_CHARSET.contains(c) { c } else { ' ' })
+        .chain(std::iter::repeat(' '))
+        .take(NAME_LEN)
+        .collect()
+}
+
+/// Сменить символ имени в позиции `pos` на соседний из [`NAME_CHARSET`]
+pub fn step_char(name: &mut String, pos: usize, forward: bool) {
+    let charset = NAME_CHARSET.chars().collect::<Vec<_>>();
+    let mut chars = normalize_name(name).chars().collect::<Vec<_>>();
+
+    if let Some(c) = chars.get_mut(pos) {
+        let i = charset.iter().position(|x| x == c).unwrap_or_default();
+        *c = if forward {
+            charset[(i + 1) % charset.len()]
+        } else {
+            charset[(i + charset.len() - 1) % charset.len()]
+        };
+    }
+
+    *name = chars.into_iter().collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::Parameters;
+    use crate::mock::MemoryStorage;
+
+    #[test]
+    fn keys() {
+        assert_eq!(key(0, "thr"), "thr");
+        assert_eq!(key(2, "thr"), "p2_thr");
+    }
+
+    #[test]
+    fn store_and_load() {
+        let mut nvs = MemoryStorage::default();
+        let loaded = Profiles::load(&nvs);
+        assert_eq!(loaded.active, 0);
+        assert_eq!(loaded.active_name(), "ПРОФИЛЬ1");
+
+        let mut profiles = loaded;
+        profiles.active = 2;
+        profiles.names[2] = "MKS-2".to_string();
+        profiles.store(&mut nvs);
+
+        let loaded = Profiles::load(&nvs);
+        assert_eq!(loaded.active, 2);
+        assert_eq!(loaded.names[2], "MKS-2   ");
+        assert_eq!(loaded.active_name(), "MKS-2");
+    }
+
+    #[test]
+    fn default_names_survive_reload() {
+        let mut nvs = MemoryStorage::default();
+        let defaults = Profiles::load(&nvs);
+        defaults.store(&mut nvs);
+        let loaded = Profiles::load(&nvs);
+        assert_eq!(loaded.names, defaults.names);
+        assert_eq!(loaded.names[3], "ПРОФИЛЬ4");
+
+        // редактирование начинается с символа имени, а не с пробела
+        let mut name = loaded.names[0].clone();
+        step_char(&mut name, 6, true);
+        assert_eq!(name, "ПРОФИЛЭ1");
+    }
+
+    #[test]
+    fn load_clamps_and_normalizes() {
+        let mut nvs = MemoryStorage::default();
+        nvs.set_u8(ACTIVE_PROFILE, 9).unwrap();
+        nvs.set_str(NAMES[1], "abc_ДЛИННОЕ ИМЯ").unwrap();
+        let loaded = Profiles::load(&nvs);
+        assert_eq!(loaded.active, MAX_PROFILES - 1);
+        assert_eq!(loaded.names[1], "    ДЛИН");
+    }
+
+    #[test]
+    fn parameters_per_profile() {
+        let mut nvs = MemoryStorage::default();
+        let parameters = Parameters {
+            threshold: 5.0,
+            ..Parameters::default()
+        };
+        parameters.store(&mut nvs, 1).unwrap();
+
+        assert_eq!(Parameters::load(&mut nvs, 1).threshold, 5.0);
+        assert_eq!(
+            Parameters::load(&mut nvs, 0).threshold,
+            Parameters::default().threshold
+        );
+    }
+
+    #[test]
+    fn step_char_wraps() {
+        let mut name = "А".to_string();
+        step_char(&mut name, 0, false);
+        assert_eq!(name, "        ");
+        step_char(&mut name, 0, false);
+        assert_eq!(name, "Z       ");
+        step_char(&mut name, 0, true);
+        assert_eq!(name, "        ");
+        step_char(&mut name, 1, true);
+        assert_eq!(name, " А      ");
+    }
+}
@@ -0,0 +1,853 @@
+//! Текстовый протокол управления по консольному UART.
+//!
+//! Одна команда - одна строка, регистр ключевых слов не важен. На каждую команду
+//! приходит ровно одна строка ответа: `OK [key=value ...]` или `ERR <причина>`.
+//! Ответы и поток показаний идут в том же порту, что и отладочный вывод, поэтому
+//! передаются в обрамлении [`frame`]: `$<строка>*<XOR байтов строки, 2 hex>`.
+//! Строки без обрамления или с неверной суммой - отладочный вывод.
+//!
+//! ```text
+//! START AUTO|MANUAL|CURVE|LEAK   запуск измерения (только с главного экрана)
+//! ABORT                          отмена измерения, возврат на главный экран
+//! READ                           состояние и последние показания P/F/T
+//! GET [name]                     значение параметра или всех параметров
+//! SET name value                 изменить параметр (без сохранения)
+//! SAVE                           сохранить параметры активного профиля в NVS
+//! RESULT                         результат последнего измерения, данные DUT и калибровки
+//! GAUGES                         найденные образцовые датчики и счетчики обмена
+//! GAUGE n INFO                   серийный номер, прошивка, наработка, единица индикации
+//! GAUGE n ZERO                   юстировка нуля
+//! GAUGE n ATM p_mbar             юстировка атмосферы при давлении p_mbar
+//! GAUGE n SP i [p_mbar]          точка переключения i: чтение или запись
+//! HELP                           список команд
+//! ```
+
+use crate::calibration::CalibrationOutcome;
+use crate::controller::{CurveResult, HoldResult, LeakResult, Parameters};
+use crate::gauge::{self, GaugeCommand, GaugeError, GaugeReply, LinkStats};
+use crate::i2c_sensor::SctbInfo;
+use crate::linear_regression::RegressionFit;
+use crate::stream::StreamFormat;
+use crate::verdict::{Criterion, Verdict};
+
+/// Максимальная длина строки команды
+pub const MAX_LINE: usize = 128;
+
+/// Начало строки ответа или потока, см. [`frame`]
+pub const FRAME_START: char = '$';
+
+/// Имена параметров для GET/SET в порядке меню настроек
+pub const PARAMETER_NAMES: [&str; 35] = [
+    "threshold",
+    "update_period_ms",
+    "alt_sensor",
+    "wait_time_s",
+    "settle_enabled",
+    "settle_window_s",
+    "settle_df_max",
+    "settle_dp_max",
+    "curve_points_count",
+    "curve_p1",
+    "curve_p2",
+    "curve_p3",
+    "curve_p4",
+    "curve_p5",
+    "chamber_volume_ml",
+    "leak_time_s",
+    "leak_limit",
+    "tolerances_enabled",
+    "tol_sensivity_min",
+    "tol_sensivity_max",
+    "tol_f_atm_min",
+    "tol_f_atm_max",
+    "tol_f_vac_min",
+    "tol_f_vac_max",
+    "tol_t_min",
+    "tol_t_max",
+    "stream",
+    "write_calibration",
+    "sctb_reference",
+    "sctb_dut",
+    "auto_start",
+    "dut_count",
+    "sctb_dut2",
+    "sctb_dut3",
+    "sctb_dut4",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunMode {
+    Auto,
+    Manual,
+    Curve,
+    Leak,
+}
+
+impl RunMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunMode::Auto => "auto",
+            RunMode::Manual => "manual",
+            RunMode::Curve => "curve",
+            RunMode::Leak => "leak",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeviceState {
+    Title,
+    ProfileSelect,
+    Setup,
+    Measuring,
+    Result,
+    /// Подменю настройки образцового датчика
+    GaugeSetup,
+    /// Экран данных испытуемого датчика
+    Diagnostics,
+}
+
+impl DeviceState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceState::Title => "title",
+            DeviceState::ProfileSelect => "profile",
+            DeviceState::Setup => "setup",
+            DeviceState::Measuring => "measuring",
+            DeviceState::Result => "result",
+            DeviceState::GaugeSetup => "gauge_setup",
+            DeviceState::Diagnostics => "diagnostics",
+        }
+    }
+}
+
+/// Состояние прибора для READ и регистров Modbus
+#[derive(Clone, Copy, Debug)]
+pub struct Status {
+    pub state: DeviceState,
+    /// Режим текущего или последнего измерения
+    pub mode: RunMode,
+    /// Последние показания, mmHg / Hz первого DUT / *C
+    pub p: f32,
+    pub f: f32,
+    pub t: f32,
+    pub threshold: f32,
+    /// Сколько идет удержание или отсечка, s
+    pub timer_s: f32,
+    pub profile: usize,
+    /// Счетчик законченных измерений, чтобы заметить новый результат
+    pub results: u16,
+    /// Все ли DUT на шине I2C, None - шина не опрашивается
+    pub dut: Option<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RemoteCommand {
+    Start(RunMode),
+    Abort,
+    Read,
+    Get(Option<String>),
+    Set {
+        name: String,
+        value: String,
+    },
+    Save,
+    /// Результат DUT номер `dut` (с 0) последнего измерения
+    Result {
+        dut: usize,
+    },
+    /// Список найденных образцовых датчиков
+    Gauges,
+    /// Настройка образцового датчика номер `gauge`
+    Gauge {
+        gauge: usize,
+        command: GaugeCommand,
+    },
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RemoteError {
+    UnknownCommand(String),
+    BadArguments,
+    UnknownParameter(String),
+    BadValue(String),
+    /// Значение вне допустимого диапазона, в ответе - ближайшее допустимое
+    OutOfRange(String),
+    /// Команда недоступна в текущем состоянии
+    Busy(&'static str),
+    NoResult,
+    Storage(String),
+    LineTooLong,
+    UnknownGauge(usize),
+    /// В последнем измерении нет DUT с таким номером (с 1, как в команде)
+    UnknownDut(usize),
+    Gauge(GaugeError),
+}
+
+impl std::fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RemoteError::UnknownCommand(c) => write!(f, "unknown_command {c}"),
+            RemoteError::BadArguments => write!(f, "bad_arguments"),
+            RemoteError::UnknownParameter(n) => write!(f, "unknown_parameter {n}"),
+            RemoteError::BadValue(v) => write!(f, "bad_value {v}"),
+            RemoteError::OutOfRange(v) => write!(f, "out_of_range {v}"),
+            RemoteError::Busy(state) => write!(f, "busy {state}"),
+            RemoteError::NoResult => write!(f, "no_result"),
+            RemoteError::Storage(e) => write!(f, "storage {e}"),
+            RemoteError::LineTooLong => write!(f, "line_too_long"),
+            RemoteError::UnknownGauge(n) => write!(f, "unknown_gauge {n}"),
+            RemoteError::UnknownDut(n) => write!(f, "unknown_dut {n}"),
+            RemoteError::Gauge(e) => write!(f, "gauge {}", e.name()),
+        }
+    }
+}
+
+/// Результат последнего законченного измерения для команды RESULT
+#[derive(Clone, Debug)]
+pub enum RunResult {
+    Sensivity {
+        p: f32,
+        f: f32,
+        t: f32,
+        sensivity: f32,
+        f_atm: f32,
+        fit: Option<RegressionFit>,
+        hold: HoldResult,
+        verdict: Option<Verdict>,
+    },
+    Curve(CurveResult),
+    Leak(LeakResult),
+}
+
+/// Результат одного DUT, датчик, на котором он получен, и записанная в него калибровка.
+/// У проверки на течь DUT один - первый.
+#[derive(Clone, Debug)]
+pub struct DutResult {
+    pub result: RunResult,
+    pub dut: Option<SctbInfo>,
+    /// None - калибровка выключена
+    pub calibration: Option<CalibrationOutcome>,
+}
+
+pub fn parse(line: &str) -> Result<RemoteCommand, RemoteError> {
+    let mut words = line.split_whitespace();
+    let Some(command) = words.next() else {
+        return Err(RemoteError::BadArguments);
+    };
+    let args = words.collect::<Vec<_>>();
+
+    let command = match (command.to_ascii_uppercase().as_str(), args.as_slice()) {
+        ("START", [mode]) => RemoteCommand::Start(match mode.to_ascii_lowercase().as_str() {
+            "auto" => RunMode::Auto,
+            "manual" => RunMode::Manual,
+            "curve" => RunMode::Curve,
+            "leak" => RunMode::Leak,
+            _ => return Err(RemoteError::BadArguments),
+        }),
+        ("ABORT", []) => RemoteCommand::Abort,
+        ("READ", []) => RemoteCommand::Read,
+        ("GET", []) => RemoteCommand::Get(None),
+        ("GET", [name]) => RemoteCommand::Get(Some(name.to_ascii_lowercase())),
+        ("SET", [name, value]) => RemoteCommand::Set {
+            name: name.to_ascii_lowercase(),
+            value: value.to_string(),
+        },
+        ("SAVE", []) => RemoteCommand::Save,
+        ("RESULT", []) => RemoteCommand::Result { dut: 0 },
+        ("RESULT", [dut]) => match dut.parse::<usize>() {
+            Ok(n) if n > 0 => RemoteCommand::Result { dut: n - 1 },
+            _ => return Err(RemoteError::BadValue(dut.to_string())),
+        },
+        ("GAUGES", []) => RemoteCommand::Gauges,
+        ("GAUGE", [gauge, command, args @ ..]) => RemoteCommand::Gauge {
+            gauge: gauge.parse().map_err(|_| RemoteError::BadArguments)?,
+            command: parse_gauge_command(command, args)?,
+        },
+        ("HELP", []) => RemoteCommand::Help,
+        (
+            "START" | "ABORT" | "READ" | "GET" | "SET" | "SAVE" | "RESULT" | "GAUGES" | "GAUGE"
+            | "HELP",
+            _,
+        ) => return Err(RemoteError::BadArguments),
+        (c, _) => return Err(RemoteError::UnknownCommand(c.to_string())),
+    };
+    Ok(command)
+}
+
+fn parse_gauge_command(command: &str, args: &[&str]) -> Result<GaugeCommand, RemoteError> {
+    let pressure = |v: &str| match v.parse::<f32>() {
+        Ok(p) if p.is_finite() && p > 0.0 => Ok(p),
+        _ => Err(RemoteError::BadValue(v.to_string())),
+    };
+    let switch_point = |v: &str| match v.parse::<u8>() {
+        Ok(i) if (1..=gauge::SWITCH_POINTS).contains(&i) => Ok(i),
+        _ => Err(RemoteError::BadValue(v.to_string())),
+    };
+    Ok(match (command.to_ascii_uppercase().as_str(), args) {
+        ("INFO", []) => GaugeCommand::Info,
+        ("ZERO", []) => GaugeCommand::AdjustZero,
+        ("ATM", [p]) => GaugeCommand::AdjustAtmosphere(pressure(p)?),
+        ("SP", [i]) => GaugeCommand::GetSwitchPoint(switch_point(i)?),
+        ("SP", [i, p]) => GaugeCommand::SetSwitchPoint(switch_point(i)?, pressure(p)?),
+        _ => return Err(RemoteError::BadArguments),
+    })
+}
+
+pub fn help() -> String {
+    "OK commands=START,ABORT,READ,GET,SET,SAVE,RESULT,GAUGES,GAUGE,HELP modes=auto,manual,curve,leak gauge=INFO,ZERO,ATM,SP"
+        .to_string()
+}
+
+pub fn ok(pairs: &[(&str, String)]) -> String {
+    pairs
+        .iter()
+        .fold("OK".to_string(), |s, (k, v)| format!("{s} {k}={v}"))
+}
+
+/// `gaugeN` - номер в [`SensorResult::GaugeResult`](crate::controller::SensorResult), пробелы
+/// в названии заменены на `_`. Счетчики обмена `okN crcN timeoutN invalidN` - если драйвер их ведет.
+pub fn format_gauges(ids: &[String], stats: &[Option<LinkStats>]) -> String {
+    let mut pairs = vec![("count".to_string(), ids.len().to_string())];
+    for (i, id) in ids.iter().enumerate() {
+        pairs.push((format!("gauge{i}"), id.replace(' ', "_")));
+        if let Some(s) = stats.get(i).copied().flatten() {
+            pairs.push((format!("ok{i}"), s.ok.to_string()));
+            pairs.push((format!("crc{i}"), s.crc_errors.to_string()));
+            pairs.push((format!("timeout{i}"), s.timeouts.to_string()));
+            pairs.push((format!("invalid{i}"), s.invalid.to_string()));
+        }
+    }
+    ok(&pairs
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.clone()))
+        .collect::<Vec<_>>())
+}
+
+/// Ответ на GAUGE, пробелы в строках прибора заменены на `_`
+pub fn format_gauge_reply(gauge: usize, reply: &GaugeReply) -> String {
+    let mut pairs = vec![("gauge", gauge.to_string())];
+    match reply {
+        GaugeReply::Done => {}
+        GaugeReply::Info(info) => {
+            let text = |v: &Option<String>| v.as_deref().map(|v| v.replace(' ', "_"));
+            pairs.extend(
+                [
+                    ("serial", text(&info.serial)),
+                    ("firmware", text(&info.firmware)),
+                    ("hours", info.hours.map(|h| h.to_string())),
+                    ("unit", text(&info.unit)),
+                ]
+                .into_iter()
+                .filter_map(|(k, v)| Some((k, v?))),
+            );
+        }
+        GaugeReply::SwitchPoint { index, p_mbar } => {
+            pairs.push(("sp", index.to_string()));
+            pairs.push(("p_mbar", p_mbar.to_string()));
+        }
+    }
+    ok(&pairs)
+}
+
+pub fn error(e: &RemoteError) -> String {
+    format!("ERR {e}")
+}
+
+/// Строка ответа или потока для отправки: `$<line>*<XOR байтов line, 2 hex>`
+pub fn frame(line: &str) -> String {
+    format!("{FRAME_START}{line}*{:02X}", checksum(line))
+}
+
+/// Строка без обрамления, None - отладочный вывод или строка испорчена
+pub fn unframe(line: &str) -> Option<&str> {
+    let (line, sum) = line.strip_prefix(FRAME_START)?.rsplit_once('*')?;
+    (sum.len() == 2 && u8::from_str_radix(sum, 16).ok()? == checksum(line)).then_some(line)
+}
+
+fn checksum(line: &str) -> u8 {
+    line.bytes().fold(0, |sum, b| sum ^ b)
+}
+
+enum Field<'a> {
+    F32(&'a mut f32),
+    U32(&'a mut u32),
+    Bool(&'a mut bool),
+    Stream(&'a mut StreamFormat),
+}
+
+fn field<'a>(parameters: &'a mut Parameters, name: &str) -> Option<Field<'a>> {
+    let t = &mut parameters.tolerances;
+    Some(match name {
+        "threshold" => Field::F32(&mut parameters.threshold),
+        "update_period_ms" => Field::U32(&mut parameters.update_period_ms),
+        "alt_sensor" => Field::Bool(&mut parameters.try_use_alternative_sensor),
+        "wait_time_s" => Field::U32(&mut parameters.wait_time_s),
+        "settle_enabled" => Field::Bool(&mut parameters.settle_enabled),
+        "settle_window_s" => Field::U32(&mut parameters.settle_window_s),
+        "settle_df_max" => Field::F32(&mut parameters.settle_df_max),
+        "settle_dp_max" => Field::F32(&mut parameters.settle_dp_max),
+        "curve_points_count" => Field::U32(&mut parameters.curve_points_count),
+        "curve_p1" => Field::F32(&mut parameters.curve_points[0]),
+        "curve_p2" => Field::F32(&mut parameters.curve_points[1]),
+        "curve_p3" => Field::F32(&mut parameters.curve_points[2]),
+        "curve_p4" => Field::F32(&mut parameters.curve_points[3]),
+        "curve_p5" => Field::F32(&mut parameters.curve_points[4]),
+        "chamber_volume_ml" => Field::U32(&mut parameters.chamber_volume_ml),
+        "leak_time_s" => Field::U32(&mut parameters.leak_time_s),
+        "leak_limit" => Field::F32(&mut parameters.leak_limit),
+        "tolerances_enabled" => Field::Bool(&mut parameters.tolerances_enabled),
+        "tol_sensivity_min" => Field::F32(&mut t.sensivity.min),
+        "tol_sensivity_max" => Field::F32(&mut t.sensivity.max),
+        "tol_f_atm_min" => Field::F32(&mut t.f_atm.min),
+        "tol_f_atm_max" => Field::F32(&mut t.f_atm.max),
+        "tol_f_vac_min" => Field::F32(&mut t.f_vac.min),
+        "tol_f_vac_max" => Field::F32(&mut t.f_vac.max),
+        "tol_t_min" => Field::F32(&mut t.t.min),
+        "tol_t_max" => Field::F32(&mut t.t.max),
+        "stream" => Field::Stream(&mut parameters.stream_format),
+        "write_calibration" => Field::Bool(&mut parameters.write_calibration),
+        "sctb_reference" => Field::U32(&mut parameters.sctb_reference),
+        "sctb_dut" => Field::U32(&mut parameters.sctb_duts[0]),
+        "auto_start" => Field::Bool(&mut parameters.auto_start),
+        "dut_count" => Field::U32(&mut parameters.dut_count),
+        "sctb_dut2" => Field::U32(&mut parameters.sctb_duts[1]),
+        "sctb_dut3" => Field::U32(&mut parameters.sctb_duts[2]),
+        "sctb_dut4" => Field::U32(&mut parameters.sctb_duts[3]),
+        _ => return None,
+    })
+}
+
+impl Parameters {
+    /// Значение параметра по имени из [`PARAMETER_NAMES`]
+    pub fn get(&self, name: &str) -> Option<String> {
+        let mut parameters = *self;
+        Some(match field(&mut parameters, name)? {
+            Field::F32(v) => v.to_string(),
+            Field::U32(v) => v.to_string(),
+            Field::Bool(v) => (*v as u8).to_string(),
+            Field::Stream(v) => v.name().to_string(),
+        })
+    }
+
+    /// Значение параметра числом: флаги - 0/1, формат потока - номер [`StreamFormat`]
+    pub fn get_f32(&self, name: &str) -> Option<f32> {
+        let mut parameters = *self;
+        Some(match field(&mut parameters, name)? {
+            Field::F32(v) => *v,
+            Field::U32(v) => *v as f32,
+            Field::Bool(v) => *v as u8 as f32,
+            Field::Stream(v) => *v as u8 as f32,
+        })
+    }
+
+    /// Значение вне допустимого диапазона отклоняется, параметры не меняются
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), RemoteError> {
+        self.update(name, |field| {
+            let bad_value = || RemoteError::BadValue(value.to_string());
+            match field {
+                Field::F32(v) => *v = value.parse().map_err(|_| bad_value())?,
+                Field::U32(v) => *v = value.parse().map_err(|_| bad_value())?,
+                Field::Bool(v) => {
+                    *v = match value.to_ascii_lowercase().as_str() {
+                        "1" | "on" | "true" => true,
+                        "0" | "off" | "false" => false,
+                        _ => return Err(bad_value()),
+                    }
+                }
+                Field::Stream(v) => *v = StreamFormat::from_name(value).ok_or_else(bad_value)?,
+            }
+            Ok(())
+        })
+    }
+
+    /// Числовой вариант [`Parameters::set`], см. [`Parameters::get_f32`]
+    pub fn set_f32(&mut self, name: &str, value: f32) -> Result<(), RemoteError> {
+        self.update(name, |field| {
+            let bad_value = || RemoteError::BadValue(value.to_string());
+            let integer =
+                (value.is_finite() && value >= 0.0 && value.fract() == 0.0).then_some(value as u32);
+            match field {
+                Field::F32(v) => *v = value,
+                Field::U32(v) => *v = integer.ok_or_else(bad_value)?,
+                Field::Bool(v) => {
+                    *v = match integer {
+                        Some(0) => false,
+                        Some(1) => true,
+                        _ => return Err(bad_value()),
+                    }
+                }
+                Field::Stream(v) => {
+                    *v = match integer {
+                        Some(0) => StreamFormat::Off,
+                        Some(1) => StreamFormat::Csv,
+                        Some(2) => StreamFormat::Json,
+                        _ => return Err(bad_value()),
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn update(
+        &mut self,
+        name: &str,
+        f: impl FnOnce(Field) -> Result<(), RemoteError>,
+    ) -> Result<(), RemoteError> {
+        let mut parameters = *self;
+        f(field(&mut parameters, name)
+            .ok_or_else(|| RemoteError::UnknownParameter(name.to_string()))?)?;
+
+        let sanitized = parameters.sanitized();
+        let clamped = sanitized.get(name).unwrap_or_default();
+        if parameters.get(name).as_deref() != Some(clamped.as_str()) {
+            return Err(RemoteError::OutOfRange(clamped));
+        }
+
+        *self = parameters;
+        Ok(())
+    }
+}
+
+fn criterion_key(criterion: Criterion) -> &'static str {
+    match criterion {
+        Criterion::Sensivity => "sensivity",
+        Criterion::FAtmosphere => "f_atm",
+        Criterion::FVacuum => "f_vac",
+        Criterion::Temperature => "t",
+    }
+}
+
+pub fn format_status(status: &Status) -> String {
+    ok(&[
+        ("state", status.state.name().to_string()),
+        ("mode", status.mode.name().to_string()),
+        ("p", status.p.to_string()),
+        ("f", status.f.to_string()),
+        ("t", status.t.to_string()),
+        ("threshold", status.threshold.to_string()),
+        ("hold_s", status.timer_s.to_string()),
+        ("results", status.results.to_string()),
+        (
+            "dut",
+            match status.dut {
+                Some(true) => "present",
+                Some(false) => "absent",
+                None => "unknown",
+            }
+            .to_string(),
+        ),
+    ])
+}
+
+/// Результат DUT номер `index` из `count` и датчик, на котором он получен (`dut_*`, если
+/// датчик был прочитан)
+pub fn format_result(result: &DutResult, index: usize, count: usize) -> String {
+    let DutResult {
+        result,
+        dut,
+        calibration,
+    } = result;
+    let mut pairs = Vec::new();
+    match result {
+        RunResult::Sensivity {
+            p,
+            f,
+            t,
+            sensivity,
+            f_atm,
+            fit,
+            hold,
+            verdict,
+        } => {
+            pairs.push(("mode", RunMode::Auto.name().to_string()));
+            pairs.push(("p", p.to_string()));
+            pairs.push(("f", f.to_string()));
+            pairs.push(("t", t.to_string()));
+            pairs.push(("sensivity", sensivity.to_string()));
+            pairs.push(("f_atm", f_atm.to_string()));
+            if let Some(fit) = fit {
+                pairs.push(("r2", fit.r2.to_string()));
+                pairs.push(("k_std_err", fit.k_std_err.to_string()));
+                pairs.push(("points", fit.points.to_string()));
+                pairs.push(("rejected", fit.rejected.to_string()));
+            }
+            pairs.push(("hold_s", hold.duration.as_secs_f32().to_string()));
+            pairs.push(("settled", (hold.settled as u8).to_string()));
+            pairs.push((
+                "verdict",
+                match verdict {
+                    None => "none".to_string(),
+                    Some(Verdict::Pass) => "pass".to_string(),
+                    Some(Verdict::Fail(c)) => format!("fail:{}", criterion_key(*c)),
+                },
+            ));
+        }
+        RunResult::Curve(curve) => {
+            pairs.push(("mode", RunMode::Curve.name().to_string()));
+            pairs.push(("points", curve.points.len().to_string()));
+            let join = |v: &mut dyn Iterator<Item = String>| v.collect::<Vec<_>>().join(",");
+            pairs.push(("p", join(&mut curve.points.iter().map(|p| p.p.to_string()))));
+            pairs.push(("f", join(&mut curve.points.iter().map(|p| p.f.to_string()))));
+            pairs.push(("t", join(&mut curve.points.iter().map(|p| p.t.to_string()))));
+            pairs.push((
+                "segments",
+                join(&mut curve.segments.iter().map(f32::to_string)),
+            ));
+            if let Some(poly) = &curve.poly {
+                pairs.push(("poly", join(&mut poly.iter().map(f32::to_string))));
+            }
+        }
+        RunResult::Leak(leak) => {
+            pairs.push(("mode", RunMode::Leak.name().to_string()));
+            pairs.push(("p_start", leak.p_start.to_string()));
+            pairs.push(("p_end", leak.p_end.to_string()));
+            pairs.push(("dp_dt", leak.dp_dt.to_string()));
+            pairs.push(("leak_rate", leak.leak_rate.to_string()));
+            pairs.push(("limit", leak.limit.to_string()));
+            pairs.push(("passed", (leak.passed as u8).to_string()));
+            pairs.push(("duration_s", leak.duration.as_secs_f32().to_string()));
+        }
+    }
+    pairs.push(("channel", (index + 1).to_string()));
+    pairs.push(("channels", count.to_string()));
+    if let Some(dut) = dut {
+        pairs.push(("dut_addr", dut.address.to_string()));
+        pairs.push(("dut_serial", dut.info.serial.to_string()));
+        pairs.push((
+            "dut_fw",
+            format!("{}.{}", dut.info.firmware.0, dut.info.firmware.1),
+        ));
+        pairs.push(("dut_period_ms", dut.info.period_ms.to_string()));
+        pairs.push(("dut_f0", dut.calibration.f0.to_string()));
+        pairs.push((
+            "dut_coeffs",
+            dut.calibration
+                .coeffs
+                .iter()
+                .map(f32::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        ));
+    }
+    if let Some(calibration) = calibration {
+        pairs.push(("calibration", calibration.name().to_string()));
+        if let Some(fit) = calibration.fit() {
+            pairs.push(("cal_f0", fit.calibration.f0.to_string()));
+            pairs.push((
+                "cal_coeffs",
+                fit.calibration
+                    .coeffs
+                    .iter()
+                    .map(f32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ));
+            pairs.push(("cal_max_error", fit.max_error.to_string()));
+        }
+    }
+    ok(&pairs)
+}
+
+/// Сборка строк из байтов UART. `\r`, `\n` и `\r\n` завершают строку,
+/// слишком длинная строка отбрасывается целиком с ошибкой.
+#[derive(Default)]
+pub struct LineBuffer {
+    buf: Vec<u8>,
+    overflow: bool,
+}
+
+impl LineBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Возвращает законченную непустую строку
+    pub fn push(&mut self, byte: u8) -> Option<Result<String, RemoteError>> {
+        match byte {
+            b'\r' | b'\n' => {
+                let line = std::mem::take(&mut self.buf);
+                if std::mem::take(&mut self.overflow) {
+                    return Some(Err(RemoteError::LineTooLong));
+                }
+                if line.is_empty() {
+                    return None;
+                }
+                Some(Ok(String::from_utf8_lossy(&line).into_owned()))
+            }
+            _ if self.buf.len() >= MAX_LINE => {
+                self.overflow = true;
+                None
+            }
+            _ => {
+                self.buf.push(byte);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(
+            parse("start Curve"),
+            Ok(RemoteCommand::Start(RunMode::Curve))
+        );
+        assert_eq!(
+            parse("SET Threshold 0.5"),
+            Ok(RemoteCommand::Set {
+                name: "threshold".to_string(),
+                value: "0.5".to_string()
+            })
+        );
+        assert_eq!(parse("RESULT 2"), Ok(RemoteCommand::Result { dut: 1 }));
+        assert_eq!(
+            parse("GAUGE 0 SP 1 0.05"),
+            Ok(RemoteCommand::Gauge {
+                gauge: 0,
+                command: GaugeCommand::SetSwitchPoint(1, 0.05)
+            })
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("  "), Err(RemoteError::BadArguments));
+        assert_eq!(parse("START fast"), Err(RemoteError::BadArguments));
+        assert_eq!(parse("ABORT now"), Err(RemoteError::BadArguments));
+        assert_eq!(
+            parse("RESULT 0"),
+            Err(RemoteError::BadValue("0".to_string()))
+        );
+        assert_eq!(
+            parse("JUMP"),
+            Err(RemoteError::UnknownCommand("JUMP".to_string()))
+        );
+        assert_eq!(
+            error(&RemoteError::UnknownDut(4)),
+            "ERR unknown_dut 4".to_string()
+        );
+    }
+
+    #[test]
+    fn gauge_commands() {
+        let gauge = |line| match parse(line) {
+            Ok(RemoteCommand::Gauge { gauge, command }) => Ok((gauge, command)),
+            other => Err(other),
+        };
+        assert_eq!(gauge("GAUGE 1 info"), Ok((1, GaugeCommand::Info)));
+        assert_eq!(gauge("GAUGE 0 ZERO"), Ok((0, GaugeCommand::AdjustZero)));
+        assert_eq!(
+            gauge("GAUGE 0 ATM 1013"),
+            Ok((0, GaugeCommand::AdjustAtmosphere(1013.0)))
+        );
+        assert_eq!(
+            gauge("GAUGE 0 SP 2"),
+            Ok((0, GaugeCommand::GetSwitchPoint(2)))
+        );
+        assert!(gauge("GAUGE 0 SP 3").is_err());
+        assert!(gauge("GAUGE 0 ATM").is_err());
+
+        let info = GaugeReply::Info(gauge::GaugeInfo {
+            serial: Some("SN 1".to_string()),
+            hours: Some(12),
+            ..Default::default()
+        });
+        assert_eq!(
+            format_gauge_reply(0, &info),
+            "OK gauge=0 serial=SN_1 hours=12"
+        );
+        assert_eq!(
+            format_gauge_reply(
+                1,
+                &GaugeReply::SwitchPoint {
+                    index: 2,
+                    p_mbar: 0.05
+                }
+            ),
+            "OK gauge=1 sp=2 p_mbar=0.05"
+        );
+
+        let stats = LinkStats {
+            ok: 5,
+            crc_errors: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_gauges(
+                &["Thyracont VSP206 @001".to_string(), "Pfeiffer".to_string()],
+                &[Some(stats), None]
+            ),
+            "OK count=2 gauge0=Thyracont_VSP206_@001 ok0=5 crc0=1 timeout0=0 invalid0=0 gauge1=Pfeiffer"
+        );
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        assert_eq!(frame("OK"), "$OK*04");
+        for line in ["OK", "ERR busy measuring", "time_ms,sensor", "{\"p\":1*2}"] {
+            assert_eq!(unframe(&frame(line)), Some(line));
+        }
+    }
+
+    #[test]
+    fn unframed_is_debug() {
+        assert_eq!(unframe("OK"), None);
+        assert_eq!(unframe("123,sctb,0.5"), None);
+        assert_eq!(unframe("$OK*05"), None);
+        assert_eq!(unframe("$OK*4"), None);
+        assert_eq!(unframe("$OK"), None);
+    }
+
+    #[test]
+    fn line_buffer() {
+        let mut line = LineBuffer::new();
+        let mut lines = Vec::new();
+        for &b in b"READ\r\n\nGET\n" {
+            lines.extend(line.push(b));
+        }
+        assert_eq!(lines, [Ok("READ".to_string()), Ok("GET".to_string())]);
+
+        for _ in 0..MAX_LINE + 1 {
+            assert_eq!(line.push(b'x'), None);
+        }
+        assert_eq!(line.push(b'\n'), Some(Err(RemoteError::LineTooLong)));
+        assert_eq!(line.push(b'A'), None);
+        assert_eq!(line.push(b'\r'), Some(Ok("A".to_string())));
+    }
+
+    #[test]
+    fn get_set_parameters() {
+        let mut parameters = Parameters::default();
+        for name in PARAMETER_NAMES {
+            let value = parameters.get(name).unwrap();
+            assert_eq!(parameters.set(name, &value), Ok(()), "{name}");
+        }
+
+        parameters.set("stream", "json").unwrap();
+        assert_eq!(parameters.get("stream").as_deref(), Some("json"));
+        assert_eq!(parameters.get_f32("stream"), Some(2.0));
+        parameters.set_f32("settle_enabled", 1.0).unwrap();
+        assert!(parameters.settle_enabled);
+
+        assert_eq!(
+            parameters.set("wait_time_s", "100000"),
+            Err(RemoteError::OutOfRange("300".to_string()))
+        );
+        assert_eq!(
+            parameters.set("threshold", "abc"),
+            Err(RemoteError::BadValue("abc".to_string()))
+        );
+        assert_eq!(
+            parameters.set("speed", "1"),
+            Err(RemoteError::UnknownParameter("speed".to_string()))
+        );
+        assert_eq!(parameters.wait_time_s, Parameters::default().wait_time_s);
+    }
+}
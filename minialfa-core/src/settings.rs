@@ -0,0 +1,529 @@
+//! Хранение [`Parameters`] в NVS одним блобом с версией и CRC32.
+//!
+//! ```text
+//! "MA" | version: u8 | len: u16 | payload[len] | crc32(все предыдущее): u32
+//! ```
+//! Все числа little-endian. Новые поля дописываются в конец payload, поэтому блоб старой
+//! версии читается, а отсутствующие в нем поля берутся из [`Parameters::default`].
+//! Блоб более новой версии не читается, чтобы не потерять его поля при следующем сохранении.
+//! Если блоба нет, параметры читаются из раздельных ключей прошивки до блоба (только порог,
+//! период опроса, образцовый датчик и выдержка, профиля тогда не было) и сразу сохраняются блобом.
+
+use crate::controller::{self, Parameters, MAX_CURVE_POINTS, MAX_DUTS};
+use crate::i2c_sensor::SCAN_ADDRESSES;
+use crate::platform::SettingsStorage;
+use crate::profile;
+use crate::stream::StreamFormat;
+use crate::verdict::{Range, Tolerances};
+
+const MAGIC: [u8; 2] = *b"MA";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 5;
+const CRC_LEN: usize = 4;
+
+const BLOB: &str = "params";
+
+#[derive(Debug, PartialEq)]
+pub enum SettingsError {
+    Truncated,
+    BadMagic,
+    BadChecksum { expected: u32, actual: u32 },
+    UnsupportedVersion(u8),
+}
+
+impl Parameters {
+    /// Никогда не паникует: при ошибке чтения или порче данных - значения по умолчанию
+    pub fn load<S: SettingsStorage>(nvs: &mut S, profile: usize) -> Self {
+        let key = profile::key(profile, BLOB);
+
+        match nvs.get_blob(&key) {
+            Ok(Some(data)) => match decode(&data) {
+                Ok(parameters) => return parameters.sanitized(),
+                Err(e) => println!("Settings '{key}' rejected: {e:?}, using defaults"),
+            },
+            Ok(None) if profile == 0 && legacy::present(nvs) => {
+                println!("Migrating settings to '{key}'");
+                let parameters = legacy::load(nvs).sanitized();
+                if let Err(e) = parameters.store(nvs, profile) {
+                    println!("Failed to store migrated settings: {e:?}");
+                }
+                return parameters;
+            }
+            Ok(None) => {}
+            Err(e) => println!("Failed to read settings '{key}': {e:?}, using defaults"),
+        }
+
+        Self::default()
+    }
+
+    pub fn store<S: SettingsStorage>(&self, nvs: &mut S, profile: usize) -> Result<(), S::Error> {
+        nvs.set_blob(&profile::key(profile, BLOB), &encode(self))
+    }
+
+    /// Значения вне допустимых диапазонов ограничиваются, NaN заменяется значением по умолчанию
+    pub fn sanitized(self) -> Self {
+        let default = Self::default();
+
+        let clamp_f32 = |v: f32, min: f32, max: f32, default: f32| {
+            if v.is_finite() {
+                v.clamp(min, max)
+            } else {
+                default
+            }
+        };
+        let clamp_range = |r: Range, limits: Range, default: Range| {
+            let (a, b) = (
+                clamp_f32(r.min, limits.min, limits.max, default.min),
+                clamp_f32(r.max, limits.min, limits.max, default.max),
+            );
+            Range::new(a.min(b), a.max(b))
+        };
+
+        let address =
+            |a: u32| a.clamp(*SCAN_ADDRESSES.start() as u32, *SCAN_ADDRESSES.end() as u32);
+        let mut curve_points = self.curve_points;
+        for (p, d) in curve_points.iter_mut().zip(default.curve_points) {
+            *p = clamp_f32(*p, controller::MIN_PREASURE, controller::MAX_PRESSURE, d);
+        }
+
+        Self {
+            threshold: clamp_f32(
+                self.threshold,
+                controller::MIN_PREASURE,
+                controller::MAX_PRESSURE,
+                default.threshold,
+            ),
+            update_period_ms: self
+                .update_period_ms
+                .clamp(controller::MIN_INTERVAL, controller::MAX_INTERVAL),
+            try_use_alternative_sensor: self.try_use_alternative_sensor,
+            wait_time_s: self.wait_time_s.min(controller::MAX_WAIT_TIME_S),
+            curve_points_count: self.curve_points_count.clamp(2, MAX_CURVE_POINTS as u32),
+            curve_points,
+            settle_enabled: self.settle_enabled,
+            settle_window_s: self.settle_window_s.clamp(
+                controller::MIN_SETTLE_WINDOW_S,
+                controller::MAX_SETTLE_WINDOW_S,
+            ),
+            settle_df_max: clamp_f32(
+                self.settle_df_max,
+                controller::MIN_DRIFT,
+                controller::MAX_DRIFT,
+                default.settle_df_max,
+            ),
+            settle_dp_max: clamp_f32(
+                self.settle_dp_max,
+                controller::MIN_DRIFT,
+                controller::MAX_DRIFT,
+                default.settle_dp_max,
+            ),
+            chamber_volume_ml: self.chamber_volume_ml.clamp(
+                controller::MIN_CHAMBER_VOLUME_ML,
+                controller::MAX_CHAMBER_VOLUME_ML,
+            ),
+            leak_time_s: self
+                .leak_time_s
+                .clamp(controller::MIN_LEAK_TIME_S, controller::MAX_LEAK_TIME_S),
+            leak_limit: clamp_f32(
+                self.leak_limit,
+                controller::MIN_LEAK_LIMIT,
+                controller::MAX_LEAK_LIMIT,
+                default.leak_limit,
+            ),
+            tolerances_enabled: self.tolerances_enabled,
+            tolerances: Tolerances {
+                sensivity: clamp_range(
+                    self.tolerances.sensivity,
+                    controller::SENSIVITY_LIMITS,
+                    default.tolerances.sensivity,
+                ),
+                f_atm: clamp_range(
+                    self.tolerances.f_atm,
+                    controller::FREQUENCY_LIMITS,
+                    default.tolerances.f_atm,
+                ),
+                f_vac: clamp_range(
+                    self.tolerances.f_vac,
+                    controller::FREQUENCY_LIMITS,
+                    default.tolerances.f_vac,
+                ),
+                t: clamp_range(
+                    self.tolerances.t,
+                    controller::TEMPERATURE_LIMITS,
+                    default.tolerances.t,
+                ),
+            },
+            stream_format: self.stream_format,
+            write_calibration: self.write_calibration,
+            sctb_reference: address(self.sctb_reference),
+            dut_count: self.dut_count.clamp(1, MAX_DUTS as u32),
+            sctb_duts: self.sctb_duts.map(address),
+            auto_start: self.auto_start,
+        }
+    }
+}
+
+pub fn encode(parameters: &Parameters) -> Vec<u8> {
+    let mut payload = Writer(Vec::new());
+
+    // version 1, поля следующих версий - в конец
+    payload.f32(parameters.threshold);
+    payload.u32(parameters.update_period_ms);
+    payload.bool(parameters.try_use_alternative_sensor);
+    payload.u32(parameters.wait_time_s);
+    payload.u32(parameters.curve_points_count);
+    for p in parameters.curve_points {
+        payload.f32(p);
+    }
+    payload.bool(parameters.settle_enabled);
+    payload.u32(parameters.settle_window_s);
+    payload.f32(parameters.settle_df_max);
+    payload.f32(parameters.settle_dp_max);
+    payload.u32(parameters.chamber_volume_ml);
+    payload.u32(parameters.leak_time_s);
+    payload.f32(parameters.leak_limit);
+    payload.bool(parameters.tolerances_enabled);
+    for range in [
+        parameters.tolerances.sensivity,
+        parameters.tolerances.f_atm,
+        parameters.tolerances.f_vac,
+        parameters.tolerances.t,
+    ] {
+        payload.f32(range.min);
+        payload.f32(range.max);
+    }
+    payload.u8(parameters.stream_format as u8);
+    payload.bool(parameters.write_calibration);
+    payload.u32(parameters.sctb_reference);
+    payload.u32(parameters.sctb_duts[0]);
+    payload.bool(parameters.auto_start);
+    payload.u32(parameters.dut_count);
+    for a in &parameters.sctb_duts[1..] {
+        payload.u32(*a);
+    }
+
+    let mut data = Vec::with_capacity(HEADER_LEN + payload.0.len() + CRC_LEN);
+    data.extend_from_slice(&MAGIC);
+    data.push(VERSION);
+    data.extend_from_slice(&(payload.0.len() as u16).to_le_bytes());
+    data.extend_from_slice(&payload.0);
+    data.extend_from_slice(&crc32(&data).to_le_bytes());
+    data
+}
+
+/// Значения не проверяются, см. [`Parameters::sanitized`]
+pub fn decode(data: &[u8]) -> Result<Parameters, SettingsError> {
+    if data.len() < HEADER_LEN + CRC_LEN {
+        return Err(SettingsError::Truncated);
+    }
+    if data[..2] != MAGIC {
+        return Err(SettingsError::BadMagic);
+    }
+    let version = data[2];
+
+    let payload_len = u16::from_le_bytes([data[3], data[4]]) as usize;
+    let end = HEADER_LEN + payload_len;
+    let crc = data
+        .get(end..end + CRC_LEN)
+        .ok_or(SettingsError::Truncated)?;
+
+    let expected = u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]);
+    let actual = crc32(&data[..end]);
+    if expected != actual {
+        return Err(SettingsError::BadChecksum { expected, actual });
+    }
+    if version > VERSION {
+        return Err(SettingsError::UnsupportedVersion(version));
+    }
+
+    let mut r = Reader(&data[HEADER_LEN..end]);
+    let d = Parameters::default();
+
+    let threshold = r.f32().unwrap_or(d.threshold);
+    let update_period_ms = r.u32().unwrap_or(d.update_period_ms);
+    let try_use_alternative_sensor = r.bool().unwrap_or(d.try_use_alternative_sensor);
+    let wait_time_s = r.u32().unwrap_or(d.wait_time_s);
+    let curve_points_count = r.u32().unwrap_or(d.curve_points_count);
+    let mut curve_points = d.curve_points;
+    for p in curve_points.iter_mut() {
+        *p = r.f32().unwrap_or(*p);
+    }
+    let settle_enabled = r.bool().unwrap_or(d.settle_enabled);
+    let settle_window_s = r.u32().unwrap_or(d.settle_window_s);
+    let settle_df_max = r.f32().unwrap_or(d.settle_df_max);
+    let settle_dp_max = r.f32().unwrap_or(d.settle_dp_max);
+    let chamber_volume_ml = r.u32().unwrap_or(d.chamber_volume_ml);
+    let leak_time_s = r.u32().unwrap_or(d.leak_time_s);
+    let leak_limit = r.f32().unwrap_or(d.leak_limit);
+    let tolerances_enabled = r.bool().unwrap_or(d.tolerances_enabled);
+    let mut range = |d: Range| Range::new(r.f32().unwrap_or(d.min), r.f32().unwrap_or(d.max));
+    let tolerances = Tolerances {
+        sensivity: range(d.tolerances.sensivity),
+        f_atm: range(d.tolerances.f_atm),
+        f_vac: range(d.tolerances.f_vac),
+        t: range(d.tolerances.t),
+    };
+    let stream_format = r.u8().map_or(d.stream_format, StreamFormat::from_u8);
+    let write_calibration = r.bool().unwrap_or(d.write_calibration);
+    let sctb_reference = r.u32().unwrap_or(d.sctb_reference);
+    let mut sctb_duts = d.sctb_duts;
+    sctb_duts[0] = r.u32().unwrap_or(sctb_duts[0]);
+    let auto_start = r.bool().unwrap_or(d.auto_start);
+    let dut_count = r.u32().unwrap_or(d.dut_count);
+    for a in sctb_duts[1..].iter_mut() {
+        *a = r.u32().unwrap_or(*a);
+    }
+
+    Ok(Parameters {
+        threshold,
+        update_period_ms,
+        try_use_alternative_sensor,
+        wait_time_s,
+        curve_points_count,
+        curve_points,
+        settle_enabled,
+        settle_window_s,
+        settle_df_max,
+        settle_dp_max,
+        chamber_volume_ml,
+        leak_time_s,
+        leak_limit,
+        tolerances_enabled,
+        tolerances,
+        stream_format,
+        write_calibration,
+        sctb_reference,
+        dut_count,
+        sctb_duts,
+        auto_start,
+    })
+}
+
+/// CRC-32 (IEEE 802.3)
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u32(&mut self, v: u32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn f32(&mut self, v: f32) {
+        self.u32(v.to_bits());
+    }
+
+    fn u8(&mut self, v: u8) {
+        self.0.push(v);
+    }
+
+    fn bool(&mut self, v: bool) {
+        self.u8(v as u8);
+    }
+}
+
+/// None - поле за концом payload (записано более старой версией)
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.0.get(..N)?.try_into().ok()?;
+        self.0 = &self.0[N..];
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.u32().map(f32::from_bits)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take::<1>().map(|b| b[0])
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        self.u8().map(|b| b != 0)
+    }
+}
+
+/// Раздельные ключи NVS прошивки до появления блоба
+mod legacy {
+    use super::*;
+
+    const THRESHOLD: &str = "threshold";
+    const UPDATE_PERIOD_MS: &str = "upd_per_ms";
+    const TRY_USE_ALTERNATIVE_SENSOR: &str = "alt_sens";
+    const WAIT_TIME_S: &str = "wait_time_s";
+
+    /// Старая прошивка писала все ключи сразу
+    pub fn present(nvs: &impl SettingsStorage) -> bool {
+        matches!(nvs.get_u32(THRESHOLD), Ok(Some(_)))
+    }
+
+    pub fn load(nvs: &impl SettingsStorage) -> Parameters {
+        let u32_or = |name: &str, default: u32| nvs.get_u32(name).ok().flatten().unwrap_or(default);
+
+        let d = Parameters::default();
+        Parameters {
+            threshold: nvs
+                .get_u32(THRESHOLD)
+                .ok()
+                .flatten()
+                .map_or(d.threshold, f32::from_bits),
+            update_period_ms: u32_or(UPDATE_PERIOD_MS, d.update_period_ms),
+            try_use_alternative_sensor: nvs
+                .get_u8(TRY_USE_ALTERNATIVE_SENSOR)
+                .ok()
+                .flatten()
+                .map_or(d.try_use_alternative_sensor, |v| v != 0),
+            wait_time_s: u32_or(WAIT_TIME_S, d.wait_time_s),
+            ..d
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MemoryStorage;
+
+    fn parameters() -> Parameters {
+        Parameters {
+            threshold: 2.5,
+            wait_time_s: 30,
+            curve_points: [200.0, 20.0, 2.0, 0.2, 0.02],
+            settle_enabled: true,
+            stream_format: StreamFormat::Json,
+            write_calibration: true,
+            dut_count: 3,
+            sctb_duts: [21, 22, 23, 24],
+            auto_start: true,
+            ..Parameters::default()
+        }
+    }
+
+    /// Блоб с другим заголовком и пересчитанной CRC
+    fn reheader(data: &[u8], version: u8, payload_len: usize) -> Vec<u8> {
+        let mut blob = MAGIC.to_vec();
+        blob.push(version);
+        blob.extend_from_slice(&(payload_len as u16).to_le_bytes());
+        blob.extend_from_slice(&data[HEADER_LEN..HEADER_LEN + payload_len]);
+        blob.extend_from_slice(&crc32(&blob).to_le_bytes());
+        blob
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = encode(&parameters());
+        assert_eq!(&data[..3], b"MA\x01");
+        assert_eq!(decode(&data), Ok(parameters()));
+    }
+
+    #[test]
+    fn truncated() {
+        let data = encode(&parameters());
+        assert_eq!(decode(&data[..8]), Err(SettingsError::Truncated));
+        assert_eq!(
+            decode(&data[..data.len() - 1]),
+            Err(SettingsError::Truncated)
+        );
+    }
+
+    #[test]
+    fn bad_magic_and_checksum() {
+        let mut data = encode(&parameters());
+        data[HEADER_LEN] ^= 0x01;
+        assert!(matches!(
+            decode(&data),
+            Err(SettingsError::BadChecksum { .. })
+        ));
+        data[0] = b'X';
+        assert_eq!(decode(&data), Err(SettingsError::BadMagic));
+    }
+
+    #[test]
+    fn newer_version_rejected() {
+        let data = encode(&parameters());
+        let newer = reheader(&data, VERSION + 1, data.len() - HEADER_LEN - CRC_LEN);
+        assert_eq!(
+            decode(&newer),
+            Err(SettingsError::UnsupportedVersion(VERSION + 1))
+        );
+
+        let mut nvs = MemoryStorage::default();
+        nvs.set_blob(BLOB, &newer).unwrap();
+        assert_eq!(Parameters::load(&mut nvs, 0), Parameters::default());
+    }
+
+    #[test]
+    fn short_payload_fields_default() {
+        // payload без полей после auto_start: как блоб до добавления числа DUT
+        let data = encode(&parameters());
+        let short_len = data.len() - HEADER_LEN - CRC_LEN - 4 * MAX_DUTS;
+        let decoded = decode(&reheader(&data, VERSION, short_len)).unwrap();
+
+        let d = Parameters::default();
+        assert!(decoded.auto_start);
+        assert_eq!(decoded.sctb_duts[0], 21);
+        assert_eq!(decoded.dut_count, d.dut_count);
+        assert_eq!(decoded.sctb_duts[1..], d.sctb_duts[1..]);
+    }
+
+    #[test]
+    fn legacy_migration() {
+        let mut nvs = MemoryStorage::default();
+        nvs.set_u32("threshold", 3.0f32.to_bits()).unwrap();
+        nvs.set_u32("upd_per_ms", 200).unwrap();
+        nvs.set_u8("alt_sens", 1).unwrap();
+        nvs.set_u32("wait_time_s", 20).unwrap();
+
+        let migrated = Parameters::load(&mut nvs, 0);
+        assert_eq!(
+            migrated,
+            Parameters {
+                threshold: 3.0,
+                update_period_ms: 200,
+                try_use_alternative_sensor: true,
+                wait_time_s: 20,
+                ..Parameters::default()
+            }
+        );
+        // сразу сохранено блобом
+        let blob = nvs.get_blob(BLOB).unwrap().unwrap();
+        assert_eq!(decode(&blob), Ok(migrated));
+
+        // у старой прошивки профилей не было
+        assert_eq!(Parameters::load(&mut nvs, 1), Parameters::default());
+        assert!(nvs.get_blob("p1_params").unwrap().is_none());
+    }
+
+    #[test]
+    fn out_of_range_sanitized() {
+        let parameters = Parameters {
+            threshold: f32::NAN,
+            update_period_ms: 0,
+            dut_count: 9,
+            ..Parameters::default()
+        }
+        .sanitized();
+        assert_eq!(parameters.threshold, Parameters::default().threshold);
+        assert_eq!(parameters.update_period_ms, controller::MIN_INTERVAL);
+        assert_eq!(parameters.dut_count as usize, MAX_DUTS);
+    }
+}
@@ -0,0 +1,239 @@
+//! Поток показаний датчиков в консольный UART во время измерения.
+//!
+//! Каждый [`SensorResult`] превращается в одну строку CSV или JSON. Поля, которые
+//! пришли не в этом отсчете, содержат последнее известное значение. Перед первым
+//! отсчетом CSV выводится строка заголовка [`CSV_HEADER`]. `f_hz` - первый DUT, частоты
+//! остальных каналов дописаны в конец строки. В порт строки уходят в обрамлении
+//! [`crate::remote::frame`], как и ответы на команды.
+
+use std::time::Duration;
+
+use crate::controller::{SensorResult, MAX_DUTS};
+
+pub const CSV_HEADER: &str =
+    "time_ms,sensor,source,p_mmhg,sctb_p_mmhg,sctb_t_c,f_hz,gauge_p_mbar,phase,timer_s,f2_hz,f3_hz,f4_hz";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StreamFormat {
+    #[default]
+    Off,
+    Csv,
+    Json,
+}
+
+impl StreamFormat {
+    const ALL: [StreamFormat; 3] = [StreamFormat::Off, StreamFormat::Csv, StreamFormat::Json];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StreamFormat::Off => "off",
+            StreamFormat::Csv => "csv",
+            StreamFormat::Json => "json",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Неизвестное значение (из более новой прошивки) - поток выключен
+    pub fn from_u8(v: u8) -> Self {
+        Self::ALL.get(v as usize).copied().unwrap_or_default()
+    }
+
+    /// Следующий формат по кругу, для энкодера
+    pub fn next(&self, forward: bool) -> Self {
+        let n = Self::ALL.len();
+        let step = if forward { 1 } else { n - 1 };
+        Self::ALL[(*self as usize + step) % n]
+    }
+}
+
+/// Датчик, по которому идет измерение
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PressureSource {
+    Sctb,
+    /// Первый образцовый датчик из [`crate::gauge`]
+    Gauge,
+}
+
+impl PressureSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PressureSource::Sctb => "sctb",
+            PressureSource::Gauge => "gauge",
+        }
+    }
+}
+
+/// Этап измерения и его таймер
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    /// Откачка до порога
+    Pumping,
+    /// Удержание, до конца осталось
+    Hold(Duration),
+    /// Камера отсечена (режим "Течь"), до конца осталось
+    Leak(Duration),
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Pumping => "pump",
+            Phase::Hold(_) => "hold",
+            Phase::Leak(_) => "leak",
+        }
+    }
+
+    pub fn timer(&self) -> Option<Duration> {
+        match self {
+            Phase::Pumping => None,
+            Phase::Hold(left) | Phase::Leak(left) => Some(*left),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct StreamSample {
+    /// От начала измерения
+    pub time: Duration,
+    /// Какой датчик прислал этот отсчет
+    pub sensor: SensorResult,
+    pub source: PressureSource,
+    /// Давление выбранного датчика, mmHg
+    pub p: f32,
+    pub sctb_p: f32,
+    pub sctb_t: f32,
+    /// Частоты DUT по каналам
+    pub f: [f32; MAX_DUTS],
+    /// Образцовый датчик из `sensor`, для строк SCTB - первый, mbar
+    pub gauge_p: f32,
+    pub phase: Phase,
+}
+
+impl StreamSample {
+    pub fn format(&self, format: StreamFormat) -> Option<String> {
+        let sensor = match self.sensor {
+            SensorResult::SctbSensorResult { .. } => "sctb".to_string(),
+            SensorResult::GaugeResult { gauge, .. } => format!("gauge{gauge}"),
+        };
+        let timer = self.phase.timer().map(|t| t.as_secs_f32());
+
+        match format {
+            StreamFormat::Off => None,
+            StreamFormat::Csv => Some(format!(
+                "{},{},{},{},{},{},{},{},{},{}{}",
+                self.time.as_millis(),
+                sensor,
+                self.source.name(),
+                csv(self.p),
+                csv(self.sctb_p),
+                csv(self.sctb_t),
+                csv(self.f[0]),
+                csv(self.gauge_p),
+                self.phase.name(),
+                timer.map_or(String::new(), |t| format!("{t:.1}")),
+                self.f[1..]
+                    .iter()
+                    .map(|&f| format!(",{}", csv(f)))
+                    .collect::<String>(),
+            )),
+            StreamFormat::Json => Some(format!(
+                "{{\"time_ms\":{},\"sensor\":\"{}\",\"source\":\"{}\",\"p_mmhg\":{},\"sctb_p_mmhg\":{},\"sctb_t_c\":{},\"f_hz\":{},\"gauge_p_mbar\":{},\"phase\":\"{}\",\"timer_s\":{}{}}}",
+                self.time.as_millis(),
+                sensor,
+                self.source.name(),
+                json(self.p),
+                json(self.sctb_p),
+                json(self.sctb_t),
+                json(self.f[0]),
+                json(self.gauge_p),
+                self.phase.name(),
+                timer.map_or("null".to_string(), |t| format!("{t:.1}")),
+                self.f[1..]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &f)| format!(",\"f{}_hz\":{}", i + 2, json(f)))
+                    .collect::<String>(),
+            )),
+        }
+    }
+}
+
+/// Нет значения - пустое поле
+fn csv(v: f32) -> String {
+    if v.is_finite() {
+        v.to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// В JSON нет NaN
+fn json(v: f32) -> String {
+    if v.is_finite() {
+        v.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> StreamSample {
+        StreamSample {
+            time: Duration::from_millis(1500),
+            sensor: SensorResult::SctbSensorResult {
+                f: [30002.5, f32::NAN, f32::NAN, f32::NAN],
+                p: 0.5,
+                t: 23.5,
+            },
+            source: PressureSource::Sctb,
+            p: 0.5,
+            sctb_p: 0.5,
+            sctb_t: 23.5,
+            f: [30002.5, 30010.0, f32::NAN, f32::NAN],
+            gauge_p: f32::NAN,
+            phase: Phase::Hold(Duration::from_millis(7250)),
+        }
+    }
+
+    #[test]
+    fn csv_matches_header() {
+        let line = sample().format(StreamFormat::Csv).unwrap();
+        assert_eq!(
+            line,
+            "1500,sctb,sctb,0.5,0.5,23.5,30002.5,,hold,7.2,30010,,"
+        );
+        assert_eq!(line.split(',').count(), CSV_HEADER.split(',').count());
+    }
+
+    #[test]
+    fn json_without_nan() {
+        let sample = StreamSample {
+            phase: Phase::Pumping,
+            ..sample()
+        };
+        assert_eq!(
+            sample.format(StreamFormat::Json).unwrap(),
+            "{\"time_ms\":1500,\"sensor\":\"sctb\",\"source\":\"sctb\",\"p_mmhg\":0.5,\
+             \"sctb_p_mmhg\":0.5,\"sctb_t_c\":23.5,\"f_hz\":30002.5,\"gauge_p_mbar\":null,\
+             \"phase\":\"pump\",\"timer_s\":null,\"f2_hz\":30010,\"f3_hz\":null,\"f4_hz\":null}"
+        );
+        assert_eq!(sample.format(StreamFormat::Off), None);
+    }
+
+    #[test]
+    fn format_names() {
+        assert_eq!(StreamFormat::from_name("JSON"), Some(StreamFormat::Json));
+        assert_eq!(StreamFormat::from_name("xml"), None);
+        assert_eq!(StreamFormat::from_u8(7), StreamFormat::Off);
+        assert_eq!(StreamFormat::Json.next(true), StreamFormat::Off);
+        assert_eq!(StreamFormat::Off.next(false), StreamFormat::Json);
+    }
+}
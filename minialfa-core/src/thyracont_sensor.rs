@@ -0,0 +1,836 @@
+//! Датчики Thyracont (VSP, VSC, VSH ...) по протоколу Thyracont на RS485.
+//!
+//! Запрос и ответ - ASCII строка:
+//!
+//! ```text
+//! aaa  адрес 001..255
+//! c    код команды, ответ приходит с тем же кодом
+//! ...  данные
+//! s    сумма кодов предыдущих символов по модулю 64 плюс 64
+//! \r
+//! ```
+//!
+//! Давление - 6 цифр `mmmmee`: p = mmmm / 1000 * 10^(ee - 20) mbar.
+//!
+//! ```text
+//! код  запрос              ответ
+//! T                        модель
+//! M                        давление
+//! j    mmmmee              mmmmee   юстировка: 000000 - ноль, иначе атмосфера при этом давлении
+//! S    n                   n mmmmee точка переключения n
+//! s    n mmmmee            n mmmmee запись точки переключения
+//! U                        единица индикации: mbar, Torr, hPa, Pa, micron
+//! N                        серийный номер
+//! V                        версия прошивки
+//! h                        наработка, часы
+//! ```
+
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{io::Write, thread};
+
+use crate::gauge::{
+    GaugeCommand, GaugeError, GaugeInfo, GaugeReading, GaugeReply, GaugeStatus, LinkStats,
+    Pressure, PressureGauge, PressureUnit, SharedLinkStats,
+};
+
+/// Модель датчика
+pub const CODE_ID: u8 = b'T';
+/// Давление
+pub const CODE_MEASURE: u8 = b'M';
+/// Юстировка нуля или атмосферы
+pub const CODE_ADJUST: u8 = b'j';
+pub const CODE_GET_SWITCH_POINT: u8 = b'S';
+pub const CODE_SET_SWITCH_POINT: u8 = b's';
+/// Единица индикации
+pub const CODE_UNIT: u8 = b'U';
+pub const CODE_SERIAL: u8 = b'N';
+pub const CODE_FIRMWARE: u8 = b'V';
+/// Наработка
+pub const CODE_HOURS: u8 = b'h';
+
+/// Данные юстировки нуля
+const ADJUST_ZERO: &[u8] = b"000000";
+
+#[derive(Debug, PartialEq)]
+pub enum Response {
+    Id(String),
+    Pressure(f32),
+    /// Давление юстировки, 0 - ноль
+    Adjusted(f32),
+    SwitchPoint {
+        index: u8,
+        p_mbar: f32,
+    },
+    Unit(String),
+    Serial(String),
+    Firmware(String),
+    Hours(u32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodeError {
+    /// Нет `\r` в конце или кадр короче адреса, кода и суммы
+    Incomplete,
+    Crc {
+        msg: u8,
+        actual: u8,
+    },
+    /// Адрес не из трех цифр или больше 255
+    Address,
+    UnknownCode(u8),
+    /// Данные не подходят к коду ответа
+    BadData(u8),
+}
+
+/// Ответ длиннее этого - мусор на линии
+const MAX_FRAME: usize = 32;
+
+/// Таймауты ответа и число попыток на один запрос
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    pub attempts: u8,
+    /// Ожидание первого байта ответа
+    pub first_byte: Duration,
+    /// Пауза между байтами внутри ответа
+    pub inter_byte: Duration,
+    /// Весь ответ, от отправки запроса
+    pub total: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            first_byte: Duration::from_millis(40),
+            inter_byte: Duration::from_millis(10),
+            total: Duration::from_millis(100),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Assembly {
+    /// Ответ еще собирается
+    Pending,
+    /// Байты до `\r` включительно
+    Frame(Vec<u8>),
+    Timeout,
+    /// Нет `\r` за [`MAX_FRAME`] байт
+    Overflow,
+}
+
+/// Сборка ответа из байт по мере прихода, без ожидания внутри
+pub struct FrameAssembler {
+    policy: RetryPolicy,
+    buf: Vec<u8>,
+    start: Instant,
+    last_byte: Option<Instant>,
+}
+
+impl FrameAssembler {
+    /// `start` - момент отправки запроса
+    pub fn new(policy: RetryPolicy, start: Instant) -> Self {
+        Self {
+            policy,
+            buf: vec![],
+            start,
+            last_byte: None,
+        }
+    }
+
+    pub fn push(&mut self, b: u8, now: Instant) -> Assembly {
+        self.buf.push(b);
+        self.last_byte = Some(now);
+        if b == b'\r' {
+            Assembly::Frame(std::mem::take(&mut self.buf))
+        } else if self.buf.len() >= MAX_FRAME {
+            Assembly::Overflow
+        } else {
+            Assembly::Pending
+        }
+    }
+
+    /// Истек ли какой-нибудь из таймаутов
+    pub fn expired(&self, now: Instant) -> bool {
+        let silence = match self.last_byte {
+            Some(last) => now.saturating_duration_since(last) > self.policy.inter_byte,
+            None => now.saturating_duration_since(self.start) > self.policy.first_byte,
+        };
+        silence || now.saturating_duration_since(self.start) > self.policy.total
+    }
+
+    /// Забрать все пришедшие байты, не блокируя
+    pub fn poll<P, E>(&mut self, port: &mut P, now: Instant) -> Result<Assembly, E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E>,
+    {
+        loop {
+            match port.read() {
+                Ok(b) => match self.push(b, now) {
+                    Assembly::Pending => {}
+                    done => return Ok(done),
+                },
+                Err(nb::Error::WouldBlock) if self.expired(now) => return Ok(Assembly::Timeout),
+                Err(nb::Error::WouldBlock) => return Ok(Assembly::Pending),
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+        }
+    }
+}
+
+pub struct TyracontSensor {
+    addr: u8,
+    policy: RetryPolicy,
+    stats: SharedLinkStats,
+}
+
+/// Все адреса протокола Thyracont. Опрос пустого адреса ~40 ms, весь скан - около 10 s.
+pub const ADDRESSES: RangeInclusive<u8> = 1..=255;
+
+/// Линия RS485 с одним или несколькими датчиками Thyracont
+pub struct ThyracontBus<P, PIN> {
+    port: P,
+    re_de: PIN,
+    policy: RetryPolicy,
+}
+
+impl<P, E, PIN, PINE> ThyracontBus<P, PIN>
+where
+    P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+    PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+{
+    pub fn new(port: P, re_de: PIN) -> Self {
+        Self {
+            port,
+            re_de,
+            policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Политика повторов для датчиков из [`Self::into_gauges`]
+    pub fn set_policy(&mut self, policy: RetryPolicy) {
+        self.policy = policy;
+    }
+
+    /// Опрос адресов по очереди, найденные датчики: адрес и модель. На адрес одна попытка.
+    pub fn scan(&mut self, addrs: impl IntoIterator<Item = u8>) -> Result<Vec<(u8, String)>, E> {
+        let policy = RetryPolicy {
+            attempts: 1,
+            ..self.policy
+        };
+        let mut found = vec![];
+        for addr in addrs {
+            let sensor = TyracontSensor::with_policy(addr, policy);
+            if let Some(id) = sensor.get_id(&mut self.port, &mut self.re_de)? {
+                println!("Thyracont {id} found at {addr:03}");
+                found.push((addr, id));
+            }
+        }
+        Ok(found)
+    }
+
+    /// Датчики на этой линии, опрашиваются по очереди через общий замок
+    pub fn into_gauges(self, found: &[(u8, String)]) -> Vec<ThyracontGauge<P, PIN>> {
+        let policy = self.policy;
+        let bus = Arc::new(Mutex::new(self));
+        found
+            .iter()
+            .map(|(addr, model)| ThyracontGauge {
+                sensor: TyracontSensor::with_policy(*addr, policy),
+                bus: bus.clone(),
+                model: model.clone(),
+            })
+            .collect()
+    }
+
+    /// Вернуть линию, например чтобы искать на ней датчик другого типа
+    pub fn release(self) -> (P, PIN) {
+        (self.port, self.re_de)
+    }
+}
+
+/// Датчик Thyracont на линии RS485 как [`PressureGauge`], давление в mbar
+pub struct ThyracontGauge<P, PIN> {
+    sensor: TyracontSensor,
+    bus: Arc<Mutex<ThyracontBus<P, PIN>>>,
+    model: String,
+}
+
+impl<P, E, PIN, PINE> PressureGauge for ThyracontGauge<P, PIN>
+where
+    P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+    PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+    E: std::fmt::Debug,
+{
+    fn id(&self) -> String {
+        format!("Thyracont {} @{:03}", self.model, self.sensor.addr)
+    }
+
+    fn read(&mut self) -> GaugeReading {
+        let mut bus = self.bus.lock().unwrap();
+        let ThyracontBus { port, re_de, .. } = &mut *bus;
+        match self.sensor.read(port, re_de) {
+            Ok(Some(p)) => GaugeReading::ok(Pressure::new(p, PressureUnit::Mbar)),
+            Ok(None) => GaugeReading::failed(GaugeStatus::NoResponse, PressureUnit::Mbar),
+            Err(_) => GaugeReading::failed(GaugeStatus::Fault, PressureUnit::Mbar),
+        }
+    }
+
+    fn link_stats(&self) -> Option<SharedLinkStats> {
+        Some(self.sensor.stats.clone())
+    }
+
+    fn command(&mut self, cmd: GaugeCommand) -> Result<GaugeReply, GaugeError> {
+        let mut bus = self.bus.lock().unwrap();
+        let ThyracontBus { port, re_de, .. } = &mut *bus;
+        let sensor = &self.sensor;
+        let done = |ok| {
+            if ok {
+                Ok(GaugeReply::Done)
+            } else {
+                Err(GaugeError::NoResponse)
+            }
+        };
+        let switch_point = |index, p| match p {
+            Some(p_mbar) => Ok(GaugeReply::SwitchPoint { index, p_mbar }),
+            None => Err(GaugeError::NoResponse),
+        };
+        let res = match cmd {
+            GaugeCommand::Info => sensor.info(port, re_de).map(|info| {
+                if info == GaugeInfo::default() {
+                    Err(GaugeError::NoResponse)
+                } else {
+                    Ok(GaugeReply::Info(info))
+                }
+            }),
+            GaugeCommand::AdjustZero => sensor.adjust(port, re_de, None).map(done),
+            GaugeCommand::AdjustAtmosphere(p) => sensor.adjust(port, re_de, Some(p)).map(done),
+            GaugeCommand::GetSwitchPoint(i) => sensor
+                .switch_point(port, re_de, i, None)
+                .map(|p| switch_point(i, p)),
+            GaugeCommand::SetSwitchPoint(i, p) => sensor
+                .switch_point(port, re_de, i, Some(p))
+                .map(|p| switch_point(i, p)),
+        };
+        res.unwrap_or(Err(GaugeError::Fault))
+    }
+}
+
+impl TyracontSensor {
+    pub fn new(addr: u8) -> Self {
+        Self::with_policy(addr, RetryPolicy::default())
+    }
+
+    pub fn with_policy(addr: u8, policy: RetryPolicy) -> Self {
+        Self {
+            addr,
+            policy,
+            stats: Arc::new(Mutex::new(LinkStats::default())),
+        }
+    }
+
+    pub fn get_id<P, E, PIN, PINE>(
+        &self,
+        port: &mut P,
+        re_de: &mut PIN,
+    ) -> Result<Option<String>, E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+        PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+    {
+        match self.request(port, re_de, CODE_ID, b"")? {
+            Some(Response::Id(id)) => Ok(Some(id)),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn read<P, E, PIN, PINE>(&self, port: &mut P, re_de: &mut PIN) -> Result<Option<f32>, E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+        PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+    {
+        match self.request(port, re_de, CODE_MEASURE, b"")? {
+            Some(Response::Pressure(p)) => Ok(Some(p)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Юстировка: None - ноль, иначе атмосфера при давлении `p_mbar`. false - датчик не подтвердил.
+    pub fn adjust<P, E, PIN, PINE>(
+        &self,
+        port: &mut P,
+        re_de: &mut PIN,
+        p_mbar: Option<f32>,
+    ) -> Result<bool, E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+        PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+    {
+        let data = match p_mbar {
+            Some(p) => encode_pressure(p).into_bytes(),
+            None => ADJUST_ZERO.to_vec(),
+        };
+        Ok(matches!(
+            self.request(port, re_de, CODE_ADJUST, &data)?,
+            Some(Response::Adjusted(_))
+        ))
+    }
+
+    /// Точка переключения `index`, mbar. С `p_mbar` - записать и вернуть то, что принял датчик.
+    pub fn switch_point<P, E, PIN, PINE>(
+        &self,
+        port: &mut P,
+        re_de: &mut PIN,
+        index: u8,
+        p_mbar: Option<f32>,
+    ) -> Result<Option<f32>, E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+        PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+    {
+        let mut data = vec![b'0' + index % 10];
+        let code = match p_mbar {
+            Some(p) => {
+                data.extend(encode_pressure(p).into_bytes());
+                CODE_SET_SWITCH_POINT
+            }
+            None => CODE_GET_SWITCH_POINT,
+        };
+        match self.request(port, re_de, code, &data)? {
+            Some(Response::SwitchPoint { index: i, p_mbar }) if i == index => Ok(Some(p_mbar)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Серийный номер, прошивка, наработка и единица индикации, что из этого датчик отдал
+    pub fn info<P, E, PIN, PINE>(&self, port: &mut P, re_de: &mut PIN) -> Result<GaugeInfo, E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+        PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+    {
+        let mut info = GaugeInfo::default();
+        for code in [CODE_SERIAL, CODE_FIRMWARE, CODE_HOURS, CODE_UNIT] {
+            match self.request(port, re_de, code, b"")? {
+                Some(Response::Serial(s)) => info.serial = Some(s),
+                Some(Response::Firmware(s)) => info.firmware = Some(s),
+                Some(Response::Hours(h)) => info.hours = Some(h),
+                Some(Response::Unit(s)) => info.unit = Some(s),
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+
+    /// Запрос с повторами по [`RetryPolicy`], None - все попытки неудачны. Каждая попытка
+    /// учитывается в [`LinkStats`]. Ждет ответа до `policy.total` на попытку, поэтому
+    /// датчики опрашиваются из своего потока, а не из таймера.
+    fn request<P, E, PIN, PINE>(
+        &self,
+        port: &mut P,
+        re_de: &mut PIN,
+        code: u8,
+        data: &[u8],
+    ) -> Result<Option<Response>, E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+        PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+    {
+        let req = encode_frame(self.addr, code, data);
+
+        for _ in 0..self.policy.attempts {
+            Self::flush_rx(port)?;
+            Self::write_request(port, &req, re_de)?;
+
+            let mut assembler = FrameAssembler::new(self.policy, Instant::now());
+            let assembly = loop {
+                match assembler.poll(port, Instant::now())? {
+                    Assembly::Pending => thread::sleep(Duration::from_millis(1)),
+                    done => break done,
+                }
+            };
+
+            let mut stats = self.stats.lock().unwrap();
+            match assembly {
+                Assembly::Frame(frame) => match decode_resp(&frame) {
+                    Ok((addr, r)) if addr == self.addr && frame[3] == code => {
+                        stats.ok += 1;
+                        return Ok(Some(r));
+                    }
+                    // чужой адрес или код - тоже неразобранный ответ
+                    Ok(_) => stats.invalid += 1,
+                    Err(DecodeError::Crc { .. }) => stats.crc_errors += 1,
+                    Err(_) => stats.invalid += 1,
+                },
+                Assembly::Timeout => stats.timeouts += 1,
+                Assembly::Overflow | Assembly::Pending => stats.invalid += 1,
+            }
+        }
+        Ok(None)
+    }
+
+    fn write_request<P, E, PIN, PINE>(port: &mut P, req: &[u8], re_de: &mut PIN) -> Result<(), E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+        PIN: embedded_hal::digital::v2::OutputPin<Error = PINE>,
+    {
+        let _ = re_de.set_high();
+        for b in req.iter() {
+            nb::block!(port.write(*b))?;
+        }
+
+        nb::block!(port.flush())?;
+        let _ = re_de.set_low();
+
+        Ok(())
+    }
+
+    fn flush_rx<P, E>(port: &mut P) -> Result<(), E>
+    where
+        P: embedded_hal::serial::Read<u8, Error = E> + embedded_hal::serial::Write<u8, Error = E>,
+    {
+        loop {
+            match port.read() {
+                Ok(_) => {}
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => Err(e)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checksum (hex), defined as sum over bytes from fields “Address”, “Code” and “Data”, modulo 64 plus 64.
+pub fn calc_crc(data: &[u8]) -> u8 {
+    (data.iter().fold(0u32, |a, b| a + *b as u32) % 64 + 64) as u8
+}
+
+/// Кадр запроса или ответа: адрес, код, данные, сумма, `\r`
+pub fn encode_frame(addr: u8, code: u8, data: &[u8]) -> Vec<u8> {
+    let mut frame = vec![];
+    write!(&mut frame, "{addr:03}").unwrap();
+    frame.push(code);
+    frame.extend_from_slice(data);
+    frame.push(calc_crc(&frame));
+    frame.push(b'\r');
+    frame
+}
+
+/// `mmmmee` в mbar
+pub fn decode_pressure(data: &[u8]) -> Option<f32> {
+    if data.len() != 6 || !data.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let digits = |d: &[u8]| d.iter().fold(0u32, |a, b| a * 10 + (b - b'0') as u32);
+    let m = digits(&data[..4]);
+    let exp = digits(&data[4..]) as i32;
+    Some(m as f32 / 1_000.0 * 10.0f32.powi(exp - 20))
+}
+
+/// mbar в `mmmmee`
+pub fn encode_pressure(p_mbar: f32) -> String {
+    let p = p_mbar.max(1e-9);
+    let mut exp = p.log10().floor() as i32 + 20;
+    let mut m = (p / 10.0f32.powi(exp - 20) * 1000.0).round() as u32;
+    if m > 9999 {
+        m /= 10;
+        exp += 1;
+    }
+    format!("{m:04}{exp:02}")
+}
+
+/// Разбор ответа: адрес и данные. Любые байты на входе - без паники.
+pub fn decode_resp(data: &[u8]) -> Result<(u8, Response), DecodeError> {
+    // адрес, код, сумма, \r
+    const MIN_LEN: usize = 3 + 1 + 1 + 1;
+    if data.len() < MIN_LEN || data.last() != Some(&b'\r') {
+        return Err(DecodeError::Incomplete);
+    }
+
+    let body = &data[..data.len() - 2];
+    let msg = data[data.len() - 2];
+    let actual = calc_crc(body);
+    if msg != actual {
+        return Err(DecodeError::Crc { msg, actual });
+    }
+
+    let (addr, rest) = body.split_at(3);
+    if !addr.iter().all(u8::is_ascii_digit) {
+        return Err(DecodeError::Address);
+    }
+    let addr = addr.iter().fold(0u32, |a, b| a * 10 + (b - b'0') as u32);
+    let addr = u8::try_from(addr).map_err(|_| DecodeError::Address)?;
+
+    let (code, payload) = (rest[0], &rest[1..]);
+    let bad = DecodeError::BadData(code);
+    let text = |payload: &[u8]| {
+        if payload.is_empty() || !payload.iter().all(|b| (b' '..=b'~').contains(b)) {
+            return Err(bad.clone());
+        }
+        // только ASCII, ошибки быть не может
+        Ok(String::from_utf8_lossy(payload).into_owned())
+    };
+    let response = match code {
+        CODE_ID => Response::Id(text(payload)?),
+        CODE_MEASURE => Response::Pressure(decode_pressure(payload).ok_or(bad)?),
+        CODE_ADJUST => Response::Adjusted(decode_pressure(payload).ok_or(bad)?),
+        CODE_GET_SWITCH_POINT | CODE_SET_SWITCH_POINT => match payload.split_first() {
+            Some((n, p)) if n.is_ascii_digit() => Response::SwitchPoint {
+                index: n - b'0',
+                p_mbar: decode_pressure(p).ok_or(bad)?,
+            },
+            _ => return Err(bad),
+        },
+        CODE_UNIT => Response::Unit(text(payload)?),
+        CODE_SERIAL => Response::Serial(text(payload)?),
+        CODE_FIRMWARE => Response::Firmware(text(payload)?),
+        CODE_HOURS => {
+            if payload.is_empty() || !payload.iter().all(u8::is_ascii_digit) {
+                return Err(bad);
+            }
+            let hours = payload.iter().try_fold(0u32, |a, b| {
+                a.checked_mul(10)?.checked_add((b - b'0') as u32)
+            });
+            Response::Hours(hours.ok_or(bad)?)
+        }
+        _ => return Err(DecodeError::UnknownCode(code)),
+    };
+    Ok((addr, response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, thyracont_device, MockPin, MockSerial};
+
+    /// Короткие таймауты: мок отвечает сразу
+    const FAST: RetryPolicy = RetryPolicy {
+        attempts: 2,
+        first_byte: Duration::from_millis(5),
+        inter_byte: Duration::from_millis(5),
+        total: Duration::from_millis(20),
+    };
+
+    fn stats(gauge: &impl PressureGauge) -> LinkStats {
+        *gauge.link_stats().unwrap().lock().unwrap()
+    }
+
+    #[test]
+    fn assembler_frames_and_timeouts() {
+        let ms = Duration::from_millis;
+        let start = Instant::now();
+        let mut assembler = FrameAssembler::new(RetryPolicy::default(), start);
+        assert!(!assembler.expired(start + ms(40)));
+        assert!(assembler.expired(start + ms(41)));
+
+        let frame = encode_frame(1, CODE_MEASURE, b"100023");
+        let (last, head) = frame.split_last().unwrap();
+        for (i, b) in head.iter().enumerate() {
+            assert_eq!(
+                assembler.push(*b, start + ms(50 + i as u64)),
+                Assembly::Pending
+            );
+        }
+        // после первого байта ждется уже пауза между байтами
+        let now = start + ms(50 + head.len() as u64 - 1);
+        assert!(!assembler.expired(now + ms(10)));
+        assert!(assembler.expired(now + ms(11)));
+        assert_eq!(assembler.push(*last, now), Assembly::Frame(frame));
+        assert!(assembler.expired(start + ms(101)));
+
+        let mut assembler = FrameAssembler::new(RetryPolicy::default(), start);
+        for _ in 1..MAX_FRAME {
+            assert_eq!(assembler.push(b'0', start), Assembly::Pending);
+        }
+        assert_eq!(assembler.push(b'0', start), Assembly::Overflow);
+    }
+
+    #[test]
+    fn assembler_polls_without_blocking() {
+        let start = Instant::now();
+        let mut port = MockSerial::new(|_| Some(b"001M".to_vec()));
+        let mut assembler = FrameAssembler::new(FAST, start);
+        assert_eq!(assembler.poll(&mut port, start), Ok(Assembly::Pending));
+
+        embedded_hal::serial::Write::write(&mut port, b'\r').unwrap();
+        assert_eq!(assembler.poll(&mut port, start), Ok(Assembly::Pending));
+        assert_eq!(
+            assembler.poll(&mut port, start + FAST.total * 2),
+            Ok(Assembly::Timeout)
+        );
+    }
+
+    #[test]
+    fn bad_frames_counted_and_retried() {
+        let p = Arc::new(Mutex::new(5.0));
+        let mut device = thyracont_device(1, "VSP206", p);
+        let mut replies = 0;
+        let port = MockSerial::new(move |request| {
+            let mut reply = device(request)?;
+            replies += 1;
+            match replies {
+                // испорченная сумма, затем ответ чужого адреса
+                1 => *reply.iter_mut().rev().nth(1).unwrap() ^= 1,
+                2 => reply = encode_frame(9, CODE_MEASURE, b"500020"),
+                _ => {}
+            }
+            Some(reply)
+        });
+        let mut bus = ThyracontBus::new(port, MockPin);
+        bus.set_policy(RetryPolicy {
+            attempts: 3,
+            ..FAST
+        });
+        let mut gauges = bus.into_gauges(&[(1, "VSP206".to_string())]);
+
+        assert_eq!(gauges[0].read().value(PressureUnit::Mbar), Some(5.0));
+        assert_eq!(
+            stats(&gauges[0]),
+            LinkStats {
+                ok: 1,
+                crc_errors: 1,
+                timeouts: 0,
+                invalid: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn configuration_commands() {
+        let p = Arc::new(Mutex::new(1.0));
+        let mut bus = ThyracontBus::new(MockSerial::new(thyracont_device(4, "VSP206", p)), MockPin);
+        bus.set_policy(FAST);
+        let mut gauges = bus.into_gauges(&[(4, "VSP206".to_string())]);
+        let gauge = &mut gauges[0];
+
+        assert_eq!(
+            gauge.command(GaugeCommand::Info),
+            Ok(GaugeReply::Info(GaugeInfo {
+                serial: Some("SN00004".to_string()),
+                firmware: Some("2.1".to_string()),
+                hours: Some(1234),
+                unit: Some("mbar".to_string()),
+            }))
+        );
+        assert_eq!(
+            gauge.command(GaugeCommand::AdjustZero),
+            Ok(GaugeReply::Done)
+        );
+        assert_eq!(
+            gauge.command(GaugeCommand::AdjustAtmosphere(1013.0)),
+            Ok(GaugeReply::Done)
+        );
+
+        assert_eq!(
+            gauge.command(GaugeCommand::GetSwitchPoint(2)),
+            Ok(GaugeReply::SwitchPoint {
+                index: 2,
+                p_mbar: 1.0
+            })
+        );
+        assert_eq!(
+            gauge.command(GaugeCommand::SetSwitchPoint(2, 0.5)),
+            Ok(GaugeReply::SwitchPoint {
+                index: 2,
+                p_mbar: 0.5
+            })
+        );
+        assert_eq!(
+            gauge.command(GaugeCommand::GetSwitchPoint(2)),
+            Ok(GaugeReply::SwitchPoint {
+                index: 2,
+                p_mbar: 0.5
+            })
+        );
+        // точки 3 у датчика нет
+        assert_eq!(
+            gauge.command(GaugeCommand::GetSwitchPoint(3)),
+            Err(GaugeError::NoResponse)
+        );
+    }
+
+    #[test]
+    fn configuration_frames() {
+        let port = MockSerial::new(|_| None);
+        let sensor = TyracontSensor::with_policy(
+            12,
+            RetryPolicy {
+                attempts: 1,
+                ..FAST
+            },
+        );
+        let (mut port, mut pin) = (port, MockPin);
+        assert_eq!(sensor.adjust(&mut port, &mut pin, None), Ok(false));
+        assert_eq!(sensor.adjust(&mut port, &mut pin, Some(1013.0)), Ok(false));
+        assert_eq!(
+            sensor.switch_point(&mut port, &mut pin, 1, Some(0.05)),
+            Ok(None)
+        );
+        assert_eq!(
+            port.requests,
+            [
+                encode_frame(12, CODE_ADJUST, b"000000"),
+                encode_frame(12, CODE_ADJUST, b"101323"),
+                encode_frame(12, CODE_SET_SWITCH_POINT, b"1500018"),
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_finds_every_gauge() {
+        let pirani = Arc::new(Mutex::new(1.5e-2));
+        let piezo = Arc::new(Mutex::new(900.0));
+        let port = MockSerial::new(mock::bus(vec![
+            Box::new(thyracont_device(1, "VSP206", pirani.clone())),
+            Box::new(thyracont_device(7, "VSC43", piezo.clone())),
+        ]));
+        let mut bus = ThyracontBus::new(port, MockPin);
+        bus.set_policy(FAST);
+
+        let found = bus.scan(1..=10).unwrap();
+        assert_eq!(found, [(1, "VSP206".to_string()), (7, "VSC43".to_string())]);
+
+        let mut gauges = bus.into_gauges(&found);
+        assert_eq!(gauges.len(), 2);
+        assert_eq!(gauges[0].id(), "Thyracont VSP206 @001");
+        assert_eq!(gauges[1].id(), "Thyracont VSC43 @007");
+
+        // у каждого датчика свое давление и свои счетчики
+        assert_eq!(gauges[0].read().value(PressureUnit::Mbar), Some(1.5e-2));
+        assert_eq!(gauges[1].read().value(PressureUnit::Mbar), Some(900.0));
+        *piezo.lock().unwrap() = 750.0;
+        assert_eq!(gauges[1].read().value(PressureUnit::Mbar), Some(750.0));
+        assert_eq!(stats(&gauges[0]).ok, 1);
+        assert_eq!(stats(&gauges[1]).ok, 2);
+    }
+
+    #[test]
+    fn empty_bus() {
+        let mut bus = ThyracontBus::new(MockSerial::new(|_| None), MockPin);
+        bus.set_policy(FAST);
+        assert!(bus.scan(1..=3).unwrap().is_empty());
+
+        // на адрес при скане одна попытка
+        let (port, _) = bus.release();
+        assert_eq!(port.requests.len(), 3);
+        assert_eq!(port.requests[2], encode_frame(3, CODE_ID, b""));
+    }
+
+    #[test]
+    fn lost_gauge_times_out() {
+        let p = Arc::new(Mutex::new(1.0));
+        let mut bus = ThyracontBus::new(MockSerial::new(thyracont_device(2, "VSP206", p)), MockPin);
+        bus.set_policy(FAST);
+        let mut gauges = bus.into_gauges(&[(3, "VSP206".to_string())]);
+
+        assert_eq!(gauges[0].read().status, GaugeStatus::NoResponse);
+        assert_eq!(
+            stats(&gauges[0]),
+            LinkStats {
+                timeouts: FAST.attempts as u32,
+                ..LinkStats::default()
+            }
+        );
+    }
+}
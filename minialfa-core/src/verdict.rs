@@ -0,0 +1,132 @@
+//! Допуски на результат измерения и вердикт ГОДЕН/БРАК
+
+/// Диапазон допустимых значений, границы включаются
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Range {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Range {
+    pub const fn new(min: f32, max: f32) -> Self {
+        Self { min, max }
+    }
+
+    /// NaN не попадает ни в какой диапазон
+    pub fn contains(&self, value: f32) -> bool {
+        value >= self.min && value <= self.max
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Criterion {
+    Sensivity,
+    FAtmosphere,
+    FVacuum,
+    Temperature,
+}
+
+impl Criterion {
+    /// Короткое имя для баннера на экране результата
+    pub fn name(&self) -> &'static str {
+        match self {
+            Criterion::Sensivity => "Чувст.",
+            Criterion::FAtmosphere => "F атм",
+            Criterion::FVacuum => "F вак",
+            Criterion::Temperature => "Темп.",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verdict {
+    Pass,
+    /// Первый не выполненный критерий
+    Fail(Criterion),
+}
+
+/// Значения, по которым выносится вердикт
+#[derive(Clone, Copy, Debug)]
+pub struct Measured {
+    /// Hz/mmHg
+    pub sensivity: f32,
+    /// Частота DUT в начале откачки, Hz
+    pub f_atm: f32,
+    /// Частота DUT в конце удержания, Hz
+    pub f_vac: f32,
+    /// *C
+    pub t: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerances {
+    pub sensivity: Range,
+    pub f_atm: Range,
+    pub f_vac: Range,
+    pub t: Range,
+}
+
+impl Tolerances {
+    pub fn check(&self, measured: &Measured) -> Verdict {
+        [
+            (Criterion::Sensivity, self.sensivity, measured.sensivity),
+            (Criterion::FAtmosphere, self.f_atm, measured.f_atm),
+            (Criterion::FVacuum, self.f_vac, measured.f_vac),
+            (Criterion::Temperature, self.t, measured.t),
+        ]
+        .into_iter()
+        .find(|(_, range, value)| !range.contains(*value))
+        .map_or(Verdict::Pass, |(criterion, _, _)| Verdict::Fail(criterion))
+    }
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Self {
+            sensivity: Range::new(3.0, 5.0),
+            f_atm: Range::new(31000.0, 34000.0),
+            f_vac: Range::new(29000.0, 31000.0),
+            t: Range::new(15.0, 35.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOOD: Measured = Measured {
+        sensivity: 4.0,
+        f_atm: 33000.0,
+        f_vac: 30000.0,
+        t: 25.0,
+    };
+
+    #[test]
+    fn pass_inside_and_on_bounds() {
+        let tolerances = Tolerances::default();
+        assert_eq!(tolerances.check(&GOOD), Verdict::Pass);
+        let edge = Measured {
+            sensivity: 5.0,
+            f_atm: 31000.0,
+            ..GOOD
+        };
+        assert_eq!(tolerances.check(&edge), Verdict::Pass);
+    }
+
+    #[test]
+    fn first_failed_criterion() {
+        let tolerances = Tolerances::default();
+        let bad = Measured {
+            f_vac: 32000.0,
+            t: 40.0,
+            ..GOOD
+        };
+        assert_eq!(tolerances.check(&bad), Verdict::Fail(Criterion::FVacuum));
+        let nan = Measured {
+            sensivity: f32::NAN,
+            ..GOOD
+        };
+        assert_eq!(tolerances.check(&nan), Verdict::Fail(Criterion::Sensivity));
+    }
+}
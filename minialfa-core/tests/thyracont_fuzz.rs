@@ -0,0 +1,182 @@
+//! Случайные и испорченные ответы Thyracont через декодер и сборщик кадров
+//!
+//! ```shell
+//! [FUZZ_CASES=<итераций>] [FUZZ_SEED=<seed>] cargo test -p minialfa-core --test thyracont_fuzz --target x86_64-unknown-linux-gnu
+//! ```
+//!
+//! Проверяется, что на любых байтах нет паники, что верный кадр разбирается обратно в то,
+//! из чего собран, и что неполный кадр не принимается. По умолчанию seed постоянный и случаев
+//! немного, чтобы тест был быстрым и повторяемым. Контрпример печатается вместе с seed.
+
+use std::{
+    panic,
+    time::{Duration, Instant},
+};
+
+use minialfa_core::thyracont_sensor::{
+    decode_pressure, decode_resp, encode_frame, encode_pressure, Assembly, DecodeError,
+    FrameAssembler, Response, RetryPolicy, CODE_ADJUST, CODE_FIRMWARE, CODE_GET_SWITCH_POINT,
+    CODE_HOURS, CODE_ID, CODE_MEASURE, CODE_SERIAL, CODE_SET_SWITCH_POINT, CODE_UNIT,
+};
+
+/// xorshift64, чтобы не тянуть rand
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn byte(&mut self) -> u8 {
+        // чаще символы протокола, иначе до разбора данных почти не доходит
+        const ALPHABET: &[u8] = b"0123456789TMjSsUNVhP\r";
+        if self.below(4) == 0 {
+            self.next() as u8
+        } else {
+            ALPHABET[self.below(ALPHABET.len())]
+        }
+    }
+}
+
+/// Верный ответ и то, что из него должно получиться
+fn valid_frame(rng: &mut Rng) -> (Vec<u8>, u8, Response) {
+    let addr = 1 + rng.below(255) as u8;
+    let text = (0..1 + rng.below(10))
+        .map(|_| b' ' + rng.below(95) as u8)
+        .collect::<Vec<_>>();
+    let p = 10.0f32.powf(rng.below(1400) as f32 / 100.0 - 10.0);
+    let data = encode_pressure(p);
+    let p = decode_pressure(data.as_bytes()).unwrap();
+
+    let (code, payload, response) = match rng.below(8) {
+        0 => (
+            CODE_ID,
+            text.clone(),
+            Response::Id(String::from_utf8(text).unwrap()),
+        ),
+        1 => (CODE_MEASURE, data.into_bytes(), Response::Pressure(p)),
+        2 => (CODE_ADJUST, data.into_bytes(), Response::Adjusted(p)),
+        3 => {
+            let index = rng.below(10) as u8;
+            let code = [CODE_GET_SWITCH_POINT, CODE_SET_SWITCH_POINT][rng.below(2)];
+            let mut payload = vec![b'0' + index];
+            payload.extend(data.bytes());
+            (code, payload, Response::SwitchPoint { index, p_mbar: p })
+        }
+        4 => (
+            CODE_UNIT,
+            text.clone(),
+            Response::Unit(String::from_utf8(text).unwrap()),
+        ),
+        5 => (
+            CODE_SERIAL,
+            text.clone(),
+            Response::Serial(String::from_utf8(text).unwrap()),
+        ),
+        6 => (
+            CODE_FIRMWARE,
+            text.clone(),
+            Response::Firmware(String::from_utf8(text).unwrap()),
+        ),
+        _ => {
+            let hours = rng.next() as u32;
+            (
+                CODE_HOURS,
+                hours.to_string().into_bytes(),
+                Response::Hours(hours),
+            )
+        }
+    };
+    (encode_frame(addr, code, &payload), addr, response)
+}
+
+fn garbage(rng: &mut Rng) -> Vec<u8> {
+    (0..rng.below(40)).map(|_| rng.byte()).collect()
+}
+
+/// Испорченный верный кадр: замена, вставка или удаление байта
+fn mutate(rng: &mut Rng, mut frame: Vec<u8>) -> Vec<u8> {
+    let i = rng.below(frame.len());
+    match rng.below(3) {
+        0 => frame[i] = rng.byte(),
+        1 => frame.insert(i, rng.byte()),
+        _ => {
+            frame.remove(i);
+        }
+    }
+    frame
+}
+
+/// Один случай, Err - описание нарушенного свойства
+fn check(rng: &mut Rng) -> Result<(), String> {
+    let (frame, addr, expected) = valid_frame(rng);
+    match decode_resp(&frame) {
+        Ok((a, r)) if a == addr && r == expected => {}
+        other => {
+            return Err(format!(
+                "{frame:?}: expected {addr} {expected:?}, got {other:?}"
+            ))
+        }
+    }
+
+    let cut = &frame[..rng.below(frame.len())];
+    if decode_resp(cut) != Err(DecodeError::Incomplete) {
+        return Err(format!("{cut:?}: truncated frame accepted"));
+    }
+
+    for input in [garbage(rng), mutate(rng, frame)] {
+        let _ = decode_resp(&input);
+
+        let start = Instant::now();
+        let mut assembler = FrameAssembler::new(RetryPolicy::default(), start);
+        for &b in &input {
+            match assembler.push(b, start) {
+                Assembly::Frame(f) if f.last() != Some(&b'\r') => {
+                    return Err(format!("{input:?}: frame without \\r {f:?}"));
+                }
+                Assembly::Frame(f) => {
+                    let _ = decode_resp(&f);
+                }
+                _ => {}
+            }
+        }
+        assembler.expired(start + Duration::from_secs(1));
+    }
+    Ok(())
+}
+
+fn env(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+#[test]
+fn thyracont_fuzz() {
+    let iterations = env("FUZZ_CASES", 20_000);
+    let seed = env("FUZZ_SEED", 0x5EED) | 1;
+
+    let mut rng = Rng(seed);
+    for i in 0..iterations {
+        let case_seed = rng.next() | 1;
+        // паника в декодере - тоже контрпример
+        let result = panic::catch_unwind(|| check(&mut Rng(case_seed))).unwrap_or_else(|e| {
+            let msg = e
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| e.downcast_ref::<String>().cloned());
+            Err(format!("panic: {}", msg.unwrap_or_default()))
+        });
+        if let Err(e) = result {
+            panic!("seed={seed} case={i} case_seed={case_seed}: {e}");
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! Симуляция периферии: клапан, шина I2C с датчиками SCTB, RS485 с датчиками Thyracont
+
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use minialfa_core::{
+    gauge::{Pressure, PressureUnit},
+    i2c_sensor::{self, Calibration},
+    physics::Chamber,
+    thyracont_sensor::{self, encode_frame, encode_pressure},
+};
+
+pub const P_SENSOR_ADDR: u8 = 15;
+/// Гнезда испытуемых датчиков
+pub const F_SENSOR_ADDRS: [u8; 3] = [11, 12, 13];
+/// Версия карты регистров датчиков SCTB, с CRC
+const SCTB_MAP_VERSION: u8 = 2;
+const SCTB_FIRMWARE: (u8, u8) = (1, 4);
+const SCTB_PERIOD_MS: u16 = 100;
+/// Калибровка из "завода": P = (F - 30000) / 4
+const SCTB_CALIBRATION: Calibration = Calibration {
+    f0: 30000.0,
+    coeffs: [0.0, 0.25, 0.0, 0.0],
+};
+/// Адреса датчиков Thyracont, скан всего диапазона в симуляторе слишком долгий
+pub const THYRACONT_SCAN: std::ops::RangeInclusive<u8> = 1..=4;
+
+/// Pirani
+const PIRANI: (u8, &str) = (1, "VSP206");
+/// Пьезо, ниже 1 mbar показывает нижнюю границу
+const PIEZO: (u8, &str) = (2, "VSC43");
+const PIEZO_MIN_MBAR: f32 = 1.0;
+/// Задержка ответа датчика Thyracont
+const THYRACONT_LATENCY: Duration = Duration::from_millis(10);
+
+/// Выход управления клапаном: основной (high - откачка) или отсечной (high - отсечена)
+pub struct ValvePin {
+    chamber: Arc<Mutex<Chamber>>,
+    isolation: bool,
+}
+
+impl ValvePin {
+    pub fn new(chamber: Arc<Mutex<Chamber>>) -> Self {
+        Self {
+            chamber,
+            isolation: false,
+        }
+    }
+
+    pub fn isolation(chamber: Arc<Mutex<Chamber>>) -> Self {
+        Self {
+            chamber,
+            isolation: true,
+        }
+    }
+
+    fn set(&mut self, high: bool) {
+        let mut chamber = self.chamber.lock().unwrap();
+        if self.isolation {
+            chamber.set_isolated(high);
+        } else {
+            chamber.set_pumping(high);
+        }
+    }
+}
+
+impl embedded_hal::digital::v2::OutputPin for ValvePin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.set(true);
+        Ok(())
+    }
+}
+
+/// Неподключенный выход (RE/DE)
+pub struct DummyPin;
+
+impl embedded_hal::digital::v2::OutputPin for DummyPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum I2cError {
+    /// Адрес не ответил, читается только через Debug в отладочном выводе
+    Nack(#[allow(dead_code)] u8),
+}
+
+/// Шина I2C с датчиком давления и испытуемыми датчиками SCTB, испытуемые можно "вынуть"
+pub struct SctbBus {
+    chamber: Arc<Mutex<Chamber>>,
+    /// Калибровка датчика давления и испытуемых по гнездам
+    calibration: [Calibration; 1 + F_SENSOR_ADDRS.len()],
+}
+
+impl SctbBus {
+    pub fn new(chamber: Arc<Mutex<Chamber>>) -> Self {
+        Self {
+            chamber,
+            calibration: [SCTB_CALIBRATION; 1 + F_SENSOR_ADDRS.len()],
+        }
+    }
+}
+
+/// Гнездо испытуемого датчика по адресу
+fn socket(address: u8) -> Option<usize> {
+    F_SENSOR_ADDRS.iter().position(|&a| a == address)
+}
+
+impl embedded_hal::blocking::i2c::WriteRead for SctbBus {
+    type Error = I2cError;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), I2cError> {
+        let mut chamber = self.chamber.lock().unwrap();
+        let (pressure, temperature, f_p, f_t) = match (address, socket(address)) {
+            (P_SENSOR_ADDR, _) => (
+                chamber.measure_pressure(),
+                chamber.measure_temperature(),
+                f32::NAN,
+                f32::NAN,
+            ),
+            (_, Some(i)) if chamber.dut_connected() => (
+                f32::NAN,
+                chamber.measure_temperature(),
+                chamber.measure_dut_frequency(i),
+                f32::NAN,
+            ),
+            _ => return Err(I2cError::Nack(address)),
+        };
+
+        let mut block = vec![];
+        match bytes.first() {
+            Some(&i2c_sensor::IDENT_ADDR) => {
+                let mut ident = i2c_sensor::IDENT_MAGIC.to_vec();
+                ident.push(SCTB_MAP_VERSION);
+                copy_block(buffer, &ident);
+                return Ok(());
+            }
+            Some(&i2c_sensor::OUTPUT_VALUES_ADDR) => {
+                // порядок полей как в i2c_sensor::SensorResult
+                for v in [pressure, temperature, f_p, f_t] {
+                    block.extend(v.to_le_bytes());
+                }
+            }
+            Some(&i2c_sensor::INFO_ADDR) => {
+                // серийный номер из адреса, чтобы датчики различались
+                block.extend((21_000_000 + address as u32).to_le_bytes());
+                block.extend([SCTB_FIRMWARE.0, SCTB_FIRMWARE.1]);
+                block.extend(SCTB_PERIOD_MS.to_le_bytes());
+            }
+            Some(&i2c_sensor::CALIBRATION_ADDR) => {
+                let calibration = &self.calibration[socket(address).map_or(0, |i| i + 1)];
+                for v in std::iter::once(calibration.f0).chain(calibration.coeffs) {
+                    block.extend(v.to_le_bytes());
+                }
+            }
+            _ => return Err(I2cError::Nack(address)),
+        }
+        block.push(i2c_sensor::crc8(&block));
+        copy_block(buffer, &block);
+        Ok(())
+    }
+}
+
+/// Запись принимается только в блок калибровки и только с верной CRC, как в датчике
+impl embedded_hal::blocking::i2c::Write for SctbBus {
+    type Error = I2cError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), I2cError> {
+        let dut_connected = self.chamber.lock().unwrap().dut_connected();
+        let index = match (address, socket(address)) {
+            (P_SENSOR_ADDR, _) => 0,
+            (_, Some(i)) if dut_connected => i + 1,
+            _ => return Err(I2cError::Nack(address)),
+        };
+        match bytes.split_first() {
+            Some((&i2c_sensor::CALIBRATION_ADDR, block)) => {
+                match i2c_sensor::decode_calibration(block, SCTB_MAP_VERSION) {
+                    Ok(calibration) => self.calibration[index] = calibration,
+                    Err(e) => println!("SCTB {address}: calibration rejected: {e:?}"),
+                }
+                Ok(())
+            }
+            _ => Err(I2cError::Nack(address)),
+        }
+    }
+}
+
+/// Мастер может прочитать меньше или больше блока, лишнее - 0xFF как у отпущенной шины
+fn copy_block(buffer: &mut [u8], block: &[u8]) {
+    buffer.fill(0xFF);
+    let n = buffer.len().min(block.len());
+    buffer[..n].copy_from_slice(&block[..n]);
+}
+
+/// Линия RS485 с датчиками Thyracont: Pirani и пьезо
+pub struct ThyracontLine {
+    chamber: Arc<Mutex<Chamber>>,
+    /// Точки переключения Pirani и пьезо, mbar
+    switch_points: [[f32; 2]; 2],
+    request: Vec<u8>,
+    response: VecDeque<u8>,
+    response_ready_at: Instant,
+}
+
+impl ThyracontLine {
+    pub fn new(chamber: Arc<Mutex<Chamber>>) -> Self {
+        Self {
+            chamber,
+            switch_points: [[1e-2, 1.0]; 2],
+            request: vec![],
+            response: VecDeque::new(),
+            response_ready_at: Instant::now(),
+        }
+    }
+
+    fn process_request(&mut self) {
+        let req = std::mem::take(&mut self.request);
+        // "AAAC" + crc + '\r'
+        if req.len() < 6 {
+            return;
+        }
+        let Ok(addr) = String::from_utf8_lossy(&req[..3]).parse::<u8>() else {
+            return;
+        };
+        let Some((n, (_, id))) = [PIRANI, PIEZO]
+            .into_iter()
+            .enumerate()
+            .find(|(_, (a, _))| *a == addr)
+        else {
+            return;
+        };
+        let data = &req[4..req.len() - 2];
+
+        let resp = match req[3] {
+            thyracont_sensor::CODE_ID => encode_frame(addr, req[3], id.as_bytes()),
+            thyracont_sensor::CODE_MEASURE => {
+                let p = self.chamber.lock().unwrap().measure_pressure();
+                let mut p_mbar = Pressure::new(p, PressureUnit::MmHg).to(PressureUnit::Mbar);
+                if addr == PIEZO.0 {
+                    p_mbar = p_mbar.max(PIEZO_MIN_MBAR);
+                }
+                encode_frame(addr, req[3], encode_pressure(p_mbar).as_bytes())
+            }
+            // юстировка ничего не меняет, только подтверждается
+            thyracont_sensor::CODE_ADJUST => encode_frame(addr, req[3], data),
+            thyracont_sensor::CODE_GET_SWITCH_POINT | thyracont_sensor::CODE_SET_SWITCH_POINT => {
+                let Some(i @ 1..=2) = data.first().map(|d| d.wrapping_sub(b'0')) else {
+                    return;
+                };
+                let point = &mut self.switch_points[n][i as usize - 1];
+                if req[3] == thyracont_sensor::CODE_SET_SWITCH_POINT {
+                    let Some(p) = thyracont_sensor::decode_pressure(&data[1..]) else {
+                        return;
+                    };
+                    *point = p;
+                }
+                let mut resp = vec![data[0]];
+                resp.extend(encode_pressure(*point).bytes());
+                encode_frame(addr, req[3], &resp)
+            }
+            thyracont_sensor::CODE_UNIT => encode_frame(addr, req[3], b"mbar"),
+            thyracont_sensor::CODE_SERIAL => {
+                encode_frame(addr, req[3], format!("SIM{addr:05}").as_bytes())
+            }
+            thyracont_sensor::CODE_FIRMWARE => encode_frame(addr, req[3], b"1.00"),
+            thyracont_sensor::CODE_HOURS => encode_frame(addr, req[3], b"1234"),
+            _ => return,
+        };
+
+        self.response.extend(resp);
+        self.response_ready_at = Instant::now() + THYRACONT_LATENCY;
+    }
+}
+
+impl embedded_hal::serial::Read<u8> for ThyracontLine {
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Infallible> {
+        if Instant::now() < self.response_ready_at {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.response.pop_front().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl embedded_hal::serial::Write<u8> for ThyracontLine {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Infallible> {
+        self.request.push(word);
+        if word == b'\r' {
+            self.process_request();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Infallible> {
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+//! Симулятор Мини-Альфа: настоящие контроллер и экраны поверх модели вакуумной камеры
+//!
+//! ```shell
+//! cargo run -p minialfa-sim --target x86_64-unknown-linux-gnu > sim.log
+//! ```
+
+mod devices;
+mod screen;
+mod sensors;
+mod terminal;
+
+use std::sync::{Arc, Mutex};
+
+use minialfa_core::{
+    controller, display, framebuffer::FrameBuffer, klapan, mock, physics, platform,
+};
+
+fn main() -> anyhow::Result<()> {
+    let chamber = Arc::new(Mutex::new(physics::Chamber::new()));
+
+    let mut controller =
+        controller::Controller::new(mock::MemoryStorage::default(), platform::StdClock::new());
+
+    let mut klapan = klapan::Klapan::with_isolation(
+        devices::ValvePin::new(chamber.clone()),
+        devices::ValvePin::isolation(chamber.clone()),
+    );
+    let mut sensors = sensors::SimSensors::new(chamber.clone(), controller.sensor_chanel())?;
+
+    let frame = Arc::new(Mutex::new(FrameBuffer::new()));
+    let disp = screen::SharedScreen::new(frame.clone());
+
+    let disp_channel = controller.display_chanel();
+    std::thread::Builder::new()
+        .name("Display".to_string())
+        .spawn(move || display::dispaly_thread(disp, disp_channel))?;
+
+    let encoder = controller.command_chanel();
+    std::thread::Builder::new()
+        .name("Controller".to_string())
+        .spawn(move || loop {
+            controller.poll(&mut sensors, &mut klapan);
+        })?;
+
+    terminal::run(frame, chamber, encoder)
+}
@@ -0,0 +1,53 @@
+//! Экран симулятора: кадр рисуется в памяти и по flush() передается терминалу
+
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::{DrawTarget, OriginDimensions, Size},
+    Pixel,
+};
+
+use minialfa_core::{display::Display, framebuffer::FrameBuffer};
+
+pub struct SharedScreen {
+    frame: FrameBuffer,
+    shared: Arc<Mutex<FrameBuffer>>,
+}
+
+impl SharedScreen {
+    pub fn new(shared: Arc<Mutex<FrameBuffer>>) -> Self {
+        Self {
+            frame: FrameBuffer::new(),
+            shared,
+        }
+    }
+}
+
+impl OriginDimensions for SharedScreen {
+    fn size(&self) -> Size {
+        self.frame.size()
+    }
+}
+
+impl DrawTarget for SharedScreen {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Infallible>
+    where
+        I: IntoIterator<Item = Pixel<BinaryColor>>,
+    {
+        self.frame.draw_iter(pixels)
+    }
+}
+
+impl Display for SharedScreen {
+    fn flush(&mut self) -> Result<(), Infallible> {
+        *self.shared.lock().unwrap() = self.frame.clone();
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+//! Периодический опрос датчиков, замена таймеров ESP-IDF
+
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crossbeam::channel::Sender;
+
+use minialfa_core::{
+    calibration::{self, WriteError},
+    controller::{SensorResult, MAX_DUTS},
+    gauge::{
+        GaugeCommand, GaugeError, GaugeReply, LinkStats, PressureGauge, PressureUnit,
+        SharedLinkStats,
+    },
+    i2c_sensor::{self, Calibration, I2CGauge, I2CSensor, SctbDevice, SctbInfo},
+    physics::Chamber,
+    platform::SensorsControl,
+    thyracont_sensor::ThyracontBus,
+};
+
+use crate::devices::{self, DummyPin, SctbBus, ThyracontLine};
+
+/// Датчики SCTB на одной шине: давления и испытуемые
+struct SctbSensors {
+    p_sensor: I2CGauge<SctbBus>,
+    duts: Vec<I2CSensor>,
+}
+
+impl SctbSensors {
+    /// P, F по DUT, T для контроллера
+    fn read(&mut self) -> (f32, [f32; MAX_DUTS], f32) {
+        let p = self
+            .p_sensor
+            .read()
+            .value(PressureUnit::MmHg)
+            .unwrap_or(f32::NAN);
+        let t = self.p_sensor.temperature();
+        let mut f = [f32::NAN; MAX_DUTS];
+        for (f_dut, dut) in f.iter_mut().zip(self.duts.iter_mut()) {
+            match dut.read(self.p_sensor.bus()) {
+                Ok(v) => *f_dut = v.f_p.unwrap_or(f32::NAN),
+                Err(e) => println!("Failed to read I2C sensor at {}: {e:?}", dut.address()),
+            }
+        }
+        (p, f, t)
+    }
+}
+
+pub struct SimSensors {
+    period: Arc<Mutex<Option<Duration>>>,
+    sctb: Arc<Mutex<SctbSensors>>,
+    gauges: Vec<String>,
+    gauge_stats: Vec<Option<SharedLinkStats>>,
+    gauge_handles: Arc<Mutex<Vec<Box<dyn PressureGauge + Send>>>>,
+}
+
+impl SimSensors {
+    pub fn new(
+        chamber: Arc<Mutex<Chamber>>,
+        sensor_channel: Sender<SensorResult>,
+    ) -> anyhow::Result<Self> {
+        let period = Arc::new(Mutex::new(None));
+
+        let sctb = Arc::new(Mutex::new(SctbSensors {
+            p_sensor: I2CGauge::new(devices::P_SENSOR_ADDR, SctbBus::new(chamber.clone())),
+            duts: vec![I2CSensor::new(devices::F_SENSOR_ADDRS[0])],
+        }));
+        let thread_sctb = sctb.clone();
+
+        let mut gauges: Vec<Box<dyn PressureGauge + Send>> = Vec::new();
+        let mut thyracont = ThyracontBus::new(ThyracontLine::new(chamber), DummyPin);
+        let found = thyracont.scan(devices::THYRACONT_SCAN)?;
+        if found.is_empty() {
+            println!("Thyracont Sensor not found");
+        }
+        for g in thyracont.into_gauges(&found) {
+            gauges.push(Box::new(g));
+        }
+        let ids = gauges.iter().map(|g| g.id()).collect::<Vec<_>>();
+        let gauge_stats = gauges.iter().map(|g| g.link_stats()).collect();
+        for id in &ids {
+            println!("Gauge: {id}");
+        }
+        let gauges = Arc::new(Mutex::new(gauges));
+        let gauge_handles = gauges.clone();
+
+        let thread_period = period.clone();
+        thread::Builder::new()
+            .name("Sensors".to_string())
+            .spawn(move || loop {
+                let Some(period) = *thread_period.lock().unwrap() else {
+                    thread::sleep(Duration::from_millis(10));
+                    continue;
+                };
+                thread::sleep(period);
+
+                // датчики отпускаются до отправки: контроллер может ждать их в
+                // dut_info или gauge_command и не разбирать канал
+                let (p, f, t) = thread_sctb.lock().unwrap().read();
+                let _ = sensor_channel.send(SensorResult::SctbSensorResult { f, p, t });
+
+                let readings = gauges
+                    .lock()
+                    .unwrap()
+                    .iter_mut()
+                    .map(|g| g.read())
+                    .collect::<Vec<_>>();
+                for (gauge, reading) in readings.into_iter().enumerate() {
+                    let _ = sensor_channel.send(SensorResult::GaugeResult { gauge, reading });
+                }
+            })?;
+
+        Ok(Self {
+            period,
+            sctb,
+            gauges: ids,
+            gauge_stats,
+            gauge_handles,
+        })
+    }
+}
+
+impl SensorsControl for SimSensors {
+    type Error = Infallible;
+
+    fn start(&mut self, period: Duration) -> Result<(), Infallible> {
+        self.period.lock().unwrap().replace(period);
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), Infallible> {
+        self.period.lock().unwrap().take();
+        Ok(())
+    }
+
+    fn gauges(&self) -> &[String] {
+        &self.gauges
+    }
+
+    fn gauge_stats(&self, gauge: usize) -> Option<LinkStats> {
+        let stats = self.gauge_stats.get(gauge)?.as_ref()?;
+        Some(*stats.lock().unwrap())
+    }
+
+    fn gauge_command(&mut self, gauge: usize, cmd: GaugeCommand) -> Result<GaugeReply, GaugeError> {
+        let mut gauges = self.gauge_handles.lock().unwrap();
+        gauges.get_mut(gauge).ok_or(GaugeError::Fault)?.command(cmd)
+    }
+
+    fn dut_info(&mut self, dut: usize) -> Option<SctbInfo> {
+        let mut sctb = self.sctb.lock().unwrap();
+        let SctbSensors { p_sensor, duts } = &mut *sctb;
+        let dut = duts.get_mut(dut)?;
+        match dut.read_info(p_sensor.bus()) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                println!("Failed to read DUT info at {}: {e:?}", dut.address());
+                None
+            }
+        }
+    }
+
+    fn write_dut_calibration(&mut self, dut: usize, cal: &Calibration) -> Result<(), WriteError> {
+        let mut sctb = self.sctb.lock().unwrap();
+        let SctbSensors { p_sensor, duts } = &mut *sctb;
+        let dut = duts.get_mut(dut).ok_or(WriteError::Bus)?;
+        calibration::program(dut, p_sensor.bus(), cal)
+    }
+
+    fn scan_sctb(&mut self) -> Option<Vec<SctbDevice>> {
+        let mut sctb = self.sctb.lock().unwrap();
+        Some(i2c_sensor::scan(
+            sctb.p_sensor.bus(),
+            i2c_sensor::SCAN_ADDRESSES,
+        ))
+    }
+
+    fn assign_sctb(&mut self, reference: u8, duts: &[u8]) {
+        let mut sctb = self.sctb.lock().unwrap();
+        sctb.p_sensor.set_address(reference);
+        // прочитанные период и калибровка сохраняются, если адрес не сменился
+        let mut previous = std::mem::take(&mut sctb.duts);
+        sctb.duts = duts
+            .iter()
+            .map(
+                |&address| match previous.iter().position(|d| d.address() == address) {
+                    Some(i) => previous.swap_remove(i),
+                    None => I2CSensor::new(address),
+                },
+            )
+            .collect();
+    }
+}
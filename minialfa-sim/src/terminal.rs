@@ -0,0 +1,155 @@
+//! Отрисовка экрана 128x64 в терминале и ввод с клавиатуры вместо энкодера
+
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crossbeam::channel::Sender;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    execute, queue,
+    style::Print,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use minialfa_core::{
+    controller::EncoderCommand,
+    framebuffer::{self, FrameBuffer},
+    physics::{self, Chamber},
+};
+
+const REFRESH_PERIOD: Duration = Duration::from_millis(50);
+/// Сколько "держать" кнопку энкодера нажатой
+const BUTTON_PRESS_TIME: Duration = Duration::from_millis(150);
+
+const HELP: &str =
+    "←/↓ - Decrement  →/↑ - Increment  Enter/Space - кнопка  d - вставить/вынуть DUT  q/Esc - выход";
+
+/// Рисует в stderr, чтобы отладочный вывод (stdout) можно было перенаправить в файл
+pub fn run(
+    frame: Arc<Mutex<FrameBuffer>>,
+    chamber: Arc<Mutex<Chamber>>,
+    encoder: Sender<EncoderCommand>,
+) -> anyhow::Result<()> {
+    let mut out = io::stderr();
+
+    terminal::enable_raw_mode()?;
+    execute!(out, EnterAlternateScreen, cursor::Hide)?;
+
+    let res = event_loop(&mut out, &frame, &chamber, &encoder);
+
+    execute!(out, cursor::Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+
+    res
+}
+
+fn event_loop(
+    out: &mut impl Write,
+    frame: &Mutex<FrameBuffer>,
+    chamber: &Mutex<Chamber>,
+    encoder: &Sender<EncoderCommand>,
+) -> anyhow::Result<()> {
+    loop {
+        draw(out, frame, chamber)?;
+
+        if !event::poll(REFRESH_PERIOD)? {
+            continue;
+        }
+
+        if let Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        {
+            match code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('d') => {
+                    let mut chamber = chamber.lock().unwrap();
+                    let connected = !chamber.dut_connected();
+                    chamber.set_dut_connected(connected);
+                }
+                KeyCode::Right | KeyCode::Up => encoder.send(EncoderCommand::Increment)?,
+                KeyCode::Left | KeyCode::Down => encoder.send(EncoderCommand::Decrement)?,
+                KeyCode::Enter | KeyCode::Char(' ') => {
+                    encoder.send(EncoderCommand::Push)?;
+                    thread::sleep(BUTTON_PRESS_TIME);
+                    draw(out, frame, chamber)?;
+                    encoder.send(EncoderCommand::Pull)?;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Два ряда пикселей на строку терминала
+fn draw(
+    out: &mut impl Write,
+    frame: &Mutex<FrameBuffer>,
+    chamber: &Mutex<Chamber>,
+) -> io::Result<()> {
+    let frame = frame.lock().unwrap().clone();
+
+    queue!(
+        out,
+        cursor::MoveTo(0, 0),
+        Print(format!("┌{}┐", "─".repeat(framebuffer::WIDTH)))
+    )?;
+    for row in 0..framebuffer::HEIGHT / 2 {
+        let line: String = (0..framebuffer::WIDTH)
+            .map(
+                |x| match (frame.pixel(x, row * 2), frame.pixel(x, row * 2 + 1)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                },
+            )
+            .collect();
+        queue!(
+            out,
+            cursor::MoveTo(0, row as u16 + 1),
+            Print(format!("│{line}│"))
+        )?;
+    }
+
+    let (p, pumping, isolated, dut) = {
+        let mut chamber = chamber.lock().unwrap();
+        (
+            chamber.pressure(),
+            chamber.pumping(),
+            chamber.isolated(),
+            chamber.dut_connected(),
+        )
+    };
+    queue!(
+        out,
+        cursor::MoveTo(0, framebuffer::HEIGHT as u16 / 2 + 1),
+        Print(format!("└{}┘", "─".repeat(framebuffer::WIDTH))),
+        cursor::MoveTo(0, framebuffer::HEIGHT as u16 / 2 + 2),
+        terminal::Clear(terminal::ClearType::CurrentLine),
+        Print(format!(
+            "Камера: {p:.3} mmHg, F(DUT): {f}, клапан: {valve}",
+            f = if dut {
+                format!("{:.2} Hz", physics::dut_frequency(p, 0))
+            } else {
+                "нет".to_string()
+            },
+            valve = match (isolated, pumping) {
+                (true, _) => "отсечка",
+                (false, true) => "откачка",
+                (false, false) => "атмосфера",
+            }
+        )),
+        cursor::MoveTo(0, framebuffer::HEIGHT as u16 / 2 + 3),
+        Print(HELP),
+    )?;
+
+    out.flush()
+}
@@ -0,0 +1,491 @@
+//! Эталонные снимки экранов: отрисовка в FrameBuffer и сравнение с PBM в `snapshots/`
+//!
+//! ```shell
+//! # проверить
+//! cargo test -p minialfa-sim --test snapshots --target x86_64-unknown-linux-gnu
+//! # обновить эталоны после намеренного изменения экранов
+//! BLESS=1 cargo test -p minialfa-sim --test snapshots --target x86_64-unknown-linux-gnu
+//! ```
+
+use std::{path::PathBuf, time::Duration};
+
+use minialfa_core::{
+    calibration::{CalibrationFit, CalibrationOutcome, WriteError},
+    controller::{
+        CurvePoint, CurveResult, DisplayCommand, Drift, GaugeMenuItem, HoldResult, LeakResult,
+        Parameters, Precission, SelectedParameter,
+    },
+    display::{self, DisplayState},
+    framebuffer::FrameBuffer,
+    i2c_sensor::{Calibration, SctbInfo, SensorInfo},
+    linear_regression::{LinearRegressionResult, RegressionFit},
+    stream::StreamFormat,
+    verdict::{Criterion, Verdict},
+};
+
+fn scenarios() -> Vec<(&'static str, Vec<DisplayCommand>)> {
+    let parameters = Parameters::default();
+    let menu = |values: Parameters, selected| DisplayCommand::SetupMenu {
+        values,
+        selected,
+        precision: Precission::from(values.threshold),
+        profile: "ПРОФИЛЬ1".to_string(),
+        cursor: 0,
+        sctb: Some(vec![15]),
+    };
+
+    let calibration_fit = CalibrationFit {
+        calibration: Calibration {
+            f0: 30012.3,
+            coeffs: [-1.2e-2, 0.249, 3.1e-6, -2.0e-9],
+        },
+        max_error: 0.0123,
+        points: 412,
+    };
+
+    let result = |verdict, dut| DisplayCommand::Result {
+        f: 30002.0,
+        p: 0.5,
+        t: Some(23.5),
+        threashold: parameters.threshold,
+        sensivity: 3.98,
+        fit: Some(RegressionFit {
+            line: LinearRegressionResult {
+                k: 3.98,
+                b: 30000.0,
+            },
+            r2: 0.9998,
+            k_std_err: 0.02,
+            points: 123,
+            rejected: 2,
+        }),
+        hold: Some(HoldResult {
+            settled: true,
+            duration: Duration::from_secs(6),
+        }),
+        verdict,
+        dut,
+    };
+
+    // откачка от атмосферы до порога
+    let pumping = |n: usize| {
+        std::iter::once(DisplayCommand::Measure {
+            f: None,
+            p: None,
+            threashold: parameters.threshold,
+            wait_time: None,
+            drift: None,
+        })
+        .chain((0..n).map(move |i| {
+            let p = 760.0 * 0.8f32.powi(i as i32);
+            DisplayCommand::Measure {
+                f: Some(30000.0 + 4.0 * p),
+                p: Some(p),
+                threashold: parameters.threshold,
+                wait_time: None,
+                drift: None,
+            }
+        }))
+        .collect::<Vec<_>>()
+    };
+
+    vec![
+        (
+            "title_auto",
+            vec![DisplayCommand::TitleScreen {
+                option: "Авто",
+                selected: false,
+                dut: None,
+            }],
+        ),
+        (
+            "title_dut",
+            vec![DisplayCommand::TitleScreen {
+                option: "Авто",
+                selected: false,
+                dut: Some((1, 1)),
+            }],
+        ),
+        (
+            "title_no_dut",
+            vec![DisplayCommand::TitleScreen {
+                option: "Авто",
+                selected: false,
+                dut: Some((0, 1)),
+            }],
+        ),
+        (
+            "title_duts",
+            vec![DisplayCommand::TitleScreen {
+                option: "Авто",
+                selected: false,
+                dut: Some((2, 3)),
+            }],
+        ),
+        (
+            "title_manual_selected",
+            vec![DisplayCommand::TitleScreen {
+                option: "Ручной",
+                selected: true,
+                dut: None,
+            }],
+        ),
+        (
+            "title_setup",
+            vec![DisplayCommand::TitleScreen {
+                option: "Настройки",
+                selected: false,
+                dut: None,
+            }],
+        ),
+        (
+            "profile_select",
+            vec![DisplayCommand::ProfileSelect {
+                name: "ПРОФИЛЬ2".to_string(),
+                index: 1,
+                active: true,
+            }],
+        ),
+        (
+            "menu_profile_name",
+            vec![DisplayCommand::SetupMenu {
+                values: parameters,
+                selected: SelectedParameter::ProfileName,
+                precision: Precission::from(parameters.threshold),
+                profile: "MKS-2   ".to_string(),
+                cursor: 3,
+                sctb: None,
+            }],
+        ),
+        (
+            "menu_threshold",
+            vec![menu(parameters, SelectedParameter::Threshold)],
+        ),
+        (
+            "menu_threshold_small",
+            vec![menu(
+                Parameters {
+                    threshold: 0.05,
+                    ..parameters
+                },
+                SelectedParameter::Threshold,
+            )],
+        ),
+        (
+            "menu_wait_time",
+            vec![menu(parameters, SelectedParameter::WaitTimeS)],
+        ),
+        (
+            "menu_settle_df",
+            vec![menu(
+                Parameters {
+                    settle_enabled: true,
+                    ..parameters
+                },
+                SelectedParameter::SettleDfMax,
+            )],
+        ),
+        (
+            "menu_curve_point",
+            vec![menu(parameters, SelectedParameter::curve_point(1))],
+        ),
+        (
+            "menu_leak_limit",
+            vec![menu(parameters, SelectedParameter::LeakLimit)],
+        ),
+        (
+            "menu_stream",
+            vec![menu(
+                Parameters {
+                    stream_format: StreamFormat::Csv,
+                    ..parameters
+                },
+                SelectedParameter::StreamFormat,
+            )],
+        ),
+        (
+            "menu_reference_gauge",
+            vec![menu(parameters, SelectedParameter::ReferenceGauge)],
+        ),
+        (
+            "menu_save",
+            vec![menu(parameters, SelectedParameter::SaveAndExit)],
+        ),
+        (
+            "diagnostics",
+            vec![DisplayCommand::Diagnostics {
+                dut: Some(SctbInfo {
+                    address: 11,
+                    version: 2,
+                    info: SensorInfo {
+                        serial: 21000011,
+                        firmware: (1, 4),
+                        period_ms: 100,
+                    },
+                    calibration: Calibration {
+                        f0: 30000.0,
+                        coeffs: [0.0, 0.25, -1.5e-6, f32::NAN],
+                    },
+                }),
+                channel: None,
+            }],
+        ),
+        (
+            "diagnostics_no_dut",
+            vec![DisplayCommand::Diagnostics {
+                dut: None,
+                channel: None,
+            }],
+        ),
+        (
+            "diagnostics_dut2_no_answer",
+            vec![DisplayCommand::Diagnostics {
+                dut: None,
+                channel: Some(2),
+            }],
+        ),
+        (
+            "menu_sctb_dut",
+            vec![menu(
+                Parameters {
+                    auto_start: true,
+                    ..parameters
+                },
+                SelectedParameter::SctbDut1,
+            )],
+        ),
+        (
+            "menu_dut_count",
+            vec![menu(
+                Parameters {
+                    dut_count: 3,
+                    ..parameters
+                },
+                SelectedParameter::DutCount,
+            )],
+        ),
+        (
+            "menu_sctb_dut3",
+            vec![menu(
+                Parameters {
+                    dut_count: 3,
+                    ..parameters
+                },
+                SelectedParameter::sctb_dut(2),
+            )],
+        ),
+        (
+            "menu_write_calibration",
+            vec![menu(
+                Parameters {
+                    write_calibration: true,
+                    ..parameters
+                },
+                SelectedParameter::WriteCalibration,
+            )],
+        ),
+        (
+            "calibration_written",
+            vec![DisplayCommand::CalibrationResult {
+                outcome: CalibrationOutcome::Written(calibration_fit),
+                dut: None,
+            }],
+        ),
+        (
+            "calibration_dut2_written",
+            vec![DisplayCommand::CalibrationResult {
+                outcome: CalibrationOutcome::Written(calibration_fit),
+                dut: Some(2),
+            }],
+        ),
+        (
+            "calibration_mismatch",
+            vec![DisplayCommand::CalibrationResult {
+                outcome: CalibrationOutcome::Failed {
+                    fit: calibration_fit,
+                    error: WriteError::Mismatch(calibration_fit.calibration),
+                },
+                dut: None,
+            }],
+        ),
+        (
+            "calibration_no_fit",
+            vec![DisplayCommand::CalibrationResult {
+                outcome: CalibrationOutcome::NoFit,
+                dut: None,
+            }],
+        ),
+        (
+            "gauge_switch_point",
+            vec![DisplayCommand::GaugeSetup {
+                gauge: Some("Thyracont VSP206 @001".to_string()),
+                selected: GaugeMenuItem::SwitchPoint1,
+                editing: true,
+                atmosphere: 1013.0,
+                switch_points: [Some(0.05), None],
+                message: vec![],
+            }],
+        ),
+        (
+            "gauge_info",
+            vec![DisplayCommand::GaugeSetup {
+                gauge: Some("Thyracont VSP206 @001".to_string()),
+                selected: GaugeMenuItem::Info,
+                editing: false,
+                atmosphere: 1013.0,
+                switch_points: [None; 2],
+                message: vec![
+                    "S/N: 12345".to_string(),
+                    "ПО: 2.1".to_string(),
+                    "Наработка ч: 1234".to_string(),
+                    "Единица: mbar".to_string(),
+                ],
+            }],
+        ),
+        ("measure_start", pumping(0)),
+        ("measure_pumping", pumping(20)),
+        ("measure_hold", {
+            let mut cmds = pumping(40);
+            cmds.push(DisplayCommand::Measure {
+                f: Some(30000.5),
+                p: Some(0.5),
+                threashold: parameters.threshold,
+                wait_time: Some(Duration::from_secs(7)),
+                drift: Some(Drift {
+                    df_dt: -0.12,
+                    dp_dt: 0.004,
+                }),
+            });
+            cmds
+        }),
+        ("result", {
+            let mut cmds = pumping(40);
+            cmds.push(result(None, None));
+            cmds
+        }),
+        ("result_pass", vec![result(Some(Verdict::Pass), None)]),
+        (
+            "result_fail",
+            vec![result(Some(Verdict::Fail(Criterion::FAtmosphere)), None)],
+        ),
+        ("result_dut3", vec![result(None, Some(3))]),
+        (
+            "result_dut2_fail",
+            vec![result(Some(Verdict::Fail(Criterion::Sensivity)), Some(2))],
+        ),
+        (
+            "menu_tolerance_f_vac",
+            vec![menu(
+                Parameters {
+                    tolerances_enabled: true,
+                    ..parameters
+                },
+                SelectedParameter::FVacMax,
+            )],
+        ),
+        (
+            "leak_result",
+            vec![DisplayCommand::LeakResult {
+                result: LeakResult {
+                    p_start: 1.0,
+                    p_end: 1.015,
+                    dp_dt: 6.67e-4,
+                    leak_rate: 3.33e-4,
+                    limit: parameters.leak_limit,
+                    passed: true,
+                    duration: Duration::from_secs(30),
+                    fit: None,
+                },
+            }],
+        ),
+    ]
+    .into_iter()
+    .chain((0..=CurveResult::PAGES).map(|page| {
+        let curve = CurveResult::new(
+            [100.0f32, 10.0, 1.0]
+                .iter()
+                .map(|&p| CurvePoint {
+                    p,
+                    f: 30000.0 + 4.0 * p / (1.0 + p / 2000.0),
+                    t: 23.5,
+                    hold: Some(HoldResult {
+                        settled: p > 1.0,
+                        duration: Duration::from_secs(10),
+                    }),
+                })
+                .collect(),
+        );
+        (
+            [
+                "curve_result_points",
+                "curve_result_segments",
+                "curve_result_poly",
+                "curve_result_dut2",
+            ][page],
+            // последний - первая страница второго DUT
+            vec![DisplayCommand::CurveResult {
+                result: curve,
+                page: page % CurveResult::PAGES,
+                dut: (page == CurveResult::PAGES).then_some(2),
+            }],
+        )
+    }))
+    .collect()
+}
+
+fn render(cmds: Vec<DisplayCommand>) -> FrameBuffer {
+    let mut frame = FrameBuffer::new();
+    let mut state = DisplayState::new(&frame);
+    for cmd in cmds {
+        display::draw_frame(&mut frame, cmd, &mut state).unwrap();
+    }
+    frame
+}
+
+#[test]
+fn snapshots() {
+    let bless = std::env::var_os("BLESS").is_some();
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("snapshots");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut failed = Vec::new();
+    for (name, cmds) in scenarios() {
+        let actual = render(cmds);
+        let golden_path = dir.join(format!("{name}.pbm"));
+        let actual_path = dir.join(format!("{name}.actual.pbm"));
+
+        if bless {
+            std::fs::write(&golden_path, actual.to_pbm()).unwrap();
+            let _ = std::fs::remove_file(&actual_path);
+            continue;
+        }
+
+        let golden = std::fs::read(&golden_path)
+            .ok()
+            .and_then(|data| FrameBuffer::from_pbm(&data));
+        match golden {
+            Some(golden) if golden == actual => {
+                let _ = std::fs::remove_file(&actual_path);
+            }
+            Some(golden) => {
+                std::fs::write(&actual_path, actual.to_pbm()).unwrap();
+                println!(
+                    "{name}: {} pixels differ, see {}",
+                    golden.diff(&actual),
+                    actual_path.display()
+                );
+                failed.push(name);
+            }
+            None => {
+                std::fs::write(&actual_path, actual.to_pbm()).unwrap();
+                println!(
+                    "{name}: no golden image {}, run with BLESS=1",
+                    golden_path.display()
+                );
+                failed.push(name);
+            }
+        }
+    }
+
+    assert!(failed.is_empty(), "snapshots failed: {failed:?}");
+}
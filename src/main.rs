@@ -1,20 +1,20 @@
-mod controller;
-mod display;
-mod i2c_sensor;
-mod klapan;
-mod linear_regression;
-mod support;
-mod thyracont_sensor;
-
 use crossbeam::channel::Sender;
 
+use minialfa_core::gauge::PressureGauge;
+use minialfa_core::{
+    calibration, controller, display, gauge, i2c_sensor, klapan, modbus, pfeiffer_sensor, platform,
+    remote, thyracont_sensor,
+};
+
 use esp_idf_hal::gpio::{AnyIOPin, PinDriver};
 use esp_idf_hal::gpio::{InputPin, OutputPin};
 use esp_idf_hal::peripheral::Peripheral;
 use esp_idf_hal::peripherals::Peripherals;
-use esp_idf_svc::nvs::{EspNvs, EspNvsPartition, NvsDefault};
-use esp_idf_svc::timer::EspTimerService;
+use esp_idf_svc::nvs::{EspNvs, EspNvsPartition, NvsDefault, NvsPartitionId};
+use esp_idf_svc::systime::EspSystemTime;
+use esp_idf_svc::timer::{EspTimer, EspTimerService};
 
+use std::sync::{Arc, Condvar, Mutex};
 use std::time::Duration;
 use std::time::Instant;
 
@@ -28,6 +28,7 @@ use esp_idf_hal::uart;
 use thiserror::Error;
 
 use esp_idf_sys as _;
+use esp_idf_sys::EspError;
 
 #[derive(Error, Debug)]
 pub enum FormatError {
@@ -35,6 +36,168 @@ pub enum FormatError {
     EmptyResponce,
 }
 
+/// Системное время ESP-IDF для контроллера
+struct EspClock;
+
+impl platform::Clock for EspClock {
+    fn now(&self) -> Duration {
+        EspSystemTime {}.now()
+    }
+}
+
+/// Настройки контроллера в NVS
+struct NvsStorage<T: NvsPartitionId>(EspNvs<T>);
+
+impl<T: NvsPartitionId> platform::SettingsStorage for NvsStorage<T> {
+    type Error = EspError;
+
+    fn get_u8(&self, key: &str) -> Result<Option<u8>, EspError> {
+        self.0.get_u8(key)
+    }
+
+    fn set_u8(&mut self, key: &str, value: u8) -> Result<(), EspError> {
+        self.0.set_u8(key, value)
+    }
+
+    fn get_u32(&self, key: &str) -> Result<Option<u32>, EspError> {
+        self.0.get_u32(key)
+    }
+
+    fn set_u32(&mut self, key: &str, value: u32) -> Result<(), EspError> {
+        self.0.set_u32(key, value)
+    }
+
+    fn get_str(&self, key: &str) -> Result<Option<String>, EspError> {
+        let mut buf = [0u8; 64];
+        self.0
+            .get_str(key, &mut buf)
+            .map(|v| v.map(|v| v.to_string()))
+    }
+
+    fn set_str(&mut self, key: &str, value: &str) -> Result<(), EspError> {
+        self.0.set_str(key, value)
+    }
+
+    fn get_blob(&self, key: &str) -> Result<Option<Vec<u8>>, EspError> {
+        let mut buf = [0u8; 256];
+        self.0
+            .get_blob(key, &mut buf)
+            .map(|v| v.map(|v| v.to_vec()))
+    }
+
+    fn set_blob(&mut self, key: &str, value: &[u8]) -> Result<(), EspError> {
+        self.0.set_blob(key, value)
+    }
+}
+
+/// Образцовый датчик, общий для таймера опроса и команд настройки
+type SharedGauge = Arc<Mutex<Box<dyn PressureGauge + Send>>>;
+
+/// Датчики SCTB на одной шине I2C: давления и испытуемые
+struct SctbSensors {
+    p_sensor: i2c_sensor::I2CGauge<i2c::I2cDriver<'static>>,
+    duts: Vec<i2c_sensor::I2CSensor>,
+}
+
+/// Период опроса образцовых датчиков, None - опрос остановлен. Поток опроса ждет
+/// изменения на условной переменной.
+type GaugePeriod = Arc<(Mutex<Option<Duration>>, Condvar)>;
+
+/// Таймер опроса датчиков SCTB и поток опроса найденных образцовых датчиков
+struct SensorTimers<'a> {
+    sctb: EspTimer<'a>,
+    sctb_sensors: Arc<Mutex<SctbSensors>>,
+    gauges: GaugePeriod,
+    gauge_handles: Vec<SharedGauge>,
+    gauge_ids: Vec<String>,
+    gauge_stats: Vec<Option<gauge::SharedLinkStats>>,
+}
+
+impl platform::SensorsControl for SensorTimers<'_> {
+    type Error = EspError;
+
+    fn start(&mut self, period: Duration) -> Result<(), EspError> {
+        self.sctb.every(period)?;
+        set_gauge_period(&self.gauges, Some(period));
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), EspError> {
+        self.sctb.cancel()?;
+        set_gauge_period(&self.gauges, None);
+        Ok(())
+    }
+
+    fn gauges(&self) -> &[String] {
+        &self.gauge_ids
+    }
+
+    fn gauge_stats(&self, gauge: usize) -> Option<gauge::LinkStats> {
+        let stats = self.gauge_stats.get(gauge)?.as_ref()?;
+        Some(*stats.lock().unwrap())
+    }
+
+    fn gauge_command(
+        &mut self,
+        gauge: usize,
+        cmd: gauge::GaugeCommand,
+    ) -> Result<gauge::GaugeReply, gauge::GaugeError> {
+        let handle = self
+            .gauge_handles
+            .get(gauge)
+            .ok_or(gauge::GaugeError::Fault)?;
+        handle.lock().unwrap().command(cmd)
+    }
+
+    fn dut_info(&mut self, dut: usize) -> Option<i2c_sensor::SctbInfo> {
+        let mut sctb = self.sctb_sensors.lock().unwrap();
+        let SctbSensors { p_sensor, duts } = &mut *sctb;
+        let dut = duts.get_mut(dut)?;
+        match dut.read_info(p_sensor.bus()) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                println!("Failed to read DUT info at {}: {e:?}", dut.address());
+                None
+            }
+        }
+    }
+
+    fn write_dut_calibration(
+        &mut self,
+        dut: usize,
+        cal: &i2c_sensor::Calibration,
+    ) -> Result<(), calibration::WriteError> {
+        let mut sctb = self.sctb_sensors.lock().unwrap();
+        let SctbSensors { p_sensor, duts } = &mut *sctb;
+        let dut = duts.get_mut(dut).ok_or(calibration::WriteError::Bus)?;
+        calibration::program(dut, p_sensor.bus(), cal)
+    }
+
+    fn scan_sctb(&mut self) -> Option<Vec<i2c_sensor::SctbDevice>> {
+        let mut sctb = self.sctb_sensors.lock().unwrap();
+        Some(i2c_sensor::scan(
+            sctb.p_sensor.bus(),
+            i2c_sensor::SCAN_ADDRESSES,
+        ))
+    }
+
+    fn assign_sctb(&mut self, reference: u8, duts: &[u8]) {
+        let mut sctb = self.sctb_sensors.lock().unwrap();
+        sctb.p_sensor.set_address(reference);
+        // прочитанные период и калибровка сохраняются, если адрес не сменился
+        let mut previous = std::mem::take(&mut sctb.duts);
+        sctb.duts = duts
+            .iter()
+            .map(
+                |&address| match previous.iter().position(|d| d.address() == address) {
+                    Some(i) => previous.swap_remove(i),
+                    None => i2c_sensor::I2CSensor::new(address),
+                },
+            )
+            .collect();
+    }
+}
+
 fn main() {
     // Temporary. Will disappear once ESP-IDF 4.4 is released, but for now it is necessary to call this function once,
     // or else some patches to the runtime implemented by esp-idf-sys might not link properly.
@@ -58,9 +221,12 @@ fn main() {
         Err(e) => panic!("Could't get namespace {:?}", e),
     };
 
-    let mut controller = controller::Controller::new(nvs);
+    let mut controller = controller::Controller::new(NvsStorage(nvs), EspClock);
 
-    let mut klapan = klapan::Klapan::new(PinDriver::output(dp.pins.gpio12).unwrap());
+    let mut klapan = klapan::Klapan::with_isolation(
+        PinDriver::output(dp.pins.gpio12.downgrade_output()).unwrap(),
+        PinDriver::output(dp.pins.gpio27.downgrade_output()).unwrap(),
+    );
 
     println!("Initialising rotary encoder");
     let _encoder = create_encoder(
@@ -73,7 +239,7 @@ fn main() {
     .expect("Failed to create encoder");
 
     println!("Initialising SCTB sensors...");
-    let mut sensors_timer = create_sensors(
+    let (sensors_timer, sctb_sensors) = create_sensors(
         dp.i2c0,
         dp.pins.gpio26,
         dp.pins.gpio25,
@@ -82,7 +248,10 @@ fn main() {
     )
     .expect("Failed to create SCTB sensors");
 
-    println!("Initialising Thyracont Sensor...");
+    // новый образцовый датчик добавляется в этот список
+    let mut gauges: Vec<Box<dyn PressureGauge + Send>> = Vec::new();
+
+    println!("Scanning RS485 for reference gauges...");
     let res = {
         let config = uart::config::Config::new().baudrate(Hertz(9600));
         let uart = uart::UartDriver::new(
@@ -96,19 +265,25 @@ fn main() {
         .unwrap();
         let mut re_de = PinDriver::output(dp.pins.gpio2).unwrap();
         re_de.set_low().unwrap();
-        create_thyracont_sensor(uart, 1, re_de, controller.sensor_chanel(), &timer_service)
+        create_rs485_gauges(uart, re_de)
     };
 
-    let mut thyracont_update_timer = match res {
-        Ok((timer, addr)) => {
-            println!("Thyracont Sensor found at address {addr}!");
-            Some(timer)
-        }
-        Err(e) => {
-            println!("Thyracont Sensor not found: {e}");
-            None
-        }
-    };
+    match res {
+        Ok(found) => gauges.extend(found),
+        Err(e) => println!("Gauge not found: {e}"),
+    }
+    for (index, gauge) in gauges.iter().enumerate() {
+        println!("Gauge {index}: {}", gauge.id());
+    }
+
+    let gauge_ids = gauges.iter().map(|g| g.id()).collect();
+    let gauge_stats = gauges.iter().map(|g| g.link_stats()).collect();
+    let gauge_handles: Vec<SharedGauge> = gauges
+        .into_iter()
+        .map(|g| Arc::new(Mutex::new(g)))
+        .collect();
+    let gauge_period =
+        create_gauges(&gauge_handles, controller.sensor_chanel()).expect("Failed to create gauges");
 
     println!("Initialising display...");
     create_display(
@@ -122,10 +297,39 @@ fn main() {
     )
     .expect("Failed to create display");
 
+    println!("Initialising remote control...");
+    create_remote(
+        dp.uart0,
+        dp.pins.gpio1,
+        dp.pins.gpio3,
+        controller.remote_chanel(),
+    )
+    .expect("Failed to create remote control");
+
+    println!("Initialising Modbus RTU...");
+    create_modbus(
+        dp.uart1,
+        dp.pins.gpio4,
+        dp.pins.gpio35,
+        PinDriver::output(dp.pins.gpio13).unwrap(),
+        modbus::DEFAULT_ADDRESS,
+        controller.modbus_chanel(),
+    )
+    .expect("Failed to create Modbus RTU");
+
+    let mut sensor_timers = SensorTimers {
+        sctb: sensors_timer,
+        sctb_sensors,
+        gauges: gauge_period,
+        gauge_handles,
+        gauge_ids,
+        gauge_stats,
+    };
+
     println!("Ready!");
 
     loop {
-        controller.poll(&mut sensors_timer, &mut thyracont_update_timer, &mut klapan);
+        controller.poll(&mut sensor_timers, &mut klapan);
     }
 }
 
@@ -185,43 +389,53 @@ where
     Ok(timer)
 }
 
+/// Таймер опроса датчиков SCTB и сами датчики для чтения DUT при остановленном опросе.
+/// Адреса - по умолчанию из [`controller::Parameters`], до запуска опроса их переназначает
+/// контроллер по найденным на шине датчикам.
 fn create_sensors<'d, I2C>(
     i2c0: impl Peripheral<P = I2C> + 'static,
     sda: impl Peripheral<P = impl InputPin + OutputPin> + 'static,
     scl: impl Peripheral<P = impl InputPin + OutputPin> + 'static,
     sensor_channel: Sender<controller::SensorResult>,
     timer_svc: &esp_idf_svc::timer::EspTaskTimerService,
-) -> anyhow::Result<esp_idf_svc::timer::EspTimer>
+) -> anyhow::Result<(esp_idf_svc::timer::EspTimer, Arc<Mutex<SctbSensors>>)>
 where
     I2C: i2c::I2c,
 {
-    fn print_read_failed(addr: u8, e: I2cError) {
-        println!("Failed to read I2C sensor at {addr}: {e}");
+    fn print_read_failed(addr: u8, e: i2c_sensor::ReadError<I2cError>) {
+        println!("Failed to read I2C sensor at {addr}: {e:?}");
     }
 
     let config = i2c::I2cConfig::new()
         .baudrate(100.kHz().into())
         .timeout(Duration::from_millis(5).into());
-    let mut i2c = i2c::I2cDriver::new(i2c0, sda, scl, &config)?;
+    let i2c = i2c::I2cDriver::new(i2c0, sda, scl, &config)?;
 
-    let p_sensor = i2c_sensor::I2CSensor::new(15);
-    let f_sensor = i2c_sensor::I2CSensor::new(11);
+    let sensors = Arc::new(Mutex::new(SctbSensors {
+        p_sensor: i2c_sensor::I2CGauge::new(15, i2c),
+        duts: vec![i2c_sensor::I2CSensor::new(11)],
+    }));
 
+    let shared = sensors.clone();
     let timer = timer_svc.timer(move || {
-        let (p, t) = match p_sensor.read(&mut i2c) {
-            Ok(v) => (v.pressure, v.temperature),
-            Err(e) => {
-                print_read_failed(p_sensor.address(), e);
-                (f32::NAN, f32::NAN)
-            }
-        };
-
-        let f = match f_sensor.read(&mut i2c) {
-            Ok(v) => v.f_p,
-            Err(e) => {
-                print_read_failed(f_sensor.address(), e);
-                f32::NAN
+        let (p, f, t) = {
+            let mut sctb = shared.lock().unwrap();
+            let SctbSensors { p_sensor, duts } = &mut *sctb;
+
+            let p = p_sensor
+                .read()
+                .value(gauge::PressureUnit::MmHg)
+                .unwrap_or(f32::NAN);
+            let t = p_sensor.temperature();
+
+            let mut f = [f32::NAN; controller::MAX_DUTS];
+            for (f_dut, dut) in f.iter_mut().zip(duts.iter_mut()) {
+                match dut.read(p_sensor.bus()) {
+                    Ok(v) => *f_dut = v.f_p.unwrap_or(f32::NAN),
+                    Err(e) => print_read_failed(dut.address(), e),
+                }
             }
+            (p, f, t)
         };
 
         let now = Instant::now();
@@ -233,16 +447,15 @@ where
         }
     })?;
 
-    Ok(timer)
+    Ok((timer, sensors))
 }
 
-fn create_thyracont_sensor<P, E, PIN, PINE>(
-    mut serial: P,
-    addr: u8,
-    mut re_de: PIN,
-    sensor_channel: Sender<controller::SensorResult>,
-    timer_svc: &esp_idf_svc::timer::EspTaskTimerService,
-) -> anyhow::Result<(esp_idf_svc::timer::EspTimer, u8)>
+/// Все датчики Thyracont на линии RS485 (например Pirani и пьезо), а если их нет -
+/// датчик Pfeiffer с адресом 1
+fn create_rs485_gauges<P, E, PIN, PINE>(
+    serial: P,
+    re_de: PIN,
+) -> anyhow::Result<Vec<Box<dyn PressureGauge + Send>>>
 where
     P: embedded_hal::serial::Read<u8, Error = E>
         + embedded_hal::serial::Write<u8, Error = E>
@@ -251,34 +464,208 @@ where
     PIN: embedded_hal::digital::v2::OutputPin<Error = PINE> + Send + 'static,
     E: std::error::Error + Send + Sync + 'static,
 {
-    let sensor = thyracont_sensor::TyracontSensor::new(addr);
+    let mut bus = thyracont_sensor::ThyracontBus::new(serial, re_de);
+    let found = bus.scan(thyracont_sensor::ADDRESSES)?;
+    if !found.is_empty() {
+        return Ok(bus
+            .into_gauges(&found)
+            .into_iter()
+            .map(|g| Box::new(g) as Box<dyn PressureGauge + Send>)
+            .collect());
+    }
 
-    match sensor.get_id(&mut serial, &mut re_de) {
-        Ok(Some(id)) => println!("Sensor id: {id}"),
-        Ok(None) => Err(FormatError::EmptyResponce)?,
-        Err(e) => Err(e)?,
+    let (serial, re_de) = bus.release();
+    let mut pfeiffer = pfeiffer_sensor::PfeifferGauge::new(1, serial, re_de);
+    if pfeiffer.probe()? {
+        return Ok(vec![Box::new(pfeiffer)]);
     }
 
-    let timer = timer_svc.timer(move || {
-        let p = match sensor.read(&mut serial, &mut re_de) {
-            Ok(Some(v)) => v,
-            Ok(None) => return,
-            Err(e) => {
-                println!("Failed to read TyracontSensor: {e}");
-                return;
+    Err(FormatError::EmptyResponce.into())
+}
+
+/// Поток опроса образцовых датчиков по очереди, в порядке списка. Ответа по RS485
+/// драйвер ждет до сотен ms (нет датчика - все попытки до таймаута), поэтому опрос
+/// идет не из таймера.
+fn create_gauges(
+    gauges: &[SharedGauge],
+    sensor_channel: Sender<controller::SensorResult>,
+) -> anyhow::Result<GaugePeriod> {
+    let period = GaugePeriod::default();
+    let gauges = gauges.to_vec();
+    let shared = period.clone();
+
+    std::thread::Builder::new()
+        .stack_size(4 * 1024)
+        .name("Gauges".to_string())
+        .spawn(move || {
+            let (lock, changed) = &*shared;
+            loop {
+                let period = {
+                    let period = lock.lock().unwrap();
+                    let period = changed.wait_while(period, |p| p.is_none()).unwrap();
+                    period.unwrap()
+                };
+                let start = Instant::now();
+
+                for (index, gauge) in gauges.iter().enumerate() {
+                    let reading = gauge.lock().unwrap().read();
+
+                    let now = Instant::now();
+                    if let Err(e) = sensor_channel.send_deadline(
+                        controller::SensorResult::GaugeResult {
+                            gauge: index,
+                            reading,
+                        },
+                        now + Duration::from_millis(1),
+                    ) {
+                        println!("Failed to send gauge result: {e}");
+                    }
+                }
+
+                // остановка прерывает ожидание следующего опроса
+                let period_left = period.saturating_sub(start.elapsed());
+                let _ = changed
+                    .wait_timeout_while(lock.lock().unwrap(), period_left, |p| p.is_some())
+                    .unwrap();
             }
-        };
+        })?;
 
-        let now = Instant::now();
-        if let Err(e) = sensor_channel.send_deadline(
-            controller::SensorResult::TyracontSensorResult { p },
-            now + Duration::from_millis(1),
-        ) {
-            println!("Failed to send TyracontSensor sensor result: {e}");
-        }
-    })?;
+    Ok(period)
+}
+
+fn set_gauge_period(gauges: &GaugePeriod, period: Option<Duration>) {
+    let (lock, changed) = &**gauges;
+    *lock.lock().unwrap() = period;
+    changed.notify_all();
+}
 
-    Ok((timer, addr))
+/// Команды управления по консольному UART0, см. [`remote`]. Отладочный вывод
+/// `println!` идет в тот же порт, ответы и поток отличаются обрамлением [`remote::frame`].
+fn create_remote<UART: uart::Uart>(
+    uart0: impl Peripheral<P = UART> + 'static,
+    tx: impl Peripheral<P = impl OutputPin> + 'static,
+    rx: impl Peripheral<P = impl InputPin> + 'static,
+    (remote_channel, replies): (Sender<String>, crossbeam::channel::Receiver<String>),
+) -> anyhow::Result<()> {
+    let config = uart::config::Config::new().baudrate(Hertz(115200));
+    let uart = uart::UartDriver::new(
+        uart0,
+        tx,
+        rx,
+        Option::<AnyIOPin>::None,
+        Option::<AnyIOPin>::None,
+        &config,
+    )?;
+
+    std::thread::Builder::new()
+        .stack_size(4 * 1024)
+        .name("Remote".to_string())
+        .spawn(move || {
+            let mut line = remote::LineBuffer::new();
+            let mut buf = [0u8; 32];
+            loop {
+                let n = uart.read(&mut buf, 10).unwrap_or(0);
+                for &b in &buf[..n] {
+                    match line.push(b) {
+                        Some(Ok(cmd)) => {
+                            if let Err(e) = remote_channel.send(cmd) {
+                                println!("Failed to send remote command: {e}");
+                            }
+                        }
+                        Some(Err(e)) => println!("{}", remote::frame(&remote::error(&e))),
+                        None => {}
+                    }
+                }
+
+                while let Ok(reply) = replies.try_recv() {
+                    println!("{}", remote::frame(&reply));
+                }
+            }
+        })?;
+
+    Ok(())
+}
+
+/// Modbus RTU slave на UART1 через драйвер RS485, см. [`modbus`]
+fn create_modbus<UART: uart::Uart, DE: OutputPin>(
+    uart1: impl Peripheral<P = UART> + 'static,
+    tx: impl Peripheral<P = impl OutputPin> + 'static,
+    rx: impl Peripheral<P = impl InputPin> + 'static,
+    mut de: PinDriver<'static, DE, esp_idf_hal::gpio::Output>,
+    address: u8,
+    modbus_channel: Sender<modbus::Transaction>,
+) -> anyhow::Result<()> {
+    // контроллер может быть занят отрисовкой, мастер ждет ответа дольше
+    const REPLY_TIMEOUT: Duration = Duration::from_millis(200);
+
+    let config = uart::config::Config::new().baudrate(Hertz(9600));
+    let mut uart = uart::UartDriver::new(
+        uart1,
+        tx,
+        rx,
+        Option::<AnyIOPin>::None,
+        Option::<AnyIOPin>::None,
+        &config,
+    )?;
+    de.set_low()?;
+
+    std::thread::Builder::new()
+        .stack_size(4 * 1024)
+        .name("Modbus".to_string())
+        .spawn(move || {
+            let mut frame = Vec::with_capacity(modbus::MAX_FRAME);
+            let mut buf = [0u8; 64];
+            loop {
+                // тишина дольше 3.5 символов (4 ms на 9600) - конец кадра
+                let n = uart.read(&mut buf, 2).unwrap_or(0);
+                if n > 0 {
+                    // длинный кадр обрезается и не проходит CRC
+                    if frame.len() + n <= modbus::MAX_FRAME {
+                        frame.extend_from_slice(&buf[..n]);
+                    }
+                    continue;
+                }
+                if frame.is_empty() {
+                    continue;
+                }
+
+                let reply = match modbus::decode_request(&frame) {
+                    Ok((to, request)) if to == address || to == 0 => {
+                        let function = request.function();
+                        let (reply_tx, reply_rx) = crossbeam::channel::bounded(1);
+                        let response = modbus_channel
+                            .send_timeout((request, reply_tx), REPLY_TIMEOUT)
+                            .ok()
+                            .and_then(|_| reply_rx.recv_timeout(REPLY_TIMEOUT).ok())
+                            .unwrap_or(Err(modbus::Exception::SlaveDeviceBusy));
+                        // на широковещательный запрос не отвечают
+                        (to != 0).then(|| modbus::encode_response(address, function, &response))
+                    }
+                    Err(modbus::DecodeError::Unsupported {
+                        address: to,
+                        function,
+                        exception,
+                    }) if to == address => {
+                        Some(modbus::encode_response(address, function, &Err(exception)))
+                    }
+                    _ => None,
+                };
+                frame.clear();
+
+                if let Some(reply) = reply {
+                    let _ = de.set_high();
+                    // DE снимается только после ухода последнего байта
+                    let sent = uart.write(&reply).is_ok()
+                        && nb::block!(embedded_hal::serial::Write::flush(&mut uart)).is_ok();
+                    let _ = de.set_low();
+                    if !sent {
+                        println!("Failed to send Modbus reply");
+                    }
+                }
+            }
+        })?;
+
+    Ok(())
 }
 
 fn create_display<'d, SPI, DC, RESET, E>(